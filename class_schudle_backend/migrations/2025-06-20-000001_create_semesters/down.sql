@@ -0,0 +1,43 @@
+-- 删除课程的学期关联（SQLite 无法直接删除带外键的列，需重建课程表）
+DROP INDEX IF EXISTS idx_courses_semester_id;
+DROP TRIGGER IF EXISTS update_courses_updated_at;
+DROP INDEX IF EXISTS idx_courses_created_at;
+DROP INDEX IF EXISTS idx_courses_start_time;
+DROP INDEX IF EXISTS idx_courses_weekday;
+
+CREATE TABLE courses_old (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    teacher TEXT,
+    location TEXT,
+    weekday INTEGER NOT NULL CHECK (weekday >= 1 AND weekday <= 7),
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    weeks TEXT NOT NULL, -- JSON 数组存储周次信息
+    color TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO courses_old (id, name, teacher, location, weekday, start_time, end_time, weeks, color, created_at, updated_at)
+SELECT id, name, teacher, location, weekday, start_time, end_time, weeks, color, created_at, updated_at FROM courses;
+
+DROP TABLE courses;
+ALTER TABLE courses_old RENAME TO courses;
+
+CREATE INDEX idx_courses_weekday ON courses(weekday);
+CREATE INDEX idx_courses_start_time ON courses(start_time);
+CREATE INDEX idx_courses_created_at ON courses(created_at);
+
+CREATE TRIGGER update_courses_updated_at
+    AFTER UPDATE ON courses
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE courses SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+-- 删除学期表
+DROP TRIGGER IF EXISTS update_semesters_updated_at;
+DROP INDEX IF EXISTS idx_semesters_is_active;
+DROP TABLE IF EXISTS semesters;
@@ -0,0 +1,26 @@
+-- 创建学期表
+CREATE TABLE semesters (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    start_date DATE NOT NULL, -- 学期第一周内的任意一天，按所在周的周一对齐
+    total_weeks INTEGER NOT NULL CHECK (total_weeks >= 1),
+    is_active BOOLEAN NOT NULL DEFAULT 0,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_semesters_is_active ON semesters(is_active);
+
+-- 创建触发器自动更新 updated_at 字段
+CREATE TRIGGER update_semesters_updated_at
+    AFTER UPDATE ON semesters
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE semesters SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+-- 课程关联学期
+ALTER TABLE courses ADD COLUMN semester_id TEXT REFERENCES semesters(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_courses_semester_id ON courses(semester_id);
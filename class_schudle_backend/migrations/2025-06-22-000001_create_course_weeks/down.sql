@@ -0,0 +1,20 @@
+-- 恢复 courses.weeks（JSON 数组）列
+ALTER TABLE courses ADD COLUMN weeks TEXT NOT NULL DEFAULT '[]';
+
+UPDATE courses SET weeks = (
+    SELECT COALESCE(json_group_array(week), '[]')
+    FROM (SELECT week FROM course_weeks WHERE course_weeks.course_id = courses.id ORDER BY week)
+);
+
+-- 按第 1-16 周迁移的课程，周次未修改过时恢复原来的 weeks
+UPDATE courses SET weeks = (
+    SELECT weeks FROM course_weeks_legacy WHERE course_weeks_legacy.course_id = courses.id
+)
+WHERE id IN (SELECT course_id FROM course_weeks_legacy)
+  AND weeks = '[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16]';
+
+DROP TABLE IF EXISTS course_weeks_legacy;
+
+-- 删除课程周次表
+DROP INDEX IF EXISTS idx_course_weeks_week;
+DROP TABLE IF EXISTS course_weeks;
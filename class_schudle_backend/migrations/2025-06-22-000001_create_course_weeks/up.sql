@@ -0,0 +1,83 @@
+-- 将课程周次从 courses.weeks（JSON 数组）拆分到 course_weeks 表
+-- 先取出现有周次，无法解析的 JSON 和非正整数周次被丢弃
+CREATE TABLE course_weeks_migration AS
+SELECT DISTINCT courses.id AS course_id, CAST(weeks.value AS INTEGER) AS week
+FROM courses, json_each(courses.weeks) AS weeks
+WHERE json_valid(courses.weeks)
+  AND weeks.type = 'integer'
+  AND weeks.value >= 1;
+
+-- 一个周次都取不到的课程（JSON 无法解析或没有正整数周次）按第 1-16 周迁移，避免从所有周视图中消失
+-- 这些课程原来的 weeks 保存在 course_weeks_legacy 中，回滚时原样恢复
+CREATE TABLE course_weeks_legacy (
+    course_id TEXT PRIMARY KEY NOT NULL,
+    weeks TEXT NOT NULL
+);
+
+INSERT INTO course_weeks_legacy (course_id, weeks)
+SELECT id, weeks FROM courses
+WHERE id NOT IN (SELECT course_id FROM course_weeks_migration);
+
+WITH RECURSIVE default_weeks(week) AS (
+    SELECT 1
+    UNION ALL
+    SELECT week + 1 FROM default_weeks WHERE week < 16
+)
+INSERT INTO course_weeks_migration (course_id, week)
+SELECT course_weeks_legacy.course_id, default_weeks.week
+FROM course_weeks_legacy, default_weeks;
+
+-- 重建课程表以删除 weeks 列
+DROP INDEX IF EXISTS idx_courses_semester_id;
+DROP TRIGGER IF EXISTS update_courses_updated_at;
+DROP INDEX IF EXISTS idx_courses_created_at;
+DROP INDEX IF EXISTS idx_courses_start_time;
+DROP INDEX IF EXISTS idx_courses_weekday;
+
+CREATE TABLE courses_new (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    teacher TEXT,
+    location TEXT,
+    weekday INTEGER NOT NULL CHECK (weekday >= 1 AND weekday <= 7),
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    color TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    semester_id TEXT REFERENCES semesters(id) ON DELETE SET NULL
+);
+
+INSERT INTO courses_new (id, name, teacher, location, weekday, start_time, end_time, color, created_at, updated_at, semester_id)
+SELECT id, name, teacher, location, weekday, start_time, end_time, color, created_at, updated_at, semester_id FROM courses;
+
+DROP TABLE courses;
+ALTER TABLE courses_new RENAME TO courses;
+
+CREATE INDEX idx_courses_weekday ON courses(weekday);
+CREATE INDEX idx_courses_start_time ON courses(start_time);
+CREATE INDEX idx_courses_created_at ON courses(created_at);
+CREATE INDEX idx_courses_semester_id ON courses(semester_id);
+
+CREATE TRIGGER update_courses_updated_at
+    AFTER UPDATE ON courses
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE courses SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+-- 创建课程周次表
+CREATE TABLE course_weeks (
+    course_id TEXT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    week INTEGER NOT NULL CHECK (week >= 1),
+    PRIMARY KEY (course_id, week)
+);
+
+-- 按周次查询课程
+CREATE INDEX idx_course_weeks_week ON course_weeks(week);
+
+INSERT INTO course_weeks (course_id, week)
+SELECT course_id, week FROM course_weeks_migration;
+
+DROP TABLE course_weeks_migration;
@@ -0,0 +1,9 @@
+-- 删除课程的节次字段
+ALTER TABLE courses DROP COLUMN end_period;
+ALTER TABLE courses DROP COLUMN start_period;
+ALTER TABLE courses DROP COLUMN campus;
+
+-- 删除节次表
+DROP TRIGGER IF EXISTS update_periods_updated_at;
+DROP INDEX IF EXISTS idx_periods_variant;
+DROP TABLE IF EXISTS periods;
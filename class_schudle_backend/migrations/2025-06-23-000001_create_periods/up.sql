@@ -0,0 +1,28 @@
+-- 创建节次表（作息时间），可按学期、校区设置不同的作息
+CREATE TABLE periods (
+    id TEXT PRIMARY KEY NOT NULL,
+    semester_id TEXT REFERENCES semesters(id) ON DELETE CASCADE, -- 为空表示适用于所有学期
+    campus TEXT, -- 为空表示适用于所有校区
+    period_index INTEGER NOT NULL CHECK (period_index >= 1), -- 第几节
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 同一学期、校区下每个节次只能有一条记录（空值视为同一取值）
+CREATE UNIQUE INDEX idx_periods_variant ON periods(COALESCE(semester_id, ''), COALESCE(campus, ''), period_index);
+
+-- 创建触发器自动更新 updated_at 字段
+CREATE TRIGGER update_periods_updated_at
+    AFTER UPDATE ON periods
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE periods SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+-- 课程可按节次定义，此时 start_time/end_time 由节次表推导
+ALTER TABLE courses ADD COLUMN campus TEXT;
+ALTER TABLE courses ADD COLUMN start_period INTEGER CHECK (start_period >= 1);
+ALTER TABLE courses ADD COLUMN end_period INTEGER CHECK (end_period >= start_period);
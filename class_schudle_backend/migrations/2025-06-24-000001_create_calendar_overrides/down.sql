@@ -0,0 +1,3 @@
+-- 删除日历调整表
+DROP TRIGGER IF EXISTS update_calendar_overrides_updated_at;
+DROP TABLE IF EXISTS calendar_overrides;
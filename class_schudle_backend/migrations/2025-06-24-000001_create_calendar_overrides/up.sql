@@ -0,0 +1,20 @@
+-- 创建日历调整表（节假日停课、调休补课）
+CREATE TABLE calendar_overrides (
+    id TEXT PRIMARY KEY NOT NULL,
+    date DATE NOT NULL UNIQUE,
+    kind TEXT NOT NULL CHECK (kind IN ('cancelled', 'swap', 'custom')),
+    weekday INTEGER CHECK (weekday >= 1 AND weekday <= 7), -- swap/custom: 按星期几的课表上课
+    week INTEGER CHECK (week >= 1), -- custom: 按第几周的课表上课
+    note TEXT, -- 如 "国庆节"、"国庆调休"
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 创建触发器自动更新 updated_at 字段
+CREATE TRIGGER update_calendar_overrides_updated_at
+    AFTER UPDATE ON calendar_overrides
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE calendar_overrides SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
@@ -0,0 +1,3 @@
+-- 删除课程单次调整表
+DROP TRIGGER IF EXISTS update_course_exceptions_updated_at;
+DROP TABLE IF EXISTS course_exceptions;
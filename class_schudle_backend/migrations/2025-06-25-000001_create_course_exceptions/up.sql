@@ -0,0 +1,24 @@
+-- 创建课程单次调整表（某一周停课一次、临时换教室或换时间）
+CREATE TABLE course_exceptions (
+    id TEXT PRIMARY KEY NOT NULL,
+    course_id TEXT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    week INTEGER NOT NULL CHECK (week >= 1), -- 调整的是第几周的那次课
+    action TEXT NOT NULL CHECK (action IN ('cancel', 'reschedule')),
+    weekday INTEGER CHECK (weekday >= 1 AND weekday <= 7), -- reschedule: 改到同一周的星期几
+    start_time TEXT, -- reschedule: 新的上课时间，格式 "HH:MM:SS"
+    end_time TEXT,
+    location TEXT, -- reschedule: 新的上课地点
+    note TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (course_id, week)
+);
+
+-- 创建触发器自动更新 updated_at 字段
+CREATE TRIGGER update_course_exceptions_updated_at
+    AFTER UPDATE ON course_exceptions
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE course_exceptions SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
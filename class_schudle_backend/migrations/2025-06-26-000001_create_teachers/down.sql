@@ -0,0 +1,7 @@
+-- 删除课程的教师关联，courses.teacher 中的姓名保留
+DROP INDEX IF EXISTS idx_courses_teacher_id;
+ALTER TABLE courses DROP COLUMN teacher_id;
+
+-- 删除教师表
+DROP TRIGGER IF EXISTS update_teachers_updated_at;
+DROP TABLE IF EXISTS teachers;
@@ -0,0 +1,50 @@
+-- 创建教师表
+CREATE TABLE teachers (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    email TEXT,
+    phone TEXT,
+    office TEXT,
+    office_hours TEXT, -- 如 "周二 14:00-16:00"
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 创建触发器自动更新 updated_at 字段
+CREATE TRIGGER update_teachers_updated_at
+    AFTER UPDATE ON teachers
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE teachers SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+-- 课程关联教师，courses.teacher 保留为教师姓名
+ALTER TABLE courses ADD COLUMN teacher_id TEXT REFERENCES teachers(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_courses_teacher_id ON courses(teacher_id);
+
+-- 由已有的教师姓名生成教师记录：忽略空白和大小写后相同的姓名视为同一位教师，取最短的写法作为姓名
+INSERT INTO teachers (id, name)
+SELECT
+    lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4' ||
+    substr(lower(hex(randomblob(2))), 2) || '-' ||
+    substr('89ab', 1 + (abs(random()) % 4), 1) || substr(lower(hex(randomblob(2))), 2) || '-' ||
+    lower(hex(randomblob(6))),
+    name
+FROM (
+    SELECT TRIM(teacher) AS name, MIN(length(TRIM(teacher)))
+    FROM courses
+    WHERE TRIM(REPLACE(teacher, '　', '')) <> ''
+    GROUP BY lower(REPLACE(REPLACE(REPLACE(teacher, ' ', ''), '　', ''), char(9), ''))
+);
+
+UPDATE courses SET teacher_id = (
+    SELECT teachers.id FROM teachers
+    WHERE lower(REPLACE(REPLACE(REPLACE(teachers.name, ' ', ''), '　', ''), char(9), ''))
+        = lower(REPLACE(REPLACE(REPLACE(courses.teacher, ' ', ''), '　', ''), char(9), ''))
+)
+WHERE teacher IS NOT NULL;
+
+UPDATE courses SET teacher = (SELECT teachers.name FROM teachers WHERE teachers.id = courses.teacher_id)
+WHERE teacher_id IS NOT NULL;
@@ -0,0 +1,8 @@
+-- 删除课程的教室关联，courses.location 中的名称保留
+DROP INDEX IF EXISTS idx_courses_room_id;
+ALTER TABLE courses DROP COLUMN room_id;
+
+-- 删除教室表
+DROP TRIGGER IF EXISTS update_rooms_updated_at;
+DROP INDEX IF EXISTS idx_rooms_identity;
+DROP TABLE IF EXISTS rooms;
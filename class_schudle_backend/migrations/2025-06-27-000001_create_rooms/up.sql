@@ -0,0 +1,27 @@
+-- 创建教室表
+CREATE TABLE rooms (
+    id TEXT PRIMARY KEY NOT NULL,
+    building TEXT NOT NULL,
+    room_number TEXT NOT NULL,
+    capacity INTEGER CHECK (capacity >= 1),
+    campus TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 同一校区的同一栋楼中教室编号唯一（空校区视为同一取值）
+CREATE UNIQUE INDEX idx_rooms_identity ON rooms(COALESCE(campus, ''), building, room_number);
+
+-- 创建触发器自动更新 updated_at 字段
+CREATE TRIGGER update_rooms_updated_at
+    AFTER UPDATE ON rooms
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE rooms SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+-- 课程关联教室，courses.location 保留为教室名称
+ALTER TABLE courses ADD COLUMN room_id TEXT REFERENCES rooms(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_courses_room_id ON courses(room_id);
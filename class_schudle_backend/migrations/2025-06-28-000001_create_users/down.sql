@@ -0,0 +1,9 @@
+-- 删除课程的用户归属
+DROP INDEX IF EXISTS idx_courses_owner_id;
+ALTER TABLE courses DROP COLUMN owner_id;
+
+-- 删除会话表和用户表
+DROP INDEX IF EXISTS idx_user_sessions_user_id;
+DROP TABLE IF EXISTS user_sessions;
+DROP TRIGGER IF EXISTS update_users_updated_at;
+DROP TABLE IF EXISTS users;
@@ -0,0 +1,9 @@
+-- 删除课程的课表归属
+DROP INDEX IF EXISTS idx_courses_timetable_id;
+ALTER TABLE courses DROP COLUMN timetable_id;
+
+-- 删除课表表
+DROP TRIGGER IF EXISTS update_timetables_updated_at;
+DROP INDEX IF EXISTS idx_timetables_default;
+DROP INDEX IF EXISTS idx_timetables_owner_id;
+DROP TABLE IF EXISTS timetables;
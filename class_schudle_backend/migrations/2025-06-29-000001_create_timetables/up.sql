@@ -0,0 +1,45 @@
+-- 创建课表表，每个用户（包括未登录时的匿名用户）可以有多张课表，其中一张为默认课表
+CREATE TABLE timetables (
+    id TEXT PRIMARY KEY NOT NULL,
+    owner_id TEXT REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    is_default BOOLEAN NOT NULL DEFAULT 0,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_timetables_owner_id ON timetables(owner_id);
+
+-- 每个用户最多一张默认课表（空 owner_id 视为同一用户）
+CREATE UNIQUE INDEX idx_timetables_default ON timetables(COALESCE(owner_id, '')) WHERE is_default;
+
+-- 创建触发器自动更新 updated_at 字段
+CREATE TRIGGER update_timetables_updated_at
+    AFTER UPDATE ON timetables
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE timetables SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+-- 课程属于某张课表，删除课表时课程一并删除
+ALTER TABLE courses ADD COLUMN timetable_id TEXT REFERENCES timetables(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_courses_timetable_id ON courses(timetable_id);
+
+-- 为匿名用户和每个已注册用户创建默认课表，已有课程归入所属用户的默认课表
+INSERT INTO timetables (id, owner_id, name, is_default)
+SELECT
+    lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4' ||
+    substr(lower(hex(randomblob(2))), 2) || '-' ||
+    substr('89ab', 1 + (abs(random()) % 4), 1) || substr(lower(hex(randomblob(2))), 2) || '-' ||
+    lower(hex(randomblob(6))),
+    owner_id,
+    '默认课表',
+    1
+FROM (SELECT NULL AS owner_id UNION ALL SELECT id FROM users);
+
+UPDATE courses SET timetable_id = (
+    SELECT timetables.id FROM timetables
+    WHERE timetables.is_default AND timetables.owner_id IS courses.owner_id
+);
@@ -0,0 +1,4 @@
+-- 删除分享链接表
+DROP INDEX IF EXISTS idx_share_links_timetable_id;
+DROP INDEX IF EXISTS idx_share_links_owner_id;
+DROP TABLE IF EXISTS share_links;
@@ -0,0 +1,13 @@
+-- 课表只读分享链接，撤销即删除
+-- 令牌需要在分享列表中再次展示，因此与登录令牌不同，保存原文
+CREATE TABLE share_links (
+    id TEXT PRIMARY KEY NOT NULL,
+    token TEXT NOT NULL UNIQUE,
+    owner_id TEXT REFERENCES users(id) ON DELETE CASCADE,
+    timetable_id TEXT NOT NULL REFERENCES timetables(id) ON DELETE CASCADE,
+    expires_at DATETIME, -- 为空时永不过期
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_share_links_owner_id ON share_links(owner_id);
+CREATE INDEX idx_share_links_timetable_id ON share_links(timetable_id);
@@ -0,0 +1,3 @@
+-- 删除课程提醒相关表
+DROP TABLE IF EXISTS reminder_deliveries;
+DROP TABLE IF EXISTS course_reminders;
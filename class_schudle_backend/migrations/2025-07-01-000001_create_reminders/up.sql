@@ -0,0 +1,16 @@
+-- 单门课程的提醒设置，没有记录的课程按全局提前时间提醒
+CREATE TABLE course_reminders (
+    course_id TEXT PRIMARY KEY NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    minutes_before INTEGER, -- 为空时使用全局提前时间
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 已发送提醒的上课时间，重启后不会重复提醒
+CREATE TABLE reminder_deliveries (
+    course_id TEXT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    starts_at DATETIME NOT NULL,
+    notified_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (course_id, starts_at)
+);
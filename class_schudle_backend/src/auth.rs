@@ -0,0 +1,178 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::future::{ready, Ready};
+
+use crate::errors::ApiError;
+use crate::models::{CourseScope, User};
+use crate::storage::{self, Repository};
+
+// 登录会话的有效期
+pub const SESSION_DAYS: i64 = 30;
+
+// 认证配置：REQUIRE_AUTH=true 时所有接口（注册、登录除外）都必须携带令牌，
+// 否则未携带令牌的请求访问不属于任何用户的课程，与多用户之前的行为一致
+// ADMIN_USERS 为逗号分隔的用户名，只有这些用户可以修改学期、教师、教室和节次
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    pub required: bool,
+    pub admins: Vec<String>,
+}
+
+impl AuthConfig {
+    pub fn from_env() -> Self {
+        let required = matches!(env::var("REQUIRE_AUTH").as_deref(), Ok("true") | Ok("1"));
+        let admins = env::var("ADMIN_USERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+        Self { required, admins }
+    }
+
+    // 学期、教师、教室和节次由所有用户共用，普通用户只读
+    // 未配置管理员且不强制登录时（单用户部署），未登录的请求可以修改
+    pub fn can_manage_registries(&self, user: &CurrentUser) -> bool {
+        match &user.0 {
+            Some(user) => self
+                .admins
+                .iter()
+                .any(|admin| admin.eq_ignore_ascii_case(&user.username)),
+            None => !self.required && self.admins.is_empty(),
+        }
+    }
+}
+
+// 使用 argon2id 默认参数和随机盐生成 PHC 格式的密码摘要
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+// 256 位随机令牌，十六进制编码
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+// 数据库中只保存令牌的 SHA-256 摘要，数据库泄露时令牌不可直接使用
+pub fn token_hash(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 从 "Authorization: Bearer <token>" 中取出令牌
+pub fn bearer_token(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+// 不需要登录即可访问的接口，分享链接凭令牌只读访问
+fn is_public(path: &str) -> bool {
+    path.ends_with("/auth/register") || path.ends_with("/auth/login") || path.contains("/shared/")
+}
+
+// 认证中间件：携带令牌时校验会话，并将用户放入请求扩展供 CurrentUser 取用
+// 令牌无效或已过期时返回 401，不会退回到未登录状态
+// 错误直接转换为响应返回，而不是作为 Err 向外传递，这样外层的 CORS 中间件仍会加上跨域响应头
+pub async fn authenticate<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    match resolve_user(&req).await {
+        Ok(Some(user)) => {
+            debug!("🔑 已认证用户: {} (ID: {})", user.username, user.id);
+            req.extensions_mut().insert(user);
+        }
+        Ok(None) => {}
+        Err(e) => return Ok(req.error_response(e).map_into_right_body()),
+    }
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+async fn resolve_user(req: &ServiceRequest) -> Result<Option<User>, ApiError> {
+    let required = req
+        .app_data::<web::Data<AuthConfig>>()
+        .is_some_and(|config| config.required);
+
+    let Some(token) = bearer_token(req.request()) else {
+        if required && !is_public(req.path()) {
+            warn!("⚠️ 未登录访问: {}", req.path());
+            return Err(ApiError::Unauthorized(
+                "Authentication required".to_string(),
+            ));
+        }
+        return Ok(None);
+    };
+
+    let repo = req
+        .app_data::<Repository>()
+        .cloned()
+        .ok_or_else(|| ApiError::Internal("Storage not configured".to_string()))?;
+    let hash = token_hash(&token);
+    let user = storage::run(&repo, move |repo| repo.get_session_user(&hash))
+        .await?
+        .ok_or_else(|| {
+            warn!("⚠️ 登录令牌无效或已过期: {}", req.path());
+            ApiError::Unauthorized("Invalid or expired token".to_string())
+        })?;
+    Ok(Some(user))
+}
+
+// 当前登录用户，未登录时为空
+#[derive(Debug, Clone)]
+pub struct CurrentUser(pub Option<User>);
+
+impl CurrentUser {
+    // 课程的归属用户 ID，未登录时访问不属于任何用户的课程
+    pub fn owner_id(&self) -> Option<String> {
+        self.0.as_ref().map(|user| user.id.clone())
+    }
+
+    // 当前用户全部课表的课程，按 ID 访问课程和课程单次调整时使用
+    pub fn scope(&self) -> CourseScope {
+        CourseScope {
+            owner_id: self.owner_id(),
+            timetable_id: None,
+        }
+    }
+}
+
+impl FromRequest for CurrentUser {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(CurrentUser(req.extensions().get::<User>().cloned())))
+    }
+}
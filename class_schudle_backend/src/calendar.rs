@@ -0,0 +1,338 @@
+use chrono::{Datelike, NaiveDate, NaiveTime};
+
+use crate::models::{
+    parse_time, CalendarOverride, CommonFreeSlot, CourseException, CourseResponse, ExceptionAction,
+    FreeSlot, OverrideKind, Semester,
+};
+use crate::week_rule::format_week_rule;
+
+// 某一天实际按第几周、星期几的课表上课，停课时返回 None
+pub fn schedule_slot(
+    semester: &Semester,
+    date: NaiveDate,
+    calendar_override: Option<&CalendarOverride>,
+) -> Option<(i32, i32)> {
+    let week = semester.week_of(date);
+    let weekday = date.weekday().number_from_monday() as i32;
+
+    let Some(calendar_override) = calendar_override else {
+        return Some((week, weekday));
+    };
+    match OverrideKind::from_db(&calendar_override.kind) {
+        Some(OverrideKind::Cancelled) => None,
+        Some(OverrideKind::Swap) => Some((week, calendar_override.weekday.unwrap_or(weekday))),
+        Some(OverrideKind::Custom) => Some((
+            calendar_override.week.unwrap_or(week),
+            calendar_override.weekday.unwrap_or(weekday),
+        )),
+        None => Some((week, weekday)),
+    }
+}
+
+// 未指定学期的课程属于当前学期
+pub fn in_semester(course: &CourseResponse, semester: &Semester) -> bool {
+    match &course.semester_id {
+        Some(semester_id) => semester_id == &semester.id,
+        None => semester.is_active,
+    }
+}
+
+// 学期第 week 周星期 weekday 的课程，按开始时间排序
+pub fn courses_on(
+    courses: &[CourseResponse],
+    semester: &Semester,
+    week: i32,
+    weekday: i32,
+) -> Vec<CourseResponse> {
+    if !semester.contains_week(week) {
+        return Vec::new();
+    }
+
+    let mut day_courses: Vec<CourseResponse> = courses
+        .iter()
+        .filter(|c| in_semester(c, semester))
+        .filter(|c| c.weekday == weekday && c.weeks.contains(&week))
+        .cloned()
+        .collect();
+    day_courses.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+    day_courses
+}
+
+// 应用单次调整后的课程：改星期、改时间或换教室，未指定的沿用课程本身
+pub fn apply_exception(course: &CourseResponse, exception: &CourseException) -> CourseResponse {
+    let mut course = course.clone();
+    if let Some(weekday) = exception.weekday {
+        course.weekday = weekday;
+    }
+    if let Some(start_time) = &exception.start_time {
+        course.start_time = start_time.clone();
+    }
+    if let Some(end_time) = &exception.end_time {
+        course.end_time = end_time.clone();
+    }
+    if let Some(location) = &exception.location {
+        course.location = Some(location.clone());
+    }
+    course
+}
+
+// 某一天实际上的课：按（调休后的）课表取课，去掉停课和调出的课，加上同一周调入当天的课
+// 返回应用调整后的课程（按开始时间排序）以及影响当天的单次调整
+pub fn day_courses(
+    semester: &Semester,
+    date: NaiveDate,
+    calendar_override: Option<&CalendarOverride>,
+    courses: &[CourseResponse],
+    exceptions: &[CourseException],
+) -> (Vec<CourseResponse>, Vec<CourseException>) {
+    let mut day = Vec::new();
+    let mut applied: Vec<CourseException> = Vec::new();
+
+    if let Some((week, weekday)) = schedule_slot(semester, date, calendar_override) {
+        for course in courses_on(courses, semester, week, weekday) {
+            let exception = exceptions
+                .iter()
+                .find(|e| e.course_id == course.id && e.week == week);
+            match exception {
+                None => day.push(course),
+                Some(exception) => {
+                    applied.push(exception.clone());
+                    // 只改时间或地点的调整仍在原来的日期上课
+                    if ExceptionAction::from_db(&exception.action)
+                        == Some(ExceptionAction::Reschedule)
+                        && exception.weekday.is_none()
+                    {
+                        day.push(apply_exception(&course, exception));
+                    }
+                }
+            }
+        }
+    }
+
+    // 调课只在同一周内改星期，调入当天的课的周次即为当天所在的教学周
+    let week = semester.week_of(date);
+    let weekday = date.weekday().number_from_monday() as i32;
+    let moved_in = exceptions.iter().filter(|e| {
+        e.week == week
+            && e.weekday == Some(weekday)
+            && ExceptionAction::from_db(&e.action) == Some(ExceptionAction::Reschedule)
+    });
+    for exception in moved_in {
+        let course = courses.iter().find(|c| {
+            c.id == exception.course_id && in_semester(c, semester) && c.weeks.contains(&week)
+        });
+        if let Some(course) = course {
+            if !applied.iter().any(|e| e.id == exception.id) {
+                applied.push(exception.clone());
+            }
+            day.push(apply_exception(course, exception));
+        }
+    }
+
+    day.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+    (day, applied)
+}
+
+// 一天中 [start, end) 范围内没有课的时段，合并重叠的课程后取间隙，短于 min_minutes 的间隙不计
+pub fn free_slots(
+    courses: &[CourseResponse],
+    start: NaiveTime,
+    end: NaiveTime,
+    min_minutes: i64,
+) -> Vec<FreeSlot> {
+    free_intervals(courses, start, end)
+        .into_iter()
+        .map(|(from, to)| FreeSlot {
+            start: from.format("%H:%M").to_string(),
+            end: to.format("%H:%M").to_string(),
+            minutes: (to - from).num_minutes(),
+        })
+        .filter(|slot| slot.minutes >= min_minutes)
+        .collect()
+}
+
+// 一天中 [start, end) 范围内没有课的区间，按时间排列
+fn free_intervals(
+    courses: &[CourseResponse],
+    start: NaiveTime,
+    end: NaiveTime,
+) -> Vec<(NaiveTime, NaiveTime)> {
+    let mut busy: Vec<(NaiveTime, NaiveTime)> = courses
+        .iter()
+        .filter_map(|c| Some((parse_time(&c.start_time)?, parse_time(&c.end_time)?)))
+        .map(|(course_start, course_end)| (course_start.max(start), course_end.min(end)))
+        .filter(|(course_start, course_end)| course_start < course_end)
+        .collect();
+    busy.sort();
+
+    let mut intervals = Vec::new();
+    let mut cursor = start;
+    for (course_start, course_end) in busy {
+        if course_start > cursor {
+            intervals.push((cursor, course_start));
+        }
+        cursor = cursor.max(course_end);
+    }
+    if cursor < end {
+        intervals.push((cursor, end));
+    }
+    intervals
+}
+
+// 按课程的星期、时间和周次计算多周共同的空闲时段
+// 每个星期把各周的空闲区间按端点切成小段，逐段统计在哪些周空闲，相邻且空闲周次相同的小段合并为一个时段
+// 结果按空闲周数从多到少排列，周数相同时按星期、开始时间排列
+pub fn common_free_slots(
+    courses: &[CourseResponse],
+    from_week: i32,
+    to_week: i32,
+    start: NaiveTime,
+    end: NaiveTime,
+    min_minutes: i64,
+) -> Vec<CommonFreeSlot> {
+    let mut slots = Vec::new();
+    for weekday in 1..=7 {
+        let free_by_week: Vec<(i32, Vec<(NaiveTime, NaiveTime)>)> = (from_week..=to_week)
+            .map(|week| {
+                let day_courses: Vec<CourseResponse> = courses
+                    .iter()
+                    .filter(|c| c.weekday == weekday && c.weeks.contains(&week))
+                    .cloned()
+                    .collect();
+                (week, free_intervals(&day_courses, start, end))
+            })
+            .collect();
+
+        let mut bounds: Vec<NaiveTime> = free_by_week
+            .iter()
+            .flat_map(|(_, free)| free.iter().flat_map(|(from, to)| [*from, *to]))
+            .collect();
+        bounds.sort();
+        bounds.dedup();
+
+        let mut runs: Vec<(NaiveTime, NaiveTime, Vec<i32>)> = Vec::new();
+        for pair in bounds.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let weeks: Vec<i32> = free_by_week
+                .iter()
+                .filter(|(_, free)| free.iter().any(|(s, e)| *s <= from && to <= *e))
+                .map(|(week, _)| *week)
+                .collect();
+            match runs.last_mut() {
+                Some((_, run_end, run_weeks)) if *run_end == from && *run_weeks == weeks => {
+                    *run_end = to;
+                }
+                _ => runs.push((from, to, weeks)),
+            }
+        }
+
+        slots.extend(
+            runs.into_iter()
+                .filter(|(from, to, weeks)| {
+                    !weeks.is_empty() && (*to - *from).num_minutes() >= min_minutes
+                })
+                .map(|(from, to, weeks)| CommonFreeSlot {
+                    weekday,
+                    start: from.format("%H:%M").to_string(),
+                    end: to.format("%H:%M").to_string(),
+                    minutes: (to - from).num_minutes(),
+                    free_weeks: weeks.len(),
+                    week_rule: format_week_rule(&weeks),
+                    weeks,
+                }),
+        );
+    }
+
+    slots.sort_by_key(|slot| std::cmp::Reverse(slot.free_weeks));
+    slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn course(weekday: i32, start_time: &str, end_time: &str, weeks: Vec<i32>) -> CourseResponse {
+        CourseResponse {
+            id: format!("{}-{}", weekday, start_time),
+            name: "课程".to_string(),
+            teacher: None,
+            location: None,
+            weekday,
+            start_time: start_time.to_string(),
+            end_time: end_time.to_string(),
+            week_rule: format_week_rule(&weeks),
+            weeks,
+            color: None,
+            semester_id: None,
+            campus: None,
+            start_period: None,
+            end_period: None,
+            teacher_id: None,
+            room_id: None,
+            owner_id: None,
+            timetable_id: None,
+        }
+    }
+
+    fn time(value: &str) -> NaiveTime {
+        parse_time(value).unwrap()
+    }
+
+    fn tuesday(slots: &[CommonFreeSlot]) -> Vec<(&str, &str, Vec<i32>)> {
+        slots
+            .iter()
+            .filter(|slot| slot.weekday == 2)
+            .map(|slot| (slot.start.as_str(), slot.end.as_str(), slot.weeks.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn finds_gaps_between_overlapping_courses() {
+        let courses = vec![
+            course(1, "08:00:00", "09:40:00", vec![1]),
+            course(1, "09:00:00", "10:00:00", vec![1]),
+            course(1, "14:00:00", "15:40:00", vec![1]),
+        ];
+        let slots = free_slots(&courses, time("08:00"), time("18:00"), 60);
+        let slots: Vec<(&str, &str)> = slots
+            .iter()
+            .map(|slot| (slot.start.as_str(), slot.end.as_str()))
+            .collect();
+        assert_eq!(slots, vec![("10:00", "14:00"), ("15:40", "18:00")]);
+    }
+
+    #[test]
+    fn intersects_free_time_across_weeks() {
+        // 周二第 1 周 08:00-09:00 有课，第 2 周 20:00-22:00 有课
+        let courses = vec![
+            course(2, "08:00:00", "09:00:00", vec![1]),
+            course(2, "20:00:00", "22:00:00", vec![2]),
+        ];
+        let slots = common_free_slots(&courses, 1, 2, time("08:00"), time("22:00"), 0);
+        assert_eq!(
+            tuesday(&slots),
+            vec![
+                ("09:00", "20:00", vec![1, 2]),
+                ("08:00", "09:00", vec![2]),
+                ("20:00", "22:00", vec![1]),
+            ]
+        );
+        // 其他六天两周都全天空闲，排在最前；周二两周都空闲的时段排在只有一周空闲的时段之前
+        assert_eq!(slots.len(), 9);
+        assert!(slots[..7].iter().all(|slot| slot.free_weeks == 2));
+        assert_eq!((slots[1].weekday, slots[1].start.as_str()), (2, "09:00"));
+        assert_eq!(slots[7].week_rule, "2");
+    }
+
+    #[test]
+    fn merges_identical_weeks_and_drops_short_slots() {
+        let courses = vec![
+            course(2, "08:00:00", "09:00:00", vec![1, 2, 3]),
+            course(2, "09:30:00", "12:00:00", vec![2]),
+        ];
+        let slots = common_free_slots(&courses, 1, 3, time("08:00"), time("12:00"), 60);
+        // 09:00-09:30 三周都空闲但不足 60 分钟，09:30-12:00 只有第 1、3 周空闲
+        assert_eq!(tuesday(&slots), vec![("09:30", "12:00", vec![1, 3])]);
+        assert_eq!(slots.last().unwrap().week_rule, "1,3");
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::BTreeSet;
+
+use crate::models::{
+    parse_time, CourseConflict, CourseResponse, CreateCourseRequest, UpdateCourseRequest,
+};
+use crate::week_rule::format_week_rule;
+
+// 检查一组课程两两之间的冲突
+// active_semester_id 为当前学期，未关联学期的课程视为属于当前学期
+pub fn find_conflicts(
+    courses: &[CourseResponse],
+    active_semester_id: Option<&str>,
+) -> Vec<CourseConflict> {
+    let mut conflicts = Vec::new();
+    for (i, course) in courses.iter().enumerate() {
+        for other in &courses[i + 1..] {
+            if let Some(conflict) = check_pair(course, other, active_semester_id) {
+                conflicts.push(conflict);
+            }
+        }
+    }
+    conflicts
+}
+
+// 检查待写入的课程与已有课程、以及待写入课程之间的冲突
+// 与已有课程 ID 相同的记录视为同一门课程（更新场景），不会与自身冲突
+pub fn find_conflicts_with(
+    candidates: &[CourseResponse],
+    existing: &[CourseResponse],
+    active_semester_id: Option<&str>,
+) -> Vec<CourseConflict> {
+    let mut conflicts = find_conflicts(candidates, active_semester_id);
+    for candidate in candidates {
+        for course in existing.iter().filter(|c| c.id != candidate.id) {
+            if let Some(conflict) = check_pair(candidate, course, active_semester_id) {
+                conflicts.push(conflict);
+            }
+        }
+    }
+    conflicts
+}
+
+// 冲突中涉及的已有课程 ID（不包含待写入课程本身）
+pub fn conflicting_course_ids(
+    conflicts: &[CourseConflict],
+    candidates: &[CourseResponse],
+) -> Vec<String> {
+    let candidate_ids: BTreeSet<&str> = candidates.iter().map(|c| c.id.as_str()).collect();
+    let ids: BTreeSet<&str> = conflicts
+        .iter()
+        .flat_map(|c| [c.course_id.as_str(), c.other_course_id.as_str()])
+        .filter(|id| !candidate_ids.contains(id))
+        .collect();
+    ids.into_iter().map(str::to_string).collect()
+}
+
+// 将创建请求转换为待检查的课程，id 用于在冲突结果中标识该课程
+pub fn candidate_from_request(id: String, course_req: &CreateCourseRequest) -> CourseResponse {
+    CourseResponse {
+        id,
+        name: course_req.name.clone(),
+        teacher: course_req.teacher.clone(),
+        location: course_req.location.clone(),
+        weekday: course_req.weekday,
+        start_time: course_req.start_time.clone(),
+        end_time: course_req.end_time.clone(),
+        weeks: course_req.weeks.clone(),
+        week_rule: format_week_rule(&course_req.weeks),
+        color: course_req.color.clone(),
+        semester_id: course_req.semester_id.clone(),
+        campus: course_req.campus.clone(),
+        start_period: course_req.start_period,
+        end_period: course_req.end_period,
+        teacher_id: course_req.teacher_id.clone(),
+        room_id: course_req.room_id.clone(),
+        owner_id: None, // 冲突检查只在同一课表的课程之间进行，不关心归属
+        timetable_id: None,
+    }
+}
+
+// 将更新请求合并到已有课程上，得到更新后的课程
+pub fn candidate_from_update(
+    existing: &CourseResponse,
+    update_req: &UpdateCourseRequest,
+) -> CourseResponse {
+    // 与存储层一致：只更新时间而不指定节次时，课程改为按时间定义
+    let detach_periods = update_req.start_period.is_none()
+        && (update_req.start_time.is_some() || update_req.end_time.is_some());
+    let (start_period, end_period) = if detach_periods {
+        (None, None)
+    } else {
+        (
+            update_req.start_period.or(existing.start_period),
+            update_req.end_period.or(existing.end_period),
+        )
+    };
+
+    let weeks = update_req.weeks.clone().unwrap_or(existing.weeks.clone());
+
+    // 与存储层一致：只更新教师姓名时解除原有关联，由校验时按姓名重新匹配
+    let teacher_id = if update_req.teacher.is_some() || update_req.teacher_id.is_some() {
+        update_req.teacher_id.clone()
+    } else {
+        existing.teacher_id.clone()
+    };
+    let room_id = if update_req.location.is_some() || update_req.room_id.is_some() {
+        update_req.room_id.clone()
+    } else {
+        existing.room_id.clone()
+    };
+
+    CourseResponse {
+        id: existing.id.clone(),
+        name: update_req.name.clone().unwrap_or(existing.name.clone()),
+        teacher: update_req.teacher.clone().or(existing.teacher.clone()),
+        location: update_req.location.clone().or(existing.location.clone()),
+        weekday: update_req.weekday.unwrap_or(existing.weekday),
+        start_time: update_req
+            .start_time
+            .clone()
+            .unwrap_or(existing.start_time.clone()),
+        end_time: update_req
+            .end_time
+            .clone()
+            .unwrap_or(existing.end_time.clone()),
+        week_rule: format_week_rule(&weeks),
+        weeks,
+        color: update_req.color.clone().or(existing.color.clone()),
+        semester_id: update_req
+            .semester_id
+            .clone()
+            .or(existing.semester_id.clone()),
+        campus: update_req.campus.clone().or(existing.campus.clone()),
+        start_period,
+        end_period,
+        teacher_id,
+        room_id,
+        owner_id: existing.owner_id.clone(),
+        timetable_id: existing.timetable_id.clone(),
+    }
+}
+
+// 课程所属学期，未关联学期的课程属于当前学期
+fn semester_of<'a>(
+    course: &'a CourseResponse,
+    active_semester_id: Option<&'a str>,
+) -> Option<&'a str> {
+    course.semester_id.as_deref().or(active_semester_id)
+}
+
+fn check_pair(
+    course: &CourseResponse,
+    other: &CourseResponse,
+    active_semester_id: Option<&str>,
+) -> Option<CourseConflict> {
+    if course.weekday != other.weekday
+        || semester_of(course, active_semester_id) != semester_of(other, active_semester_id)
+    {
+        return None;
+    }
+
+    // 时间无法解析的课程不参与冲突检查
+    let (start, end) = (
+        parse_time(&course.start_time)?,
+        parse_time(&course.end_time)?,
+    );
+    let (other_start, other_end) = (parse_time(&other.start_time)?, parse_time(&other.end_time)?);
+    let overlap_start = start.max(other_start);
+    let overlap_end = end.min(other_end);
+    if overlap_start >= overlap_end {
+        return None;
+    }
+
+    let other_weeks: BTreeSet<i32> = other.weeks.iter().copied().collect();
+    let weeks: BTreeSet<i32> = course
+        .weeks
+        .iter()
+        .copied()
+        .filter(|w| other_weeks.contains(w))
+        .collect();
+    if weeks.is_empty() {
+        return None;
+    }
+
+    Some(CourseConflict {
+        course_id: course.id.clone(),
+        course_name: course.name.clone(),
+        other_course_id: other.id.clone(),
+        other_course_name: other.name.clone(),
+        weekday: course.weekday,
+        start_time: overlap_start.format("%H:%M:%S").to_string(),
+        end_time: overlap_end.format("%H:%M:%S").to_string(),
+        weeks: weeks.into_iter().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn course(id: &str, start_time: &str, end_time: &str, weeks: Vec<i32>) -> CourseResponse {
+        CourseResponse {
+            id: id.to_string(),
+            name: id.to_string(),
+            teacher: None,
+            location: None,
+            weekday: 1,
+            start_time: start_time.to_string(),
+            end_time: end_time.to_string(),
+            week_rule: format_week_rule(&weeks),
+            weeks,
+            color: None,
+            semester_id: None,
+            campus: None,
+            start_period: None,
+            end_period: None,
+            teacher_id: None,
+            room_id: None,
+            owner_id: None,
+            timetable_id: None,
+        }
+    }
+
+    fn in_semester(mut course: CourseResponse, semester_id: &str) -> CourseResponse {
+        course.semester_id = Some(semester_id.to_string());
+        course
+    }
+
+    #[test]
+    fn reports_overlapping_time_and_weeks() {
+        let courses = vec![
+            course("a", "08:00:00", "09:40:00", (1..=8).collect()),
+            course("b", "09:00:00", "10:40:00", (5..=12).collect()),
+        ];
+        let conflicts = find_conflicts(&courses, None);
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!(
+            (
+                conflict.course_id.as_str(),
+                conflict.other_course_id.as_str()
+            ),
+            ("a", "b")
+        );
+        assert_eq!(conflict.start_time, "09:00:00");
+        assert_eq!(conflict.end_time, "09:40:00");
+        assert_eq!(conflict.weeks, vec![5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn adjacent_courses_do_not_conflict() {
+        let courses = vec![
+            course("a", "08:00:00", "09:40:00", vec![1, 2]),
+            course("b", "09:40:00", "11:20:00", vec![1, 2]),
+        ];
+        assert!(find_conflicts(&courses, None).is_empty());
+    }
+
+    #[test]
+    fn disjoint_weeks_do_not_conflict() {
+        let courses = vec![
+            course("odd", "08:00:00", "09:40:00", vec![1, 3, 5]),
+            course("even", "08:00:00", "09:40:00", vec![2, 4, 6]),
+        ];
+        assert!(find_conflicts(&courses, None).is_empty());
+    }
+
+    #[test]
+    fn different_weekdays_do_not_conflict() {
+        let mut tuesday = course("b", "08:00:00", "09:40:00", vec![1]);
+        tuesday.weekday = 2;
+        let courses = vec![course("a", "08:00:00", "09:40:00", vec![1]), tuesday];
+        assert!(find_conflicts(&courses, None).is_empty());
+    }
+
+    #[test]
+    fn courses_in_other_semesters_do_not_conflict() {
+        let courses = vec![
+            in_semester(course("a", "08:00:00", "09:40:00", vec![1]), "spring"),
+            in_semester(course("b", "08:00:00", "09:40:00", vec![1]), "autumn"),
+        ];
+        assert!(find_conflicts(&courses, Some("spring")).is_empty());
+    }
+
+    #[test]
+    fn courses_without_semester_belong_to_the_active_one() {
+        let courses = vec![
+            course("a", "08:00:00", "09:40:00", vec![1]),
+            in_semester(course("b", "08:00:00", "09:40:00", vec![1]), "spring"),
+        ];
+        assert_eq!(find_conflicts(&courses, Some("spring")).len(), 1);
+        assert!(find_conflicts(&courses, Some("autumn")).is_empty());
+        // 没有当前学期时无法判断，按不同学期处理
+        assert!(find_conflicts(&courses, None).is_empty());
+    }
+
+    #[test]
+    fn updated_course_does_not_conflict_with_itself() {
+        let existing = vec![
+            course("a", "08:00:00", "09:40:00", vec![1]),
+            course("b", "10:00:00", "11:40:00", vec![1]),
+        ];
+        let candidates = vec![course("a", "08:30:00", "10:30:00", vec![1])];
+        let conflicts = find_conflicts_with(&candidates, &existing, None);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicting_course_ids(&conflicts, &candidates),
+            vec!["b".to_string()]
+        );
+    }
+}
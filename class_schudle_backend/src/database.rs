@@ -1,34 +1,92 @@
-use core::panic;
-use diesel::prelude::*;
+use diesel::connection::SimpleConnection;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool, PooledConnection};
 use diesel::sqlite::SqliteConnection;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use log::{debug, info};
 use std::env;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
-pub fn establish_connection() -> SqliteConnection {
+pub type DbPool = Pool<ConnectionManager<SqliteConnection>>;
+pub type DbConnection = PooledConnection<ConnectionManager<SqliteConnection>>;
+
+// 每个连接建立时设置的 SQLite 参数（这些设置只对当前连接生效）
+// busy_timeout 需放在最前面，连接池并发建连时切换 WAL 才不会因锁冲突失败
+const CONNECTION_PRAGMAS: &str = "
+    PRAGMA busy_timeout = 5000;
+    PRAGMA journal_mode = WAL;
+    PRAGMA foreign_keys = ON;
+    PRAGMA synchronous = NORMAL;
+";
+
+#[derive(Debug)]
+struct SqlitePragmas;
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for SqlitePragmas {
+    fn on_acquire(&self, connection: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        connection
+            .batch_execute(CONNECTION_PRAGMAS)
+            .map_err(diesel::r2d2::Error::QueryError)?;
+        debug!("SQLite 连接已建立并完成参数设置");
+        Ok(())
+    }
+}
+
+// 解析 DATABASE_URL 并确保数据库文件所在目录存在
+fn database_path() -> String {
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
-    // 如果是 SQLite 文件，确保目录存在
-    if database_url.starts_with("sqlite:") {
-        let file_path = database_url.strip_prefix("sqlite:").unwrap();
-        println!("尝试连接数据库文件: {}", file_path);
-
-        if let Some(parent) = std::path::Path::new(file_path).parent() {
-            std::fs::create_dir_all(parent).expect("Failed to create database directory");
-        }
-
-        // 检查文件是否存在
-        if !std::path::Path::new(file_path).exists() {
-            println!("数据库文件不存在，将创建: {}", file_path);
-        } else {
-            println!("数据库文件已存在: {}", file_path);
-        }
-        SqliteConnection::establish(&file_path)
-            .unwrap_or_else(|e| panic!("Error connecting to {}: {}", file_path, e))
+    let file_path = database_url
+        .strip_prefix("sqlite:")
+        .expect("Only sqlite is supported");
+    info!("尝试连接数据库文件: {}", file_path);
+
+    if let Some(parent) = std::path::Path::new(file_path).parent() {
+        std::fs::create_dir_all(parent).expect("Failed to create database directory");
+    }
+
+    // 检查文件是否存在
+    if !std::path::Path::new(file_path).exists() {
+        info!("数据库文件不存在，将创建: {}", file_path);
     } else {
-        panic!("Only sqlite is supported");
+        info!("数据库文件已存在: {}", file_path);
     }
+
+    file_path.to_string()
+}
+
+pub fn create_pool() -> DbPool {
+    let manager = ConnectionManager::<SqliteConnection>::new(database_path());
+    Pool::builder()
+        .connection_customizer(Box::new(SqlitePragmas))
+        .build(manager)
+        .expect("Failed to create database connection pool")
+}
+
+// 测试用的内存数据库，SQLite 的内存库只在单个连接内可见，所以连接池只保留一个连接
+#[cfg(test)]
+pub fn create_memory_pool() -> DbPool {
+    let manager = ConnectionManager::<SqliteConnection>::new(":memory:");
+    let pool = Pool::builder()
+        .max_size(1)
+        .connection_customizer(Box::new(SqlitePragmas))
+        .build(manager)
+        .expect("Failed to create in-memory database pool");
+    run_migrations(&mut pool.get().expect("Failed to get database connection"));
+    pool
+}
+
+// 测试用的数据库文件，允许多个连接并发访问
+#[cfg(test)]
+pub fn create_file_pool(path: &std::path::Path) -> DbPool {
+    let manager = ConnectionManager::<SqliteConnection>::new(path.to_string_lossy());
+    let pool = Pool::builder()
+        .max_size(8)
+        .connection_customizer(Box::new(SqlitePragmas))
+        .build(manager)
+        .expect("Failed to create database pool");
+    run_migrations(&mut pool.get().expect("Failed to get database connection"));
+    pool
 }
 
 pub fn run_migrations(connection: &mut SqliteConnection) {
@@ -36,3 +94,73 @@ pub fn run_migrations(connection: &mut SqliteConnection) {
         .run_pending_migrations(MIGRATIONS)
         .expect("Failed to run migrations");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::prelude::*;
+    use diesel::sql_types::{Integer, Text};
+
+    #[derive(QueryableByName)]
+    struct Weeks {
+        #[diesel(sql_type = Text)]
+        weeks: String,
+    }
+
+    #[derive(QueryableByName)]
+    struct Week {
+        #[diesel(sql_type = Integer)]
+        week: i32,
+    }
+
+    fn weeks_of(connection: &mut SqliteConnection, course_id: &str) -> Vec<i32> {
+        diesel::sql_query("SELECT week FROM course_weeks WHERE course_id = ? ORDER BY week")
+            .bind::<Text, _>(course_id)
+            .load::<Week>(connection)
+            .unwrap()
+            .into_iter()
+            .map(|row| row.week)
+            .collect()
+    }
+
+    #[test]
+    fn course_weeks_migration_keeps_unparseable_weeks() {
+        let mut connection = SqliteConnection::establish(":memory:").unwrap();
+        connection.batch_execute(CONNECTION_PRAGMAS).unwrap();
+        // 迁移到拆分周次之前，插入旧格式的课程
+        while connection
+            .run_next_migration(MIGRATIONS)
+            .unwrap()
+            .to_string()
+            != "20250620000001"
+        {}
+        connection
+            .batch_execute(
+                "INSERT INTO courses (id, name, weekday, start_time, end_time, weeks) VALUES
+                    ('valid', '高等数学', 1, '08:00:00', '09:40:00', '[1,3,5]'),
+                    ('broken', '大学英语', 2, '08:00:00', '09:40:00', '1-16'),
+                    ('empty', '体育', 3, '08:00:00', '09:40:00', '[0,\"2\"]');",
+            )
+            .unwrap();
+
+        connection.run_next_migration(MIGRATIONS).unwrap();
+        assert_eq!(weeks_of(&mut connection, "valid"), vec![1, 3, 5]);
+        assert_eq!(
+            weeks_of(&mut connection, "broken"),
+            (1..=16).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            weeks_of(&mut connection, "empty"),
+            (1..=16).collect::<Vec<_>>()
+        );
+
+        connection.revert_last_migration(MIGRATIONS).unwrap();
+        let weeks: Vec<String> = diesel::sql_query("SELECT weeks FROM courses ORDER BY id")
+            .load::<Weeks>(&mut connection)
+            .unwrap()
+            .into_iter()
+            .map(|row| row.weeks)
+            .collect();
+        assert_eq!(weeks, vec!["1-16", "[0,\"2\"]", "[1,3,5]"]);
+    }
+}
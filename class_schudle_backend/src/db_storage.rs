@@ -1,41 +1,849 @@
-use chrono::Utc;
+use chrono::{NaiveDate, NaiveDateTime, Utc};
 use diesel::prelude::*;
+use diesel::sqlite::{Sqlite, SqliteConnection};
 use log::{debug, info};
+use std::collections::{BTreeSet, HashMap};
 use uuid::Uuid;
 
-use crate::database::establish_connection;
+use crate::database::{DbConnection, DbPool};
+use crate::events::{EventBus, EventKind, ScheduleEvent};
 use crate::models::{
-    Course, CourseResponse, CreateCourseRequest, NewCourse, UpdateCourse, UpdateCourseRequest,
+    CalendarOverride, Course, CourseException, CourseExceptionQuery, CourseQuery, CourseReminder,
+    CourseResponse, CourseScope, CourseSortField, CreateCalendarOverrideRequest,
+    CreateCourseExceptionRequest, CreateCourseRequest, CreatePeriodRequest, CreateRoomRequest,
+    CreateSemesterRequest, CreateTeacherRequest, CreateTimetableRequest, NewCalendarOverride,
+    NewCourse, NewCourseException, NewCourseReminder, NewCourseWeek, NewPeriod,
+    NewReminderDelivery, NewRoom, NewSemester, NewShareLink, NewTeacher, NewTimetable, NewUser,
+    NewUserSession, Period, RegisterRequest, Room, Semester, ShareLink, SortOrder, Teacher,
+    Timetable, UpdateCalendarOverride, UpdateCalendarOverrideRequest, UpdateCourse,
+    UpdateCourseException, UpdateCourseExceptionRequest, UpdateCourseRequest, UpdatePeriod,
+    UpdatePeriodRequest, UpdateRoom, UpdateRoomRequest, UpdateSemester, UpdateSemesterRequest,
+    UpdateTeacher, UpdateTeacherRequest, UpdateTimetable, UpdateTimetableRequest, User,
 };
-use crate::schema::courses;
+use crate::schema::{
+    calendar_overrides, course_exceptions, course_reminders, course_weeks, courses, periods,
+    reminder_deliveries, rooms, semesters, share_links, teachers, timetables, user_sessions, users,
+};
+use crate::storage::{ScheduleRepository, StorageError, DEFAULT_TIMETABLE_NAME};
 
-pub fn get_all_courses() -> Result<Vec<CourseResponse>, diesel::result::Error> {
-    let mut connection = establish_connection();
+// SQLite 存储后端，每次操作从连接池取出一个连接，课程写入成功后发布变更事件
+pub struct SqliteRepository {
+    pool: DbPool,
+    events: EventBus,
+}
 
-    let results = courses::table
-        .select(Course::as_select())
-        .load(&mut connection)?;
+impl SqliteRepository {
+    pub fn new(pool: DbPool, events: EventBus) -> Self {
+        Self { pool, events }
+    }
 
-    let course_responses: Vec<CourseResponse> =
-        results.into_iter().map(|course| course.into()).collect();
+    fn connection(&self) -> Result<DbConnection, StorageError> {
+        Ok(self.pool.get()?)
+    }
 
-    info!("📋 从数据库获取到 {} 门课程", course_responses.len());
-    Ok(course_responses)
+    // 执行会间接修改课程的写入：先读取 affected 中的课程，写入成功后重新读取，内容变化的逐门发布更新事件
+    fn write_with_course_changes<T>(
+        &self,
+        affected: courses::BoxedQuery<'static, Sqlite>,
+        write: impl FnOnce(&mut SqliteConnection) -> Result<T, diesel::result::Error>,
+    ) -> Result<T, StorageError> {
+        let mut connection = self.connection()?;
+        let before = load_courses(&mut connection, affected)?;
+        let result = write(&mut connection)?;
+
+        if !before.is_empty() {
+            let course_ids: Vec<String> = before.iter().map(|c| c.id.clone()).collect();
+            let after = load_courses(
+                &mut connection,
+                courses::table
+                    .filter(courses::id.eq_any(course_ids))
+                    .into_boxed(),
+            )?;
+            self.events.publish_changed(&before, &after);
+        }
+        Ok(result)
+    }
+
+    // 单次调整改变了课程某一周的安排，发布课程的更新事件，订阅者据此重新拉取日程
+    fn publish_exception_course(
+        &self,
+        connection: &mut SqliteConnection,
+        course_id: &str,
+    ) -> Result<(), StorageError> {
+        let course = load_courses(
+            connection,
+            courses::table
+                .filter(courses::id.eq(course_id.to_string()))
+                .into_boxed(),
+        )?;
+        for course in &course {
+            self.events
+                .publish(ScheduleEvent::course(EventKind::CourseUpdated, course));
+        }
+        Ok(())
+    }
+}
+
+impl ScheduleRepository for SqliteRepository {
+    fn list_courses(&self, scope: &CourseScope) -> Result<Vec<CourseResponse>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_all_courses(&mut connection, scope)?)
+    }
+
+    fn list_all_courses(&self) -> Result<Vec<CourseResponse>, StorageError> {
+        let mut connection = self.connection()?;
+        let results = courses::table
+            .select(Course::as_select())
+            .load(&mut connection)?;
+        Ok(with_weeks(&mut connection, results)?)
+    }
+
+    fn find_courses(
+        &self,
+        scope: &CourseScope,
+        query: &CourseQuery,
+    ) -> Result<Vec<CourseResponse>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(find_courses(&mut connection, scope, query)?)
+    }
+
+    fn get_course(
+        &self,
+        scope: &CourseScope,
+        course_id: &str,
+    ) -> Result<Option<CourseResponse>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_course_by_id(&mut connection, scope, course_id)?)
+    }
+
+    fn insert_course(
+        &self,
+        scope: &CourseScope,
+        course_req: &CreateCourseRequest,
+    ) -> Result<CourseResponse, StorageError> {
+        let mut connection = self.connection()?;
+        let course = insert_course(&mut connection, scope, course_req)?;
+        self.events
+            .publish(ScheduleEvent::course(EventKind::CourseCreated, &course));
+        Ok(course)
+    }
+
+    fn update_course(
+        &self,
+        scope: &CourseScope,
+        course_id: &str,
+        update_req: &UpdateCourseRequest,
+    ) -> Result<Option<CourseResponse>, StorageError> {
+        let mut connection = self.connection()?;
+        let course = update_course(&mut connection, scope, course_id, update_req)?;
+        if let Some(course) = &course {
+            self.events
+                .publish(ScheduleEvent::course(EventKind::CourseUpdated, course));
+        }
+        Ok(course)
+    }
+
+    fn delete_course(&self, scope: &CourseScope, course_id: &str) -> Result<bool, StorageError> {
+        let mut connection = self.connection()?;
+        // 先取出课程，删除事件中带上课程所属的课表
+        let Some(course) = get_course_by_id(&mut connection, scope, course_id)? else {
+            return Ok(false);
+        };
+        let deleted = delete_course(&mut connection, scope, course_id)?;
+        if deleted {
+            self.events
+                .publish(ScheduleEvent::course(EventKind::CourseDeleted, &course));
+        }
+        Ok(deleted)
+    }
+
+    fn delete_all_courses(&self, scope: &CourseScope) -> Result<usize, StorageError> {
+        let mut connection = self.connection()?;
+        let deleted_count = delete_all_courses(&mut connection, scope)?;
+        if deleted_count > 0 {
+            self.events.publish(ScheduleEvent::replaced(
+                scope.owner_id.clone(),
+                scope.timetable_id.clone(),
+            ));
+        }
+        Ok(deleted_count)
+    }
+
+    fn insert_courses(
+        &self,
+        scope: &CourseScope,
+        course_requests: &[CreateCourseRequest],
+        replace: bool,
+    ) -> Result<Vec<CourseResponse>, StorageError> {
+        let mut connection = self.connection()?;
+        // 清空与写入在同一事务中完成，写入失败时不会丢失原有课程
+        let created_courses = connection.transaction(|conn| {
+            if replace {
+                delete_all_courses(conn, scope)?;
+            }
+            insert_multiple_courses(conn, scope, course_requests)
+        })?;
+        self.events
+            .publish_inserted(scope, &created_courses, replace);
+        Ok(created_courses)
+    }
+
+    fn list_timetables(&self, owner_id: Option<&str>) -> Result<Vec<Timetable>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_timetables(&mut connection, owner_id)?)
+    }
+
+    fn get_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+    ) -> Result<Option<Timetable>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_timetable_by_id(
+            &mut connection,
+            owner_id,
+            timetable_id,
+        )?)
+    }
+
+    fn default_timetable(&self, owner_id: Option<&str>) -> Result<Timetable, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_or_create_default_timetable(&mut connection, owner_id)?)
+    }
+
+    fn insert_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_req: &CreateTimetableRequest,
+    ) -> Result<Timetable, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(insert_timetable(
+            &mut connection,
+            owner_id,
+            &timetable_req.name,
+            false,
+        )?)
+    }
+
+    fn update_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+        update_req: &UpdateTimetableRequest,
+    ) -> Result<Option<Timetable>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(update_timetable(
+            &mut connection,
+            owner_id,
+            timetable_id,
+            update_req,
+        )?)
+    }
+
+    fn delete_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+    ) -> Result<bool, StorageError> {
+        let mut connection = self.connection()?;
+        let deleted = delete_timetable(&mut connection, owner_id, timetable_id)?;
+        if deleted {
+            self.events.publish(ScheduleEvent::replaced(
+                owner_id.map(str::to_string),
+                Some(timetable_id.to_string()),
+            ));
+        }
+        Ok(deleted)
+    }
+
+    fn duplicate_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+        name: &str,
+    ) -> Result<Option<Timetable>, StorageError> {
+        let mut connection = self.connection()?;
+        let timetable = duplicate_timetable(&mut connection, owner_id, timetable_id, name)?;
+        if let Some(timetable) = &timetable {
+            self.events.publish(ScheduleEvent::replaced(
+                timetable.owner_id.clone(),
+                Some(timetable.id.clone()),
+            ));
+        }
+        Ok(timetable)
+    }
+
+    fn insert_share_link(&self, share_link: &NewShareLink) -> Result<ShareLink, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(insert_share_link(&mut connection, share_link)?)
+    }
+
+    fn list_share_links(&self, owner_id: Option<&str>) -> Result<Vec<ShareLink>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_share_links(&mut connection, owner_id)?)
+    }
+
+    fn get_share_link(&self, token: &str) -> Result<Option<ShareLink>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_share_link_by_token(&mut connection, token)?)
+    }
+
+    fn delete_share_link(
+        &self,
+        owner_id: Option<&str>,
+        share_link_id: &str,
+    ) -> Result<bool, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(delete_share_link(&mut connection, owner_id, share_link_id)?)
+    }
+
+    fn insert_user(
+        &self,
+        register_req: &RegisterRequest,
+        password_hash: &str,
+    ) -> Result<User, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(insert_user(&mut connection, register_req, password_hash)?)
+    }
+
+    fn get_user_by_username(&self, username: &str) -> Result<Option<User>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_user_by_username(&mut connection, username)?)
+    }
+
+    fn insert_session(&self, session: &NewUserSession) -> Result<(), StorageError> {
+        let mut connection = self.connection()?;
+        Ok(insert_session(&mut connection, session)?)
+    }
+
+    fn get_session_user(&self, token_hash: &str) -> Result<Option<User>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_session_user(&mut connection, token_hash)?)
+    }
+
+    fn delete_session(&self, token_hash: &str) -> Result<bool, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(delete_session(&mut connection, token_hash)?)
+    }
+
+    fn list_semesters(&self) -> Result<Vec<Semester>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_all_semesters(&mut connection)?)
+    }
+
+    fn get_semester(&self, semester_id: &str) -> Result<Option<Semester>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_semester_by_id(&mut connection, semester_id)?)
+    }
+
+    fn get_active_semester(&self) -> Result<Option<Semester>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_active_semester(&mut connection)?)
+    }
+
+    fn insert_semester(
+        &self,
+        semester_req: &CreateSemesterRequest,
+    ) -> Result<Semester, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(insert_semester(&mut connection, semester_req)?)
+    }
+
+    fn update_semester(
+        &self,
+        semester_id: &str,
+        update_req: &UpdateSemesterRequest,
+    ) -> Result<Option<Semester>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(update_semester(&mut connection, semester_id, update_req)?)
+    }
+
+    fn delete_semester(
+        &self,
+        scope: &CourseScope,
+        semester_id: &str,
+    ) -> Result<bool, StorageError> {
+        // 课程解除学期关联，该学期的节次被删除后按节次定义的课程时间也会变化
+        self.write_with_course_changes(
+            scoped(scope).filter(
+                courses::semester_id
+                    .eq(semester_id.to_string())
+                    .or(courses::start_period.is_not_null()),
+            ),
+            |conn| delete_semester(conn, scope, semester_id),
+        )
+    }
+
+    fn list_teachers(&self) -> Result<Vec<Teacher>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_all_teachers(&mut connection)?)
+    }
+
+    fn get_teacher(&self, teacher_id: &str) -> Result<Option<Teacher>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_teacher_by_id(&mut connection, teacher_id)?)
+    }
+
+    fn insert_teacher(&self, teacher_req: &CreateTeacherRequest) -> Result<Teacher, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(insert_teacher(&mut connection, teacher_req)?)
+    }
+
+    fn update_teacher(
+        &self,
+        scope: &CourseScope,
+        teacher_id: &str,
+        update_req: &UpdateTeacherRequest,
+    ) -> Result<Option<Teacher>, StorageError> {
+        self.write_with_course_changes(teacher_courses(scope, teacher_id), |conn| {
+            update_teacher(conn, scope, teacher_id, update_req)
+        })
+    }
+
+    fn delete_teacher(&self, scope: &CourseScope, teacher_id: &str) -> Result<bool, StorageError> {
+        self.write_with_course_changes(teacher_courses(scope, teacher_id), |conn| {
+            delete_teacher(conn, scope, teacher_id)
+        })
+    }
+
+    fn list_rooms(&self) -> Result<Vec<Room>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_all_rooms(&mut connection)?)
+    }
+
+    fn get_room(&self, room_id: &str) -> Result<Option<Room>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_room_by_id(&mut connection, room_id)?)
+    }
+
+    fn insert_room(&self, room_req: &CreateRoomRequest) -> Result<Room, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(insert_room(&mut connection, room_req)?)
+    }
+
+    fn update_room(
+        &self,
+        scope: &CourseScope,
+        room_id: &str,
+        update_req: &UpdateRoomRequest,
+    ) -> Result<Option<Room>, StorageError> {
+        self.write_with_course_changes(room_courses(scope, room_id), |conn| {
+            update_room(conn, scope, room_id, update_req)
+        })
+    }
+
+    fn delete_room(&self, scope: &CourseScope, room_id: &str) -> Result<bool, StorageError> {
+        self.write_with_course_changes(room_courses(scope, room_id), |conn| {
+            delete_room(conn, scope, room_id)
+        })
+    }
+
+    fn list_overrides(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<CalendarOverride>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_overrides(&mut connection, from, to)?)
+    }
+
+    fn get_override(&self, override_id: &str) -> Result<Option<CalendarOverride>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_override_by_id(&mut connection, override_id)?)
+    }
+
+    fn get_override_by_date(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Option<CalendarOverride>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_override_by_date(&mut connection, date)?)
+    }
+
+    fn insert_override(
+        &self,
+        override_req: &CreateCalendarOverrideRequest,
+    ) -> Result<CalendarOverride, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(insert_override(&mut connection, override_req)?)
+    }
+
+    fn update_override(
+        &self,
+        override_id: &str,
+        update_req: &UpdateCalendarOverrideRequest,
+    ) -> Result<Option<CalendarOverride>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(update_override(&mut connection, override_id, update_req)?)
+    }
+
+    fn delete_override(&self, override_id: &str) -> Result<bool, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(delete_override(&mut connection, override_id)?)
+    }
+
+    fn list_exceptions(
+        &self,
+        query: &CourseExceptionQuery,
+    ) -> Result<Vec<CourseException>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_exceptions(&mut connection, query)?)
+    }
+
+    fn get_exception(&self, exception_id: &str) -> Result<Option<CourseException>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_exception_by_id(&mut connection, exception_id)?)
+    }
+
+    fn insert_exception(
+        &self,
+        exception_req: &CreateCourseExceptionRequest,
+    ) -> Result<CourseException, StorageError> {
+        let mut connection = self.connection()?;
+        let exception = insert_exception(&mut connection, exception_req)?;
+        self.publish_exception_course(&mut connection, &exception.course_id)?;
+        Ok(exception)
+    }
+
+    fn update_exception(
+        &self,
+        exception_id: &str,
+        update_req: &UpdateCourseExceptionRequest,
+    ) -> Result<Option<CourseException>, StorageError> {
+        let mut connection = self.connection()?;
+        let exception = update_exception(&mut connection, exception_id, update_req)?;
+        if let Some(exception) = &exception {
+            self.publish_exception_course(&mut connection, &exception.course_id)?;
+        }
+        Ok(exception)
+    }
+
+    fn delete_exception(&self, exception_id: &str) -> Result<bool, StorageError> {
+        let mut connection = self.connection()?;
+        // 先取出单次调整，删除后发布所属课程的更新事件
+        let Some(exception) = get_exception_by_id(&mut connection, exception_id)? else {
+            return Ok(false);
+        };
+        let deleted = delete_exception(&mut connection, exception_id)?;
+        if deleted {
+            self.publish_exception_course(&mut connection, &exception.course_id)?;
+        }
+        Ok(deleted)
+    }
+
+    fn list_periods(&self) -> Result<Vec<Period>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_all_periods(&mut connection)?)
+    }
+
+    fn insert_period(
+        &self,
+        scope: &CourseScope,
+        period_req: &CreatePeriodRequest,
+    ) -> Result<Period, StorageError> {
+        self.write_with_course_changes(period_courses(scope), |conn| {
+            insert_period(conn, scope, period_req)
+        })
+    }
+
+    fn update_period(
+        &self,
+        scope: &CourseScope,
+        period_id: &str,
+        update_req: &UpdatePeriodRequest,
+    ) -> Result<Option<Period>, StorageError> {
+        self.write_with_course_changes(period_courses(scope), |conn| {
+            update_period(conn, scope, period_id, update_req)
+        })
+    }
+
+    fn delete_period(&self, scope: &CourseScope, period_id: &str) -> Result<bool, StorageError> {
+        self.write_with_course_changes(period_courses(scope), |conn| {
+            delete_period(conn, scope, period_id)
+        })
+    }
+
+    fn list_reminder_courses(&self) -> Result<Vec<CourseResponse>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_reminder_courses(&mut connection)?)
+    }
+
+    fn list_course_reminders(&self) -> Result<Vec<CourseReminder>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_course_reminders(&mut connection)?)
+    }
+
+    fn get_course_reminder(&self, course_id: &str) -> Result<Option<CourseReminder>, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(get_course_reminder_by_course_id(
+            &mut connection,
+            course_id,
+        )?)
+    }
+
+    fn upsert_course_reminder(
+        &self,
+        reminder: &NewCourseReminder,
+    ) -> Result<CourseReminder, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(upsert_course_reminder(&mut connection, reminder)?)
+    }
+
+    fn delete_course_reminder(&self, course_id: &str) -> Result<bool, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(delete_course_reminder(&mut connection, course_id)?)
+    }
+
+    fn reminder_delivered(
+        &self,
+        course_id: &str,
+        starts_at: NaiveDateTime,
+    ) -> Result<bool, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(diesel::select(diesel::dsl::exists(
+            reminder_deliveries::table
+                .filter(reminder_deliveries::course_id.eq(course_id))
+                .filter(reminder_deliveries::starts_at.eq(starts_at)),
+        ))
+        .get_result(&mut connection)?)
+    }
+
+    fn record_reminder_delivery(
+        &self,
+        course_id: &str,
+        starts_at: NaiveDateTime,
+    ) -> Result<bool, StorageError> {
+        let mut connection = self.connection()?;
+        Ok(insert_reminder_delivery(
+            &mut connection,
+            course_id,
+            starts_at,
+        )?)
+    }
+}
+
+// 读取一批课程的周次，按课程 ID 分组并升序排列
+fn load_weeks(
+    connection: &mut SqliteConnection,
+    course_ids: &[&str],
+) -> Result<HashMap<String, Vec<i32>>, diesel::result::Error> {
+    let rows: Vec<(String, i32)> = course_weeks::table
+        .filter(course_weeks::course_id.eq_any(course_ids))
+        .order((course_weeks::course_id, course_weeks::week))
+        .select((course_weeks::course_id, course_weeks::week))
+        .load(connection)?;
+
+    let mut weeks: HashMap<String, Vec<i32>> = HashMap::new();
+    for (course_id, week) in rows {
+        weeks.entry(course_id).or_default().push(week);
+    }
+    Ok(weeks)
+}
+
+// 为课程补上周次，转换为 API 响应模型
+fn with_weeks(
+    connection: &mut SqliteConnection,
+    results: Vec<Course>,
+) -> Result<Vec<CourseResponse>, diesel::result::Error> {
+    let course_ids: Vec<&str> = results.iter().map(|course| course.id.as_str()).collect();
+    let mut weeks = load_weeks(connection, &course_ids)?;
+
+    Ok(results
+        .into_iter()
+        .map(|course| {
+            let course_weeks = weeks.remove(&course.id).unwrap_or_default();
+            course.into_response(course_weeks)
+        })
+        .collect())
+}
+
+// 用给定周次替换课程原有的周次，需在事务中调用
+fn write_weeks(
+    connection: &mut SqliteConnection,
+    course_id: &str,
+    weeks: &[i32],
+) -> Result<(), diesel::result::Error> {
+    diesel::delete(course_weeks::table.filter(course_weeks::course_id.eq(course_id)))
+        .execute(connection)?;
+
+    let new_weeks: Vec<NewCourseWeek> = weeks
+        .iter()
+        .copied()
+        .collect::<BTreeSet<i32>>()
+        .into_iter()
+        .map(|week| NewCourseWeek { course_id, week })
+        .collect();
+    diesel::insert_into(course_weeks::table)
+        .values(&new_weeks)
+        .execute(connection)?;
+    Ok(())
+}
+
+// scope 内的课程：owner_id 相同（可同为空），指定课表时只取该课表的课程
+fn scoped(scope: &CourseScope) -> courses::BoxedQuery<'static, Sqlite> {
+    let mut sql = courses::table
+        .filter(courses::owner_id.is(scope.owner_id.clone()))
+        .into_boxed();
+    if let Some(timetable_id) = &scope.timetable_id {
+        sql = sql.filter(courses::timetable_id.eq(timetable_id.clone()));
+    }
+    sql
+}
+
+// 不限所属用户读取符合条件的课程，用于发布间接修改课程的事件
+fn load_courses(
+    connection: &mut SqliteConnection,
+    query: courses::BoxedQuery<'static, Sqlite>,
+) -> Result<Vec<CourseResponse>, diesel::result::Error> {
+    let results = query.select(Course::as_select()).load(connection)?;
+    with_weeks(connection, results)
+}
+
+// 教师改名或删除时受影响的课程
+fn teacher_courses(scope: &CourseScope, teacher_id: &str) -> courses::BoxedQuery<'static, Sqlite> {
+    scoped(scope).filter(courses::teacher_id.eq(teacher_id.to_string()))
+}
+
+// 教室改名或删除时受影响的课程
+fn room_courses(scope: &CourseScope, room_id: &str) -> courses::BoxedQuery<'static, Sqlite> {
+    scoped(scope).filter(courses::room_id.eq(room_id.to_string()))
+}
+
+// 节次变化时受影响的课程，即 scope 内所有按节次定义的课程
+fn period_courses(scope: &CourseScope) -> courses::BoxedQuery<'static, Sqlite> {
+    scoped(scope).filter(courses::start_period.is_not_null())
+}
+
+// 共用数据被 scope 之外的课程引用时拒绝删除，删除会通过 ON DELETE SET NULL 改动其他用户的课程
+fn ensure_unused_outside(
+    connection: &mut SqliteConnection,
+    referencing: courses::BoxedQuery<'static, Sqlite>,
+    in_scope: courses::BoxedQuery<'static, Sqlite>,
+    message: &str,
+) -> Result<(), diesel::result::Error> {
+    let total: i64 = referencing.count().get_result(connection)?;
+    let own: i64 = in_scope.count().get_result(connection)?;
+    if total > own {
+        return Err(diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::ForeignKeyViolation,
+            Box::new(message.to_string()),
+        ));
+    }
+    Ok(())
 }
 
-pub fn get_course_by_id(course_id: &str) -> Result<Option<CourseResponse>, diesel::result::Error> {
-    let mut connection = establish_connection();
+// scope 内符合条件的课程 ID，用于只更新操作者自己的课程
+fn scoped_course_ids(
+    connection: &mut SqliteConnection,
+    query: courses::BoxedQuery<'static, Sqlite>,
+) -> Result<Vec<String>, diesel::result::Error> {
+    query.select(courses::id).load(connection)
+}
 
-    let result = courses::table
-        .filter(courses::id.eq(course_id))
+fn load_course(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    course_id: &str,
+) -> Result<Option<CourseResponse>, diesel::result::Error> {
+    let result = scoped(scope)
+        .filter(courses::id.eq(course_id.to_string()))
         .select(Course::as_select())
-        .first(&mut connection)
+        .first(connection)
         .optional()?;
 
     match result {
+        Some(course) => Ok(with_weeks(connection, vec![course])?.pop()),
+        None => Ok(None),
+    }
+}
+
+pub fn get_all_courses(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+) -> Result<Vec<CourseResponse>, diesel::result::Error> {
+    let results = scoped(scope).select(Course::as_select()).load(connection)?;
+
+    let course_responses = with_weeks(connection, results)?;
+
+    info!("📋 从数据库获取到 {} 门课程", course_responses.len());
+    Ok(course_responses)
+}
+
+// 按条件查询课程：列条件、周次和排序交给 SQLite，名称子串在内存中过滤（与内存后端区分大小写的行为一致）
+pub fn find_courses(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    query: &CourseQuery,
+) -> Result<Vec<CourseResponse>, diesel::result::Error> {
+    let mut sql = scoped(scope).select(Course::as_select());
+
+    if let Some(weekday) = query.weekday {
+        sql = sql.filter(courses::weekday.eq(weekday));
+    }
+    if let Some(teacher) = &query.teacher {
+        sql = sql.filter(courses::teacher.eq(teacher.clone()));
+    }
+    if let Some(teacher_id) = &query.teacher_id {
+        sql = sql.filter(courses::teacher_id.eq(teacher_id.clone()));
+    }
+    if let Some(room_id) = &query.room_id {
+        sql = sql.filter(courses::room_id.eq(room_id.clone()));
+    }
+    if let Some(location) = &query.location {
+        sql = sql.filter(courses::location.eq(location.clone()));
+    }
+    if let Some(week) = query.week {
+        sql = sql.filter(
+            courses::id.eq_any(
+                course_weeks::table
+                    .filter(course_weeks::week.eq(week))
+                    .select(course_weeks::course_id),
+            ),
+        );
+    }
+    if let Some(from) = query.from {
+        sql = sql.filter(courses::end_time.gt(from.format("%H:%M:%S").to_string()));
+    }
+    if let Some(to) = query.to {
+        sql = sql.filter(courses::start_time.lt(to.format("%H:%M:%S").to_string()));
+    }
+
+    sql = match (query.sort, query.order) {
+        (CourseSortField::Weekday, SortOrder::Asc) => {
+            sql.order((courses::weekday.asc(), courses::start_time.asc()))
+        }
+        (CourseSortField::Weekday, SortOrder::Desc) => {
+            sql.order((courses::weekday.desc(), courses::start_time.desc()))
+        }
+        (CourseSortField::StartTime, SortOrder::Asc) => sql.order(courses::start_time.asc()),
+        (CourseSortField::StartTime, SortOrder::Desc) => sql.order(courses::start_time.desc()),
+        (CourseSortField::Name, SortOrder::Asc) => sql.order(courses::name.asc()),
+        (CourseSortField::Name, SortOrder::Desc) => sql.order(courses::name.desc()),
+        (CourseSortField::Teacher, SortOrder::Asc) => sql.order(courses::teacher.asc()),
+        (CourseSortField::Teacher, SortOrder::Desc) => sql.order(courses::teacher.desc()),
+        (CourseSortField::Location, SortOrder::Asc) => sql.order(courses::location.asc()),
+        (CourseSortField::Location, SortOrder::Desc) => sql.order(courses::location.desc()),
+    };
+
+    let results = sql.load(connection)?;
+    let course_responses: Vec<CourseResponse> = with_weeks(connection, results)?
+        .into_iter()
+        .filter(|course| query.matches(course))
+        .collect();
+
+    debug!("🔍 按条件查询到 {} 门课程", course_responses.len());
+    Ok(course_responses)
+}
+
+pub fn get_course_by_id(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    course_id: &str,
+) -> Result<Option<CourseResponse>, diesel::result::Error> {
+    match load_course(connection, scope, course_id)? {
         Some(course) => {
             debug!("🔍 找到课程: {} (ID: {})", course.name, course.id);
-            Ok(Some(course.into()))
+            Ok(Some(course))
         }
         None => {
             debug!("❌ 未找到课程 ID: {}", course_id);
@@ -45,12 +853,11 @@ pub fn get_course_by_id(course_id: &str) -> Result<Option<CourseResponse>, diese
 }
 
 pub fn insert_course(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
     course_req: &CreateCourseRequest,
 ) -> Result<CourseResponse, diesel::result::Error> {
-    let mut connection = establish_connection();
-
     let course_id = Uuid::new_v4().to_string();
-    let weeks_json = serde_json::to_string(&course_req.weeks).unwrap_or_default();
 
     let new_course = NewCourse {
         id: course_id.clone(),
@@ -60,48 +867,63 @@ pub fn insert_course(
         weekday: course_req.weekday,
         start_time: course_req.start_time.clone(),
         end_time: course_req.end_time.clone(),
-        weeks: weeks_json,
         color: course_req.color.clone(),
+        semester_id: course_req.semester_id.clone(),
+        campus: course_req.campus.clone(),
+        start_period: course_req.start_period,
+        end_period: course_req.end_period,
+        teacher_id: course_req.teacher_id.clone(),
+        room_id: course_req.room_id.clone(),
+        owner_id: scope.owner_id.clone(),
+        timetable_id: scope.timetable_id.clone(),
     };
 
-    diesel::insert_into(courses::table)
-        .values(&new_course)
-        .execute(&mut connection)?;
+    // 课程与周次在同一事务中写入
+    let inserted_course = connection.transaction(|conn| {
+        diesel::insert_into(courses::table)
+            .values(&new_course)
+            .execute(conn)?;
+        write_weeks(conn, &course_id, &course_req.weeks)?;
 
-    // 获取插入的课程
-    let inserted_course = courses::table
-        .filter(courses::id.eq(&course_id))
-        .select(Course::as_select())
-        .first(&mut connection)?;
+        // 获取插入的课程
+        load_course(conn, scope, &course_id)?.ok_or(diesel::result::Error::NotFound)
+    })?;
 
     info!(
         "💾 课程已存储: {} (ID: {})",
         inserted_course.name, inserted_course.id
     );
-    Ok(inserted_course.into())
+    Ok(inserted_course)
 }
 
 pub fn update_course(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
     course_id: &str,
     update_req: &UpdateCourseRequest,
 ) -> Result<Option<CourseResponse>, diesel::result::Error> {
-    let mut connection = establish_connection();
-
-    // 首先检查课程是否存在
-    let existing_course = courses::table
-        .filter(courses::id.eq(course_id))
-        .select(Course::as_select())
-        .first(&mut connection)
-        .optional()?;
-
-    if existing_course.is_none() {
-        return Ok(None);
-    }
+    // 只更新时间而不指定节次时，课程改为按时间定义
+    let detach_periods = update_req.start_period.is_none()
+        && (update_req.start_time.is_some() || update_req.end_time.is_some());
+    let period_change = |period: Option<i32>| {
+        if detach_periods {
+            Some(None)
+        } else {
+            period.map(Some)
+        }
+    };
 
-    let weeks_json = update_req
-        .weeks
-        .as_ref()
-        .map(|weeks| serde_json::to_string(weeks).unwrap_or_default());
+    // 修改教师或地点时 teacher_id/room_id 一并替换，只给出名称时解除原有关联
+    let teacher_change = if update_req.teacher.is_some() || update_req.teacher_id.is_some() {
+        Some(update_req.teacher_id.clone())
+    } else {
+        None
+    };
+    let room_change = if update_req.location.is_some() || update_req.room_id.is_some() {
+        Some(update_req.room_id.clone())
+    } else {
+        None
+    };
 
     let update_course = UpdateCourse {
         name: update_req.name.clone(),
@@ -110,33 +932,54 @@ pub fn update_course(
         weekday: update_req.weekday,
         start_time: update_req.start_time.clone(),
         end_time: update_req.end_time.clone(),
-        weeks: weeks_json,
         color: update_req.color.clone(),
+        semester_id: update_req.semester_id.clone(),
+        campus: update_req.campus.clone(),
+        start_period: period_change(update_req.start_period),
+        end_period: period_change(update_req.end_period),
+        teacher_id: teacher_change,
+        room_id: room_change,
         updated_at: Utc::now().naive_utc(),
     };
 
-    diesel::update(courses::table.filter(courses::id.eq(course_id)))
+    let updated_course = connection.transaction(|conn| {
+        let updated_rows = diesel::update(
+            courses::table
+                .filter(courses::id.eq(course_id))
+                .filter(courses::id.eq_any(scoped(scope).select(courses::id))),
+        )
         .set(&update_course)
-        .execute(&mut connection)?;
+        .execute(conn)?;
+        if updated_rows == 0 {
+            return Ok(None);
+        }
 
-    // 获取更新后的课程
-    let updated_course = courses::table
-        .filter(courses::id.eq(course_id))
-        .select(Course::as_select())
-        .first(&mut connection)?;
+        if let Some(weeks) = &update_req.weeks {
+            write_weeks(conn, course_id, weeks)?;
+        }
 
-    info!(
-        "🔄 课程已更新: {} (ID: {})",
-        updated_course.name, updated_course.id
-    );
-    Ok(Some(updated_course.into()))
-}
+        // 获取更新后的课程
+        load_course(conn, scope, course_id)
+    })?;
 
-pub fn delete_course(course_id: &str) -> Result<bool, diesel::result::Error> {
-    let mut connection = establish_connection();
+    if let Some(course) = &updated_course {
+        info!("🔄 课程已更新: {} (ID: {})", course.name, course.id);
+    }
+    Ok(updated_course)
+}
 
-    let deleted_rows = diesel::delete(courses::table.filter(courses::id.eq(course_id)))
-        .execute(&mut connection)?;
+pub fn delete_course(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    course_id: &str,
+) -> Result<bool, diesel::result::Error> {
+    // course_weeks、course_exceptions 通过 ON DELETE CASCADE 一并删除
+    let deleted_rows = diesel::delete(
+        courses::table
+            .filter(courses::id.eq(course_id))
+            .filter(courses::id.eq_any(scoped(scope).select(courses::id))),
+    )
+    .execute(connection)?;
 
     if deleted_rows > 0 {
         info!("🗑️ 课程已删除 (ID: {})", course_id);
@@ -147,55 +990,1285 @@ pub fn delete_course(course_id: &str) -> Result<bool, diesel::result::Error> {
     }
 }
 
-pub fn delete_all_courses() -> Result<usize, diesel::result::Error> {
-    let mut connection = establish_connection();
-
-    let deleted_count = diesel::delete(courses::table).execute(&mut connection)?;
+pub fn delete_all_courses(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+) -> Result<usize, diesel::result::Error> {
+    let deleted_count = diesel::delete(
+        courses::table.filter(courses::id.eq_any(scoped(scope).select(courses::id))),
+    )
+    .execute(connection)?;
 
     info!("🗑️ 已删除所有课程，共 {} 门", deleted_count);
     Ok(deleted_count)
 }
 
 pub fn insert_multiple_courses(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
     course_requests: &[CreateCourseRequest],
 ) -> Result<Vec<CourseResponse>, diesel::result::Error> {
-    let mut connection = establish_connection();
-    let mut created_courses = Vec::new();
-
     // 使用事务确保数据一致性
-    connection.transaction::<_, diesel::result::Error, _>(|conn| {
-        for course_req in course_requests {
-            let course_id = Uuid::new_v4().to_string();
-            let weeks_json = serde_json::to_string(&course_req.weeks).unwrap_or_default();
+    let created_courses = connection.transaction::<_, diesel::result::Error, _>(|conn| {
+        course_requests
+            .iter()
+            .map(|course_req| insert_course(conn, scope, course_req))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
 
-            let new_course = NewCourse {
-                id: course_id.clone(),
-                name: course_req.name.clone(),
-                teacher: course_req.teacher.clone(),
-                location: course_req.location.clone(),
-                weekday: course_req.weekday,
-                start_time: course_req.start_time.clone(),
-                end_time: course_req.end_time.clone(),
-                weeks: weeks_json,
-                color: course_req.color.clone(),
-            };
+    info!("💾 批量创建了 {} 门课程", created_courses.len());
+    Ok(created_courses)
+}
 
-            diesel::insert_into(courses::table)
-                .values(&new_course)
-                .execute(conn)?;
+pub fn get_all_semesters(
+    connection: &mut SqliteConnection,
+) -> Result<Vec<Semester>, diesel::result::Error> {
+    let results = semesters::table
+        .order(semesters::start_date.desc())
+        .select(Semester::as_select())
+        .load(connection)?;
 
-            // 获取插入的课程
-            let inserted_course = courses::table
-                .filter(courses::id.eq(&course_id))
-                .select(Course::as_select())
-                .first(conn)?;
+    info!("📅 从数据库获取到 {} 个学期", results.len());
+    Ok(results)
+}
 
-            created_courses.push(inserted_course.into());
-        }
+pub fn get_semester_by_id(
+    connection: &mut SqliteConnection,
+    semester_id: &str,
+) -> Result<Option<Semester>, diesel::result::Error> {
+    semesters::table
+        .filter(semesters::id.eq(semester_id))
+        .select(Semester::as_select())
+        .first(connection)
+        .optional()
+}
 
-        Ok(())
-    })?;
+pub fn get_active_semester(
+    connection: &mut SqliteConnection,
+) -> Result<Option<Semester>, diesel::result::Error> {
+    semesters::table
+        .filter(semesters::is_active.eq(true))
+        .select(Semester::as_select())
+        .first(connection)
+        .optional()
+}
 
-    info!("💾 批量创建了 {} 门课程", created_courses.len());
-    Ok(created_courses)
+pub fn insert_semester(
+    connection: &mut SqliteConnection,
+    semester_req: &CreateSemesterRequest,
+) -> Result<Semester, diesel::result::Error> {
+    let semester_id = Uuid::new_v4().to_string();
+    let new_semester = NewSemester {
+        id: semester_id.clone(),
+        name: semester_req.name.clone(),
+        start_date: semester_req.start_date,
+        total_weeks: semester_req.total_weeks,
+        is_active: semester_req.is_active,
+    };
+
+    // 同一时间只允许一个当前学期
+    let inserted_semester = connection.transaction::<_, diesel::result::Error, _>(|conn| {
+        if new_semester.is_active {
+            diesel::update(semesters::table)
+                .set(semesters::is_active.eq(false))
+                .execute(conn)?;
+        }
+
+        diesel::insert_into(semesters::table)
+            .values(&new_semester)
+            .execute(conn)?;
+
+        semesters::table
+            .filter(semesters::id.eq(&semester_id))
+            .select(Semester::as_select())
+            .first(conn)
+    })?;
+
+    info!(
+        "💾 学期已存储: {} (ID: {})",
+        inserted_semester.name, inserted_semester.id
+    );
+    Ok(inserted_semester)
+}
+
+pub fn update_semester(
+    connection: &mut SqliteConnection,
+    semester_id: &str,
+    update_req: &UpdateSemesterRequest,
+) -> Result<Option<Semester>, diesel::result::Error> {
+    let update_semester = UpdateSemester {
+        name: update_req.name.clone(),
+        start_date: update_req.start_date,
+        total_weeks: update_req.total_weeks,
+        is_active: update_req.is_active,
+        updated_at: Utc::now().naive_utc(),
+    };
+
+    let updated_semester = connection.transaction::<_, diesel::result::Error, _>(|conn| {
+        let existing_semester = semesters::table
+            .filter(semesters::id.eq(semester_id))
+            .select(Semester::as_select())
+            .first(conn)
+            .optional()?;
+
+        if existing_semester.is_none() {
+            return Ok(None);
+        }
+
+        if update_req.is_active == Some(true) {
+            diesel::update(semesters::table.filter(semesters::id.ne(semester_id)))
+                .set(semesters::is_active.eq(false))
+                .execute(conn)?;
+        }
+
+        diesel::update(semesters::table.filter(semesters::id.eq(semester_id)))
+            .set(&update_semester)
+            .execute(conn)?;
+
+        semesters::table
+            .filter(semesters::id.eq(semester_id))
+            .select(Semester::as_select())
+            .first(conn)
+            .optional()
+    })?;
+
+    if let Some(semester) = &updated_semester {
+        info!("🔄 学期已更新: {} (ID: {})", semester.name, semester.id);
+    }
+    Ok(updated_semester)
+}
+
+pub fn delete_semester(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    semester_id: &str,
+) -> Result<bool, diesel::result::Error> {
+    let deleted_rows = connection.transaction::<_, diesel::result::Error, _>(|conn| {
+        ensure_unused_outside(
+            conn,
+            courses::table
+                .filter(courses::semester_id.eq(semester_id.to_string()))
+                .into_boxed(),
+            scoped(scope).filter(courses::semester_id.eq(semester_id.to_string())),
+            "Semester is still used by other users' courses",
+        )?;
+
+        // 解除课程与该学期的关联，课程本身保留
+        diesel::update(courses::table.filter(courses::semester_id.eq(semester_id)))
+            .set(courses::semester_id.eq(None::<String>))
+            .execute(conn)?;
+
+        // 该学期的节次随学期级联删除，课程改用通用作息
+        let deleted_rows =
+            diesel::delete(semesters::table.filter(semesters::id.eq(semester_id))).execute(conn)?;
+        resync_period_times(conn, scope)?;
+        Ok(deleted_rows)
+    })?;
+
+    if deleted_rows > 0 {
+        info!("🗑️ 学期已删除 (ID: {})", semester_id);
+        Ok(true)
+    } else {
+        debug!("❌ 未找到要删除的学期 ID: {}", semester_id);
+        Ok(false)
+    }
+}
+
+pub fn get_all_periods(
+    connection: &mut SqliteConnection,
+) -> Result<Vec<Period>, diesel::result::Error> {
+    periods::table
+        .order((periods::semester_id, periods::campus, periods::period_index))
+        .select(Period::as_select())
+        .load(connection)
+}
+
+// 节次变化后重新计算 scope 内按节次定义的课程时间，需在事务中调用
+fn resync_period_times(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+) -> Result<(), diesel::result::Error> {
+    let all_periods = get_all_periods(connection)?;
+    let results = period_courses(scope)
+        .select(Course::as_select())
+        .load(connection)?;
+
+    let mut updated_count = 0;
+    for mut course in with_weeks(connection, results)? {
+        if !crate::periods::resync_course_times(&all_periods, &mut course) {
+            continue;
+        }
+
+        diesel::update(courses::table.filter(courses::id.eq(&course.id)))
+            .set((
+                courses::start_time.eq(&course.start_time),
+                courses::end_time.eq(&course.end_time),
+                courses::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(connection)?;
+        updated_count += 1;
+    }
+
+    if updated_count > 0 {
+        info!("⏰ 按新的作息更新了 {} 门课程的时间", updated_count);
+    }
+    Ok(())
+}
+
+pub fn insert_period(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    period_req: &CreatePeriodRequest,
+) -> Result<Period, diesel::result::Error> {
+    let period_id = Uuid::new_v4().to_string();
+    let new_period = NewPeriod {
+        id: period_id.clone(),
+        semester_id: period_req.semester_id.clone(),
+        campus: period_req.campus.clone(),
+        period_index: period_req.period_index,
+        start_time: period_req.start_time.clone(),
+        end_time: period_req.end_time.clone(),
+    };
+
+    let inserted_period = connection.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(periods::table)
+            .values(&new_period)
+            .execute(conn)?;
+        resync_period_times(conn, scope)?;
+
+        periods::table
+            .filter(periods::id.eq(&period_id))
+            .select(Period::as_select())
+            .first(conn)
+    })?;
+
+    info!(
+        "💾 节次已存储: 第 {} 节 {}~{} (ID: {})",
+        inserted_period.period_index,
+        inserted_period.start_time,
+        inserted_period.end_time,
+        inserted_period.id
+    );
+    Ok(inserted_period)
+}
+
+pub fn update_period(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    period_id: &str,
+    update_req: &UpdatePeriodRequest,
+) -> Result<Option<Period>, diesel::result::Error> {
+    let update_period = UpdatePeriod {
+        period_index: update_req.period_index,
+        start_time: update_req.start_time.clone(),
+        end_time: update_req.end_time.clone(),
+        updated_at: Utc::now().naive_utc(),
+    };
+
+    let updated_period = connection.transaction::<_, diesel::result::Error, _>(|conn| {
+        let updated_rows = diesel::update(periods::table.filter(periods::id.eq(period_id)))
+            .set(&update_period)
+            .execute(conn)?;
+        if updated_rows == 0 {
+            return Ok(None);
+        }
+        resync_period_times(conn, scope)?;
+
+        periods::table
+            .filter(periods::id.eq(period_id))
+            .select(Period::as_select())
+            .first(conn)
+            .optional()
+    })?;
+
+    if let Some(period) = &updated_period {
+        info!(
+            "🔄 节次已更新: 第 {} 节 {}~{} (ID: {})",
+            period.period_index, period.start_time, period.end_time, period.id
+        );
+    }
+    Ok(updated_period)
+}
+
+pub fn delete_period(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    period_id: &str,
+) -> Result<bool, diesel::result::Error> {
+    let deleted_rows = connection.transaction::<_, diesel::result::Error, _>(|conn| {
+        let deleted_rows =
+            diesel::delete(periods::table.filter(periods::id.eq(period_id))).execute(conn)?;
+        resync_period_times(conn, scope)?;
+        Ok(deleted_rows)
+    })?;
+
+    if deleted_rows > 0 {
+        info!("🗑️ 节次已删除 (ID: {})", period_id);
+        Ok(true)
+    } else {
+        debug!("❌ 未找到要删除的节次 ID: {}", period_id);
+        Ok(false)
+    }
+}
+
+pub fn get_overrides(
+    connection: &mut SqliteConnection,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<CalendarOverride>, diesel::result::Error> {
+    let mut sql = calendar_overrides::table
+        .select(CalendarOverride::as_select())
+        .into_boxed();
+    if let Some(from) = from {
+        sql = sql.filter(calendar_overrides::date.ge(from));
+    }
+    if let Some(to) = to {
+        sql = sql.filter(calendar_overrides::date.le(to));
+    }
+
+    sql.order(calendar_overrides::date.asc()).load(connection)
+}
+
+pub fn get_override_by_id(
+    connection: &mut SqliteConnection,
+    override_id: &str,
+) -> Result<Option<CalendarOverride>, diesel::result::Error> {
+    calendar_overrides::table
+        .filter(calendar_overrides::id.eq(override_id))
+        .select(CalendarOverride::as_select())
+        .first(connection)
+        .optional()
+}
+
+pub fn get_override_by_date(
+    connection: &mut SqliteConnection,
+    date: NaiveDate,
+) -> Result<Option<CalendarOverride>, diesel::result::Error> {
+    calendar_overrides::table
+        .filter(calendar_overrides::date.eq(date))
+        .select(CalendarOverride::as_select())
+        .first(connection)
+        .optional()
+}
+
+pub fn insert_override(
+    connection: &mut SqliteConnection,
+    override_req: &CreateCalendarOverrideRequest,
+) -> Result<CalendarOverride, diesel::result::Error> {
+    let override_id = Uuid::new_v4().to_string();
+    let new_override = NewCalendarOverride {
+        id: override_id.clone(),
+        date: override_req.date,
+        kind: override_req.kind.as_str().to_string(),
+        weekday: override_req.weekday,
+        week: override_req.week,
+        note: override_req.note.clone(),
+    };
+
+    diesel::insert_into(calendar_overrides::table)
+        .values(&new_override)
+        .execute(connection)?;
+
+    let inserted_override = calendar_overrides::table
+        .filter(calendar_overrides::id.eq(&override_id))
+        .select(CalendarOverride::as_select())
+        .first(connection)?;
+
+    info!(
+        "💾 日历调整已存储: {} {} (ID: {})",
+        inserted_override.date, inserted_override.kind, inserted_override.id
+    );
+    Ok(inserted_override)
+}
+
+pub fn update_override(
+    connection: &mut SqliteConnection,
+    override_id: &str,
+    update_req: &UpdateCalendarOverrideRequest,
+) -> Result<Option<CalendarOverride>, diesel::result::Error> {
+    // 修改 kind 时 weekday/week 整体替换
+    let replace_slot = update_req.kind.is_some();
+    let slot_change = |value: Option<i32>| {
+        if replace_slot {
+            Some(value)
+        } else {
+            value.map(Some)
+        }
+    };
+
+    let update_override = UpdateCalendarOverride {
+        date: update_req.date,
+        kind: update_req.kind.map(|kind| kind.as_str().to_string()),
+        weekday: slot_change(update_req.weekday),
+        week: slot_change(update_req.week),
+        note: update_req.note.clone(),
+        updated_at: Utc::now().naive_utc(),
+    };
+
+    let updated_rows =
+        diesel::update(calendar_overrides::table.filter(calendar_overrides::id.eq(override_id)))
+            .set(&update_override)
+            .execute(connection)?;
+    if updated_rows == 0 {
+        return Ok(None);
+    }
+
+    let updated_override = get_override_by_id(connection, override_id)?;
+    if let Some(calendar_override) = &updated_override {
+        info!(
+            "🔄 日历调整已更新: {} {} (ID: {})",
+            calendar_override.date, calendar_override.kind, calendar_override.id
+        );
+    }
+    Ok(updated_override)
+}
+
+pub fn delete_override(
+    connection: &mut SqliteConnection,
+    override_id: &str,
+) -> Result<bool, diesel::result::Error> {
+    let deleted_rows =
+        diesel::delete(calendar_overrides::table.filter(calendar_overrides::id.eq(override_id)))
+            .execute(connection)?;
+
+    if deleted_rows > 0 {
+        info!("🗑️ 日历调整已删除 (ID: {})", override_id);
+        Ok(true)
+    } else {
+        debug!("❌ 未找到要删除的日历调整 ID: {}", override_id);
+        Ok(false)
+    }
+}
+
+pub fn get_exceptions(
+    connection: &mut SqliteConnection,
+    query: &CourseExceptionQuery,
+) -> Result<Vec<CourseException>, diesel::result::Error> {
+    let mut sql = course_exceptions::table
+        .select(CourseException::as_select())
+        .into_boxed();
+    if let Some(course_id) = &query.course_id {
+        sql = sql.filter(course_exceptions::course_id.eq(course_id));
+    }
+    if let Some(week) = query.week {
+        sql = sql.filter(course_exceptions::week.eq(week));
+    }
+
+    sql.order((
+        course_exceptions::week.asc(),
+        course_exceptions::created_at.asc(),
+    ))
+    .load(connection)
+}
+
+pub fn get_exception_by_id(
+    connection: &mut SqliteConnection,
+    exception_id: &str,
+) -> Result<Option<CourseException>, diesel::result::Error> {
+    course_exceptions::table
+        .filter(course_exceptions::id.eq(exception_id))
+        .select(CourseException::as_select())
+        .first(connection)
+        .optional()
+}
+
+pub fn insert_exception(
+    connection: &mut SqliteConnection,
+    exception_req: &CreateCourseExceptionRequest,
+) -> Result<CourseException, diesel::result::Error> {
+    let exception_id = Uuid::new_v4().to_string();
+    let new_exception = NewCourseException {
+        id: exception_id.clone(),
+        course_id: exception_req.course_id.clone(),
+        week: exception_req.week,
+        action: exception_req.action.as_str().to_string(),
+        weekday: exception_req.weekday,
+        start_time: exception_req.start_time.clone(),
+        end_time: exception_req.end_time.clone(),
+        location: exception_req.location.clone(),
+        note: exception_req.note.clone(),
+    };
+
+    diesel::insert_into(course_exceptions::table)
+        .values(&new_exception)
+        .execute(connection)?;
+
+    let inserted_exception = course_exceptions::table
+        .filter(course_exceptions::id.eq(&exception_id))
+        .select(CourseException::as_select())
+        .first(connection)?;
+
+    info!(
+        "💾 课程单次调整已存储: 课程 {} 第 {} 周 {} (ID: {})",
+        inserted_exception.course_id,
+        inserted_exception.week,
+        inserted_exception.action,
+        inserted_exception.id
+    );
+    Ok(inserted_exception)
+}
+
+pub fn update_exception(
+    connection: &mut SqliteConnection,
+    exception_id: &str,
+    update_req: &UpdateCourseExceptionRequest,
+) -> Result<Option<CourseException>, diesel::result::Error> {
+    // 修改 action 时调整内容整体替换
+    let replace_details = update_req.action.is_some();
+    let weekday = if replace_details {
+        Some(update_req.weekday)
+    } else {
+        update_req.weekday.map(Some)
+    };
+    let detail_change = |value: &Option<String>| {
+        if replace_details {
+            Some(value.clone())
+        } else {
+            value.clone().map(Some)
+        }
+    };
+
+    let update_exception = UpdateCourseException {
+        week: update_req.week,
+        action: update_req.action.map(|action| action.as_str().to_string()),
+        weekday,
+        start_time: detail_change(&update_req.start_time),
+        end_time: detail_change(&update_req.end_time),
+        location: detail_change(&update_req.location),
+        note: update_req.note.clone(),
+        updated_at: Utc::now().naive_utc(),
+    };
+
+    let updated_rows =
+        diesel::update(course_exceptions::table.filter(course_exceptions::id.eq(exception_id)))
+            .set(&update_exception)
+            .execute(connection)?;
+    if updated_rows == 0 {
+        return Ok(None);
+    }
+
+    let updated_exception = get_exception_by_id(connection, exception_id)?;
+    if let Some(exception) = &updated_exception {
+        info!(
+            "🔄 课程单次调整已更新: 课程 {} 第 {} 周 {} (ID: {})",
+            exception.course_id, exception.week, exception.action, exception.id
+        );
+    }
+    Ok(updated_exception)
+}
+
+pub fn delete_exception(
+    connection: &mut SqliteConnection,
+    exception_id: &str,
+) -> Result<bool, diesel::result::Error> {
+    let deleted_rows =
+        diesel::delete(course_exceptions::table.filter(course_exceptions::id.eq(exception_id)))
+            .execute(connection)?;
+
+    if deleted_rows > 0 {
+        info!("🗑️ 课程单次调整已删除 (ID: {})", exception_id);
+        Ok(true)
+    } else {
+        debug!("❌ 未找到要删除的课程单次调整 ID: {}", exception_id);
+        Ok(false)
+    }
+}
+
+pub fn get_all_teachers(
+    connection: &mut SqliteConnection,
+) -> Result<Vec<Teacher>, diesel::result::Error> {
+    teachers::table
+        .select(Teacher::as_select())
+        .order(teachers::name.asc())
+        .load(connection)
+}
+
+pub fn get_teacher_by_id(
+    connection: &mut SqliteConnection,
+    teacher_id: &str,
+) -> Result<Option<Teacher>, diesel::result::Error> {
+    teachers::table
+        .filter(teachers::id.eq(teacher_id))
+        .select(Teacher::as_select())
+        .first(connection)
+        .optional()
+}
+
+pub fn insert_teacher(
+    connection: &mut SqliteConnection,
+    teacher_req: &CreateTeacherRequest,
+) -> Result<Teacher, diesel::result::Error> {
+    let teacher_id = Uuid::new_v4().to_string();
+    let new_teacher = NewTeacher {
+        id: teacher_id.clone(),
+        name: teacher_req.name.clone(),
+        email: teacher_req.email.clone(),
+        phone: teacher_req.phone.clone(),
+        office: teacher_req.office.clone(),
+        office_hours: teacher_req.office_hours.clone(),
+    };
+
+    diesel::insert_into(teachers::table)
+        .values(&new_teacher)
+        .execute(connection)?;
+
+    let inserted_teacher = teachers::table
+        .filter(teachers::id.eq(&teacher_id))
+        .select(Teacher::as_select())
+        .first(connection)?;
+
+    info!(
+        "💾 教师已存储: {} (ID: {})",
+        inserted_teacher.name, inserted_teacher.id
+    );
+    Ok(inserted_teacher)
+}
+
+pub fn update_teacher(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    teacher_id: &str,
+    update_req: &UpdateTeacherRequest,
+) -> Result<Option<Teacher>, diesel::result::Error> {
+    let update_teacher = UpdateTeacher {
+        name: update_req.name.clone(),
+        email: update_req.email.clone(),
+        phone: update_req.phone.clone(),
+        office: update_req.office.clone(),
+        office_hours: update_req.office_hours.clone(),
+        updated_at: Utc::now().naive_utc(),
+    };
+
+    // 教师姓名与关联课程的 teacher 在同一事务中更新
+    let updated_teacher = connection.transaction(|conn| {
+        let updated_rows = diesel::update(teachers::table.filter(teachers::id.eq(teacher_id)))
+            .set(&update_teacher)
+            .execute(conn)?;
+        if updated_rows == 0 {
+            return Ok(None);
+        }
+
+        if let Some(name) = &update_req.name {
+            let course_ids = scoped_course_ids(conn, teacher_courses(scope, teacher_id))?;
+            diesel::update(courses::table.filter(courses::id.eq_any(course_ids)))
+                .set(courses::teacher.eq(name))
+                .execute(conn)?;
+        }
+        get_teacher_by_id(conn, teacher_id)
+    })?;
+
+    if let Some(teacher) = &updated_teacher {
+        info!("🔄 教师已更新: {} (ID: {})", teacher.name, teacher.id);
+    }
+    Ok(updated_teacher)
+}
+
+pub fn delete_teacher(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    teacher_id: &str,
+) -> Result<bool, diesel::result::Error> {
+    // courses.teacher_id 通过 ON DELETE SET NULL 解除关联，教师姓名保留
+    let deleted_rows = connection.transaction(|conn| {
+        ensure_unused_outside(
+            conn,
+            courses::table
+                .filter(courses::teacher_id.eq(teacher_id.to_string()))
+                .into_boxed(),
+            teacher_courses(scope, teacher_id),
+            "Teacher is still used by other users' courses",
+        )?;
+        diesel::delete(teachers::table.filter(teachers::id.eq(teacher_id))).execute(conn)
+    })?;
+
+    if deleted_rows > 0 {
+        info!("🗑️ 教师已删除 (ID: {})", teacher_id);
+        Ok(true)
+    } else {
+        debug!("❌ 未找到要删除的教师 ID: {}", teacher_id);
+        Ok(false)
+    }
+}
+
+pub fn get_all_rooms(
+    connection: &mut SqliteConnection,
+) -> Result<Vec<Room>, diesel::result::Error> {
+    rooms::table
+        .select(Room::as_select())
+        .order((rooms::building.asc(), rooms::room_number.asc()))
+        .load(connection)
+}
+
+pub fn get_room_by_id(
+    connection: &mut SqliteConnection,
+    room_id: &str,
+) -> Result<Option<Room>, diesel::result::Error> {
+    rooms::table
+        .filter(rooms::id.eq(room_id))
+        .select(Room::as_select())
+        .first(connection)
+        .optional()
+}
+
+pub fn insert_room(
+    connection: &mut SqliteConnection,
+    room_req: &CreateRoomRequest,
+) -> Result<Room, diesel::result::Error> {
+    let room_id = Uuid::new_v4().to_string();
+    let new_room = NewRoom {
+        id: room_id.clone(),
+        building: room_req.building.clone(),
+        room_number: room_req.room_number.clone(),
+        capacity: room_req.capacity,
+        campus: room_req.campus.clone(),
+    };
+
+    diesel::insert_into(rooms::table)
+        .values(&new_room)
+        .execute(connection)?;
+
+    let inserted_room = rooms::table
+        .filter(rooms::id.eq(&room_id))
+        .select(Room::as_select())
+        .first(connection)?;
+
+    info!(
+        "💾 教室已存储: {} (ID: {})",
+        inserted_room.label(),
+        inserted_room.id
+    );
+    Ok(inserted_room)
+}
+
+pub fn update_room(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    room_id: &str,
+    update_req: &UpdateRoomRequest,
+) -> Result<Option<Room>, diesel::result::Error> {
+    let update_room = UpdateRoom {
+        building: update_req.building.clone(),
+        room_number: update_req.room_number.clone(),
+        capacity: update_req.capacity,
+        campus: update_req.campus.clone(),
+        updated_at: Utc::now().naive_utc(),
+    };
+
+    // 教室名称与关联课程的 location 在同一事务中更新
+    let updated_room = connection.transaction::<_, diesel::result::Error, _>(|conn| {
+        let updated_rows = diesel::update(rooms::table.filter(rooms::id.eq(room_id)))
+            .set(&update_room)
+            .execute(conn)?;
+        if updated_rows == 0 {
+            return Ok(None);
+        }
+
+        let room = get_room_by_id(conn, room_id)?;
+        if let Some(room) = &room {
+            if update_req.building.is_some() || update_req.room_number.is_some() {
+                let course_ids = scoped_course_ids(conn, room_courses(scope, room_id))?;
+                diesel::update(courses::table.filter(courses::id.eq_any(course_ids)))
+                    .set(courses::location.eq(room.label()))
+                    .execute(conn)?;
+            }
+        }
+        Ok(room)
+    })?;
+
+    if let Some(room) = &updated_room {
+        info!("🔄 教室已更新: {} (ID: {})", room.label(), room.id);
+    }
+    Ok(updated_room)
+}
+
+pub fn delete_room(
+    connection: &mut SqliteConnection,
+    scope: &CourseScope,
+    room_id: &str,
+) -> Result<bool, diesel::result::Error> {
+    // courses.room_id 通过 ON DELETE SET NULL 解除关联，教室名称保留
+    let deleted_rows = connection.transaction(|conn| {
+        ensure_unused_outside(
+            conn,
+            courses::table
+                .filter(courses::room_id.eq(room_id.to_string()))
+                .into_boxed(),
+            room_courses(scope, room_id),
+            "Room is still used by other users' courses",
+        )?;
+        diesel::delete(rooms::table.filter(rooms::id.eq(room_id))).execute(conn)
+    })?;
+
+    if deleted_rows > 0 {
+        info!("🗑️ 教室已删除 (ID: {})", room_id);
+        Ok(true)
+    } else {
+        debug!("❌ 未找到要删除的教室 ID: {}", room_id);
+        Ok(false)
+    }
+}
+
+// 默认课表在前，其余按创建顺序
+pub fn get_timetables(
+    connection: &mut SqliteConnection,
+    owner_id: Option<&str>,
+) -> Result<Vec<Timetable>, diesel::result::Error> {
+    timetables::table
+        .filter(timetables::owner_id.is(owner_id))
+        .order((timetables::is_default.desc(), timetables::created_at.asc()))
+        .select(Timetable::as_select())
+        .load(connection)
+}
+
+pub fn get_timetable_by_id(
+    connection: &mut SqliteConnection,
+    owner_id: Option<&str>,
+    timetable_id: &str,
+) -> Result<Option<Timetable>, diesel::result::Error> {
+    timetables::table
+        .filter(timetables::id.eq(timetable_id))
+        .filter(timetables::owner_id.is(owner_id))
+        .select(Timetable::as_select())
+        .first(connection)
+        .optional()
+}
+
+pub fn get_or_create_default_timetable(
+    connection: &mut SqliteConnection,
+    owner_id: Option<&str>,
+) -> Result<Timetable, diesel::result::Error> {
+    // 先插入再查询：唯一索引 idx_timetables_default 保证每个用户只有一张默认课表，
+    // 并发的首次请求中后到的插入被忽略，随后查询到先创建的那张，不会重复创建或返回唯一约束错误
+    connection.transaction(|conn| {
+        let new_timetable = NewTimetable {
+            id: Uuid::new_v4().to_string(),
+            owner_id: owner_id.map(str::to_string),
+            name: DEFAULT_TIMETABLE_NAME.to_string(),
+            is_default: true,
+        };
+        let inserted = diesel::insert_or_ignore_into(timetables::table)
+            .values(&new_timetable)
+            .execute(conn)?;
+
+        let timetable = timetables::table
+            .filter(timetables::owner_id.is(owner_id))
+            .filter(timetables::is_default.eq(true))
+            .select(Timetable::as_select())
+            .first(conn)?;
+        if inserted > 0 {
+            info!("📒 已创建默认课表 (ID: {})", timetable.id);
+        }
+        Ok(timetable)
+    })
+}
+
+pub fn insert_timetable(
+    connection: &mut SqliteConnection,
+    owner_id: Option<&str>,
+    name: &str,
+    is_default: bool,
+) -> Result<Timetable, diesel::result::Error> {
+    let timetable_id = Uuid::new_v4().to_string();
+
+    let new_timetable = NewTimetable {
+        id: timetable_id.clone(),
+        owner_id: owner_id.map(str::to_string),
+        name: name.to_string(),
+        is_default,
+    };
+
+    diesel::insert_into(timetables::table)
+        .values(&new_timetable)
+        .execute(connection)?;
+
+    let inserted_timetable = timetables::table
+        .filter(timetables::id.eq(&timetable_id))
+        .select(Timetable::as_select())
+        .first(connection)?;
+
+    debug!(
+        "💾 课表已存储: {} (ID: {})",
+        inserted_timetable.name, inserted_timetable.id
+    );
+    Ok(inserted_timetable)
+}
+
+pub fn update_timetable(
+    connection: &mut SqliteConnection,
+    owner_id: Option<&str>,
+    timetable_id: &str,
+    update_req: &UpdateTimetableRequest,
+) -> Result<Option<Timetable>, diesel::result::Error> {
+    let now = Utc::now().naive_utc();
+    let update_timetable = UpdateTimetable {
+        name: update_req.name.clone(),
+        is_default: update_req.is_default,
+        updated_at: now,
+    };
+
+    // 先取消原默认课表再设置新的，避免违反每个用户只有一个默认课表的唯一索引
+    let updated_timetable = connection.transaction(|conn| {
+        if get_timetable_by_id(conn, owner_id, timetable_id)?.is_none() {
+            return Ok(None);
+        }
+
+        if update_req.is_default == Some(true) {
+            diesel::update(
+                timetables::table
+                    .filter(timetables::owner_id.is(owner_id))
+                    .filter(timetables::is_default.eq(true)),
+            )
+            .set((
+                timetables::is_default.eq(false),
+                timetables::updated_at.eq(now),
+            ))
+            .execute(conn)?;
+        }
+
+        diesel::update(timetables::table.filter(timetables::id.eq(timetable_id)))
+            .set(&update_timetable)
+            .execute(conn)?;
+        get_timetable_by_id(conn, owner_id, timetable_id)
+    })?;
+
+    if let Some(timetable) = &updated_timetable {
+        debug!("🔄 课表已更新: {} (ID: {})", timetable.name, timetable.id);
+    }
+    Ok(updated_timetable)
+}
+
+pub fn delete_timetable(
+    connection: &mut SqliteConnection,
+    owner_id: Option<&str>,
+    timetable_id: &str,
+) -> Result<bool, diesel::result::Error> {
+    // 课表的课程通过 ON DELETE CASCADE 一并删除
+    let deleted_rows = diesel::delete(
+        timetables::table
+            .filter(timetables::id.eq(timetable_id))
+            .filter(timetables::owner_id.is(owner_id)),
+    )
+    .execute(connection)?;
+
+    if deleted_rows > 0 {
+        info!("🗑️ 课表已删除 (ID: {})", timetable_id);
+        Ok(true)
+    } else {
+        debug!("❌ 未找到要删除的课表 ID: {}", timetable_id);
+        Ok(false)
+    }
+}
+
+// 复制课表：课程使用新 ID，周次和课程单次调整随课程一并复制
+pub fn duplicate_timetable(
+    connection: &mut SqliteConnection,
+    owner_id: Option<&str>,
+    timetable_id: &str,
+    name: &str,
+) -> Result<Option<Timetable>, diesel::result::Error> {
+    connection.transaction(|conn| {
+        if get_timetable_by_id(conn, owner_id, timetable_id)?.is_none() {
+            return Ok(None);
+        }
+        let timetable = insert_timetable(conn, owner_id, name, false)?;
+
+        let source_courses: Vec<Course> = courses::table
+            .filter(courses::timetable_id.eq(timetable_id))
+            .select(Course::as_select())
+            .load(conn)?;
+        let course_ids: Vec<&str> = source_courses
+            .iter()
+            .map(|course| course.id.as_str())
+            .collect();
+        let mut weeks = load_weeks(conn, &course_ids)?;
+        let source_exceptions: Vec<CourseException> = course_exceptions::table
+            .filter(course_exceptions::course_id.eq_any(&course_ids))
+            .select(CourseException::as_select())
+            .load(conn)?;
+
+        let mut id_map: HashMap<&str, String> = HashMap::new();
+        for course in &source_courses {
+            let course_id = Uuid::new_v4().to_string();
+            let new_course = NewCourse {
+                id: course_id.clone(),
+                name: course.name.clone(),
+                teacher: course.teacher.clone(),
+                location: course.location.clone(),
+                weekday: course.weekday,
+                start_time: course.start_time.clone(),
+                end_time: course.end_time.clone(),
+                color: course.color.clone(),
+                semester_id: course.semester_id.clone(),
+                campus: course.campus.clone(),
+                start_period: course.start_period,
+                end_period: course.end_period,
+                teacher_id: course.teacher_id.clone(),
+                room_id: course.room_id.clone(),
+                owner_id: course.owner_id.clone(),
+                timetable_id: Some(timetable.id.clone()),
+            };
+            diesel::insert_into(courses::table)
+                .values(&new_course)
+                .execute(conn)?;
+            write_weeks(
+                conn,
+                &course_id,
+                &weeks.remove(&course.id).unwrap_or_default(),
+            )?;
+            id_map.insert(course.id.as_str(), course_id);
+        }
+
+        let new_exceptions: Vec<NewCourseException> = source_exceptions
+            .into_iter()
+            .map(|exception| NewCourseException {
+                id: Uuid::new_v4().to_string(),
+                course_id: id_map[exception.course_id.as_str()].clone(),
+                week: exception.week,
+                action: exception.action,
+                weekday: exception.weekday,
+                start_time: exception.start_time,
+                end_time: exception.end_time,
+                location: exception.location,
+                note: exception.note,
+            })
+            .collect();
+        diesel::insert_into(course_exceptions::table)
+            .values(&new_exceptions)
+            .execute(conn)?;
+
+        info!(
+            "📋 课表已复制: {} -> {} ({} 门课程)",
+            timetable_id,
+            timetable.id,
+            source_courses.len()
+        );
+        Ok(Some(timetable))
+    })
+}
+
+pub fn insert_share_link(
+    connection: &mut SqliteConnection,
+    share_link: &NewShareLink,
+) -> Result<ShareLink, diesel::result::Error> {
+    diesel::insert_into(share_links::table)
+        .values(share_link)
+        .execute(connection)?;
+
+    let inserted_share_link = share_links::table
+        .filter(share_links::id.eq(&share_link.id))
+        .select(ShareLink::as_select())
+        .first(connection)?;
+
+    info!(
+        "💾 分享链接已存储: 课表 {} (ID: {})",
+        inserted_share_link.timetable_id, inserted_share_link.id
+    );
+    Ok(inserted_share_link)
+}
+
+pub fn get_share_links(
+    connection: &mut SqliteConnection,
+    owner_id: Option<&str>,
+) -> Result<Vec<ShareLink>, diesel::result::Error> {
+    share_links::table
+        .filter(share_links::owner_id.is(owner_id))
+        .order(share_links::created_at.asc())
+        .select(ShareLink::as_select())
+        .load(connection)
+}
+
+pub fn get_share_link_by_token(
+    connection: &mut SqliteConnection,
+    token: &str,
+) -> Result<Option<ShareLink>, diesel::result::Error> {
+    share_links::table
+        .filter(share_links::token.eq(token))
+        .filter(
+            share_links::expires_at
+                .is_null()
+                .or(share_links::expires_at.gt(Utc::now().naive_utc())),
+        )
+        .select(ShareLink::as_select())
+        .first(connection)
+        .optional()
+}
+
+pub fn delete_share_link(
+    connection: &mut SqliteConnection,
+    owner_id: Option<&str>,
+    share_link_id: &str,
+) -> Result<bool, diesel::result::Error> {
+    let deleted_rows = diesel::delete(
+        share_links::table
+            .filter(share_links::id.eq(share_link_id))
+            .filter(share_links::owner_id.is(owner_id)),
+    )
+    .execute(connection)?;
+
+    if deleted_rows > 0 {
+        info!("🗑️ 分享链接已撤销 (ID: {})", share_link_id);
+        Ok(true)
+    } else {
+        debug!("❌ 未找到要撤销的分享链接 ID: {}", share_link_id);
+        Ok(false)
+    }
+}
+
+pub fn insert_user(
+    connection: &mut SqliteConnection,
+    register_req: &RegisterRequest,
+    password_hash: &str,
+) -> Result<User, diesel::result::Error> {
+    let user_id = Uuid::new_v4().to_string();
+
+    let new_user = NewUser {
+        id: user_id.clone(),
+        username: register_req.username.clone(),
+        password_hash: password_hash.to_string(),
+        display_name: register_req.display_name.clone(),
+    };
+
+    diesel::insert_into(users::table)
+        .values(&new_user)
+        .execute(connection)?;
+
+    let user = users::table
+        .filter(users::id.eq(&user_id))
+        .select(User::as_select())
+        .first(connection)?;
+
+    info!("💾 用户已注册: {} (ID: {})", user.username, user.id);
+    Ok(user)
+}
+
+// users.username 声明了 COLLATE NOCASE，比较时不区分大小写
+pub fn get_user_by_username(
+    connection: &mut SqliteConnection,
+    username: &str,
+) -> Result<Option<User>, diesel::result::Error> {
+    users::table
+        .filter(users::username.eq(username))
+        .select(User::as_select())
+        .first(connection)
+        .optional()
+}
+
+pub fn insert_session(
+    connection: &mut SqliteConnection,
+    session: &NewUserSession,
+) -> Result<(), diesel::result::Error> {
+    diesel::insert_into(user_sessions::table)
+        .values(session)
+        .execute(connection)?;
+
+    debug!("🔑 已创建登录会话: 用户 ID={}", session.user_id);
+    Ok(())
+}
+
+pub fn get_session_user(
+    connection: &mut SqliteConnection,
+    token_hash: &str,
+) -> Result<Option<User>, diesel::result::Error> {
+    user_sessions::table
+        .inner_join(users::table)
+        .filter(user_sessions::token_hash.eq(token_hash))
+        .filter(user_sessions::expires_at.gt(Utc::now().naive_utc()))
+        .select(User::as_select())
+        .first(connection)
+        .optional()
+}
+
+pub fn delete_session(
+    connection: &mut SqliteConnection,
+    token_hash: &str,
+) -> Result<bool, diesel::result::Error> {
+    let deleted_rows =
+        diesel::delete(user_sessions::table.filter(user_sessions::token_hash.eq(token_hash)))
+            .execute(connection)?;
+    Ok(deleted_rows > 0)
+}
+
+// 各用户默认课表中的课程，提醒调度只处理这些课程
+pub fn get_reminder_courses(
+    connection: &mut SqliteConnection,
+) -> Result<Vec<CourseResponse>, diesel::result::Error> {
+    let default_timetables = timetables::table
+        .filter(timetables::is_default.eq(true))
+        .select(timetables::id);
+    let results = courses::table
+        .filter(courses::timetable_id.eq_any(default_timetables.nullable()))
+        .select(Course::as_select())
+        .load(connection)?;
+    with_weeks(connection, results)
+}
+
+pub fn get_course_reminders(
+    connection: &mut SqliteConnection,
+) -> Result<Vec<CourseReminder>, diesel::result::Error> {
+    course_reminders::table
+        .select(CourseReminder::as_select())
+        .load(connection)
+}
+
+pub fn get_course_reminder_by_course_id(
+    connection: &mut SqliteConnection,
+    course_id: &str,
+) -> Result<Option<CourseReminder>, diesel::result::Error> {
+    course_reminders::table
+        .filter(course_reminders::course_id.eq(course_id))
+        .select(CourseReminder::as_select())
+        .first(connection)
+        .optional()
+}
+
+pub fn upsert_course_reminder(
+    connection: &mut SqliteConnection,
+    reminder: &NewCourseReminder,
+) -> Result<CourseReminder, diesel::result::Error> {
+    diesel::insert_into(course_reminders::table)
+        .values(reminder)
+        .on_conflict(course_reminders::course_id)
+        .do_update()
+        .set((
+            course_reminders::enabled.eq(reminder.enabled),
+            course_reminders::minutes_before.eq(reminder.minutes_before),
+            course_reminders::updated_at.eq(Utc::now().naive_utc()),
+        ))
+        .execute(connection)?;
+
+    let saved_reminder = course_reminders::table
+        .filter(course_reminders::course_id.eq(&reminder.course_id))
+        .select(CourseReminder::as_select())
+        .first(connection)?;
+
+    debug!(
+        "⏰ 课程提醒设置已保存 (课程ID: {})",
+        saved_reminder.course_id
+    );
+    Ok(saved_reminder)
+}
+
+pub fn delete_course_reminder(
+    connection: &mut SqliteConnection,
+    course_id: &str,
+) -> Result<bool, diesel::result::Error> {
+    let deleted_rows =
+        diesel::delete(course_reminders::table.filter(course_reminders::course_id.eq(course_id)))
+            .execute(connection)?;
+    Ok(deleted_rows > 0)
+}
+
+// 主键冲突时忽略插入，插入行数为 0 即说明已经提醒过
+pub fn insert_reminder_delivery(
+    connection: &mut SqliteConnection,
+    course_id: &str,
+    starts_at: NaiveDateTime,
+) -> Result<bool, diesel::result::Error> {
+    let inserted_rows = diesel::insert_or_ignore_into(reminder_deliveries::table)
+        .values(NewReminderDelivery {
+            course_id,
+            starts_at,
+        })
+        .execute(connection)?;
+    Ok(inserted_rows > 0)
 }
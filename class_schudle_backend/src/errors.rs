@@ -0,0 +1,154 @@
+use actix_web::error::{JsonPayloadError, PathError, QueryPayloadError};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use log::{error, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::storage::StorageError;
+use crate::validation::FieldError;
+
+// 统一的 API 错误，响应体为 {code, message, details}，客户端可根据 code 分支处理
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    // 请求字段校验失败，details 中列出每个字段的错误
+    Validation(Vec<FieldError>),
+    // 未登录、令牌无效或已过期，以及用户名密码错误
+    Unauthorized(String),
+    // 已登录但无权执行该操作
+    Forbidden(String),
+    NotFound(String),
+    // 尚未设置当前学期，客户端可据此退回不按学期计算的方式
+    NoActiveSemester,
+    Conflict {
+        message: String,
+        details: Option<Value>,
+    },
+    // 违反数据库约束（CHECK、外键、非空）
+    ConstraintViolation(String),
+    Internal(String),
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    pub details: Option<Value>,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "BAD_REQUEST",
+            ApiError::Validation(_) => "VALIDATION_FAILED",
+            ApiError::Unauthorized(_) => "UNAUTHORIZED",
+            ApiError::Forbidden(_) => "FORBIDDEN",
+            ApiError::NotFound(_) => "NOT_FOUND",
+            ApiError::NoActiveSemester => "NO_ACTIVE_SEMESTER",
+            ApiError::Conflict { .. } => "CONFLICT",
+            ApiError::ConstraintViolation(_) => "CONSTRAINT_VIOLATION",
+            ApiError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::BadRequest(message)
+            | ApiError::Unauthorized(message)
+            | ApiError::Forbidden(message)
+            | ApiError::NotFound(message)
+            | ApiError::Conflict { message, .. }
+            | ApiError::ConstraintViolation(message)
+            | ApiError::Internal(message) => message,
+            ApiError::Validation(_) => "Request validation failed",
+            ApiError::NoActiveSemester => "No active semester",
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        match self {
+            ApiError::Conflict { details, .. } => details.clone(),
+            ApiError::Validation(errors) => Some(json!({ "fields": errors })),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) | ApiError::NoActiveSemester => StatusCode::NOT_FOUND,
+            ApiError::Conflict { .. } => StatusCode::CONFLICT,
+            ApiError::ConstraintViolation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ErrorBody {
+            code: self.code(),
+            message: self.message().to_string(),
+            details: self.details(),
+        })
+    }
+}
+
+impl From<StorageError> for ApiError {
+    fn from(e: StorageError) -> Self {
+        match e {
+            StorageError::Query(DieselError::NotFound) => {
+                ApiError::NotFound("Resource not found".to_string())
+            }
+            StorageError::Query(DieselError::DatabaseError(kind, info)) => match kind {
+                DatabaseErrorKind::UniqueViolation => {
+                    warn!("⚠️ 唯一约束冲突: {}", info.message());
+                    ApiError::Conflict {
+                        message: info.message().to_string(),
+                        details: None,
+                    }
+                }
+                DatabaseErrorKind::CheckViolation
+                | DatabaseErrorKind::ForeignKeyViolation
+                | DatabaseErrorKind::NotNullViolation => {
+                    warn!("⚠️ 违反数据库约束: {}", info.message());
+                    ApiError::ConstraintViolation(info.message().to_string())
+                }
+                _ => {
+                    error!("❌ 数据库错误: {}", info.message());
+                    ApiError::Internal("Database error".to_string())
+                }
+            },
+            e => {
+                // 连接池、线程池等内部错误不向客户端暴露细节
+                error!("❌ 存储访问失败: {}", e);
+                ApiError::Internal("Storage error".to_string())
+            }
+        }
+    }
+}
+
+// 请求体、查询参数、路径参数解析失败时返回统一格式的 400
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::BadRequest(err.to_string()).into()
+}
+
+pub fn query_error_handler(err: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::BadRequest(err.to_string()).into()
+}
+
+pub fn path_error_handler(err: PathError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::BadRequest(err.to_string()).into()
+}
@@ -0,0 +1,150 @@
+use chrono::NaiveDateTime;
+use log::debug;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::models::{CourseResponse, CourseScope, Reminder};
+
+// 每个订阅者最多积压的事件数，处理不及时的订阅者会丢失最早的事件
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EventKind {
+    #[serde(rename = "course.created")]
+    CourseCreated,
+    #[serde(rename = "course.updated")]
+    CourseUpdated,
+    #[serde(rename = "course.deleted")]
+    CourseDeleted,
+    #[serde(rename = "schedule.replaced")]
+    ScheduleReplaced, // 整个课表被清空、替换或删除，客户端应重新拉取
+    #[serde(rename = "course.reminder")]
+    CourseReminder, // 课程即将开始，由提醒调度发布
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::CourseCreated => "course.created",
+            EventKind::CourseUpdated => "course.updated",
+            EventKind::CourseDeleted => "course.deleted",
+            EventKind::ScheduleReplaced => "schedule.replaced",
+            EventKind::CourseReminder => "course.reminder",
+        }
+    }
+}
+
+// 课程变更事件，owner_id 只用于把事件分发给课程所属的用户，不发送给客户端
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleEvent {
+    #[serde(rename = "type")]
+    pub kind: EventKind,
+    #[serde(skip)]
+    pub owner_id: Option<String>,
+    pub timetable_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub course_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub course: Option<CourseResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starts_at: Option<NaiveDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minutes_before: Option<i32>,
+}
+
+impl ScheduleEvent {
+    pub fn course(kind: EventKind, course: &CourseResponse) -> Self {
+        Self {
+            kind,
+            owner_id: course.owner_id.clone(),
+            timetable_id: course.timetable_id.clone(),
+            course_id: Some(course.id.clone()),
+            course: Some(course.clone()),
+            starts_at: None,
+            minutes_before: None,
+        }
+    }
+
+    pub fn reminder(reminder: &Reminder) -> Self {
+        Self {
+            starts_at: Some(reminder.starts_at),
+            minutes_before: Some(reminder.minutes_before),
+            ..Self::course(EventKind::CourseReminder, &reminder.course)
+        }
+    }
+
+    pub fn replaced(owner_id: Option<String>, timetable_id: Option<String>) -> Self {
+        Self {
+            kind: EventKind::ScheduleReplaced,
+            owner_id,
+            timetable_id,
+            course_id: None,
+            course: None,
+            starts_at: None,
+            minutes_before: None,
+        }
+    }
+
+    // Server-Sent Events 格式："event: <类型>\ndata: <JSON>\n\n"
+    pub fn to_sse(&self) -> String {
+        let data = serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string());
+        format!("event: {}\ndata: {}\n\n", self.kind.as_str(), data)
+    }
+}
+
+// 课程变更广播，由存储后端在写入成功后发布，GET /events 的每个连接各自订阅
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<ScheduleEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl EventBus {
+    pub fn publish(&self, event: ScheduleEvent) {
+        debug!(
+            "📣 发布事件: {} (课表={:?}, 课程={:?})",
+            event.kind.as_str(),
+            event.timetable_id,
+            event.course_id
+        );
+        // 没有订阅者时发送会失败，事件直接丢弃即可
+        let _ = self.sender.send(event);
+    }
+
+    // 批量写入课程后发布：替换模式下整个课表被替换，否则逐门课程发布创建事件
+    pub fn publish_inserted(&self, scope: &CourseScope, courses: &[CourseResponse], replace: bool) {
+        if replace {
+            self.publish(ScheduleEvent::replaced(
+                scope.owner_id.clone(),
+                scope.timetable_id.clone(),
+            ));
+        } else {
+            for course in courses {
+                self.publish(ScheduleEvent::course(EventKind::CourseCreated, course));
+            }
+        }
+    }
+
+    // 教师、教室、节次、学期的修改会间接改写课程，对比写入前后的课程，内容变化的逐门发布更新事件
+    pub fn publish_changed(&self, before: &[CourseResponse], after: &[CourseResponse]) {
+        for course in after {
+            if before
+                .iter()
+                .find(|c| c.id == course.id)
+                .is_some_and(|old| old != course)
+            {
+                self.publish(ScheduleEvent::course(EventKind::CourseUpdated, course));
+            }
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ScheduleEvent> {
+        self.sender.subscribe()
+    }
+}
@@ -1,26 +1,219 @@
-use crate::models::{CreateCourseRequest, PushScheduleRequest, Schedule, UpdateCourseRequest};
-use actix_web::{delete, get, post, put, web, HttpResponse, Result};
-use log::{debug, error, info, warn};
+use crate::auth::{self, AuthConfig, CurrentUser};
+use crate::calendar;
+use crate::conflicts;
+use crate::errors::ApiError;
+use crate::events::EventBus;
+use crate::models::{
+    CalendarOverrideQuery, CommonFreeRequest, CommonFreeResponse, ConflictReport, CourseException,
+    CourseExceptionQuery, CourseQuery, CourseReminder, CourseReminderResponse, CourseResponse,
+    CourseScope, CreateCalendarOverrideRequest, CreateCourseExceptionRequest, CreateCourseRequest,
+    CreatePeriodRequest, CreateRoomRequest, CreateSemesterRequest, CreateShareLinkRequest,
+    CreateTeacherRequest, CreateTimetableRequest, CurrentWeekResponse, DayFreeSlots,
+    DayScheduleResponse, DuplicateTimetableRequest, ExceptionAction, FreeRoomQuery, FreeSlotQuery,
+    FreeSlotsResponse, ImportScheduleResponse, LoginRequest, LoginResponse, NewCourseReminder,
+    NewShareLink, NewUserSession, OverrideKind, PushScheduleRequest, RegisterRequest, Schedule,
+    TeacherScheduleResponse, Timetable, UpdateCalendarOverrideRequest,
+    UpdateCourseExceptionRequest, UpdateCourseReminderRequest, UpdateCourseRequest,
+    UpdatePeriodRequest, UpdateRoomRequest, UpdateSemesterRequest, UpdateTeacherRequest,
+    UpdateTimetableRequest,
+};
+use crate::reminders::ReminderConfig;
+use crate::storage::{self, Repository};
+use crate::validation;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use chrono::{Datelike, Duration, Local, NaiveDate, Utc};
+use futures_util::stream::{self, StreamExt};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
 
 #[get("/schedule")]
-pub async fn get_schedule() -> Result<HttpResponse> {
+pub async fn get_schedule(repo: Repository, user: CurrentUser) -> Result<HttpResponse, ApiError> {
     info!("📋 获取课程表请求");
 
-    match crate::db_storage::get_all_courses() {
-        Ok(courses) => {
-            let schedule = Schedule { courses };
-            info!("✅ 返回 {} 门课程", schedule.courses.len());
-            Ok(HttpResponse::Ok().json(schedule))
-        }
-        Err(e) => {
-            error!("❌ 获取课程表失败: {}", e);
-            Ok(HttpResponse::InternalServerError().json("Failed to get schedule"))
-        }
+    let scope = default_scope(&repo, &user).await?;
+    let courses = storage::run(&repo, move |repo| repo.list_courses(&scope)).await?;
+    let schedule = Schedule { courses };
+    info!("✅ 返回 {} 门课程", schedule.courses.len());
+    Ok(HttpResponse::Ok().json(schedule))
+}
+
+#[delete("/schedule")]
+pub async fn clear_schedule(repo: Repository, user: CurrentUser) -> Result<HttpResponse, ApiError> {
+    info!("🗑️ 清空课程表请求");
+
+    let scope = default_scope(&repo, &user).await?;
+    let deleted_count = storage::run(&repo, move |repo| repo.delete_all_courses(&scope)).await?;
+    info!("✅ 已清空 {} 门课程", deleted_count);
+    Ok(HttpResponse::Ok().json(deleted_count))
+}
+
+#[get("/schedule.ics")]
+pub async fn export_schedule_ics(
+    repo: Repository,
+    user: CurrentUser,
+) -> Result<HttpResponse, ApiError> {
+    info!("📆 导出 iCalendar 课程表请求");
+
+    let scope = default_scope(&repo, &user).await?;
+    let courses = storage::run(&repo, move |repo| repo.list_courses(&scope)).await?;
+    calendar_response(&repo, courses, "课程表").await
+}
+
+// 将课程导出为 iCalendar 响应，课程单次调整按课程 ID 匹配
+async fn calendar_response(
+    repo: &Repository,
+    courses: Vec<CourseResponse>,
+    calendar_name: &str,
+) -> Result<HttpResponse, ApiError> {
+    let semesters = storage::run(repo, |repo| repo.list_semesters()).await?;
+    let exceptions = storage::run(repo, |repo| {
+        repo.list_exceptions(&CourseExceptionQuery::default())
+    })
+    .await?;
+
+    let calendar = crate::ics::export_calendar(&courses, &semesters, &exceptions, calendar_name);
+    info!("✅ 已导出 {} 门课程的 iCalendar", courses.len());
+    Ok(HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .insert_header(("Content-Disposition", "inline; filename=\"schedule.ics\""))
+        .body(calendar))
+}
+
+#[get("/schedule/conflicts")]
+pub async fn get_schedule_conflicts(
+    repo: Repository,
+    user: CurrentUser,
+) -> Result<HttpResponse, ApiError> {
+    info!("⚔️ 获取课程冲突报告请求");
+
+    let scope = default_scope(&repo, &user).await?;
+    let courses = storage::run(&repo, move |repo| repo.list_courses(&scope)).await?;
+    let active_semester_id = active_semester_id(&repo).await?;
+    let conflicts = conflicts::find_conflicts(&courses, active_semester_id.as_deref());
+    info!("✅ 发现 {} 处课程冲突", conflicts.len());
+    Ok(HttpResponse::Ok().json(ConflictReport { conflicts }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConflictQuery {
+    #[serde(default)]
+    pub reject_conflicts: bool, // 存在时间冲突时返回 409 而不是写入
+}
+
+// 当前学期 ID，冲突检查时未关联学期的课程按当前学期处理
+async fn active_semester_id(repo: &Repository) -> Result<Option<String>, ApiError> {
+    let semester = storage::run(repo, |repo| repo.get_active_semester()).await?;
+    Ok(semester.map(|s| s.id))
+}
+
+// 检查待写入课程的冲突，include_existing 为 false 时只检查待写入课程之间（替换模式）
+async fn ensure_no_conflicts(
+    repo: &Repository,
+    scope: CourseScope,
+    candidates: &[CourseResponse],
+    include_existing: bool,
+) -> Result<(), ApiError> {
+    let existing = if include_existing {
+        storage::run(repo, move |repo| repo.list_courses(&scope)).await?
+    } else {
+        Vec::new()
+    };
+
+    let active_semester_id = active_semester_id(repo).await?;
+    let conflicts =
+        conflicts::find_conflicts_with(candidates, &existing, active_semester_id.as_deref());
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+
+    warn!("⚠️ 拒绝写入: 发现 {} 处课程冲突", conflicts.len());
+    Err(ApiError::Conflict {
+        message: "Course time conflicts detected".to_string(),
+        details: Some(json!({
+            "conflicting_course_ids": conflicts::conflicting_course_ids(&conflicts, candidates),
+            "conflicts": conflicts,
+        })),
+    })
+}
+
+// 校验待写入的课程，按节次定义的课程先由节次表推导出上课时间，并关联教师和教室
+// batch 为 true 时错误字段带上 "courses[i]." 前缀
+async fn ensure_valid(
+    repo: &Repository,
+    candidates: &mut [CourseResponse],
+    batch: bool,
+) -> Result<(), ApiError> {
+    let semesters = storage::run(repo, |repo| repo.list_semesters()).await?;
+    let periods = storage::run(repo, |repo| repo.list_periods()).await?;
+    let teachers = storage::run(repo, |repo| repo.list_teachers()).await?;
+    let rooms = storage::run(repo, |repo| repo.list_rooms()).await?;
+
+    let mut errors = Vec::new();
+    for (i, candidate) in candidates.iter_mut().enumerate() {
+        let prefix = if batch {
+            format!("courses[{}].", i)
+        } else {
+            String::new()
+        };
+        errors.extend(validation::apply_period_times(&periods, candidate, &prefix));
+        errors.extend(validation::link_course_teacher(
+            &teachers, candidate, &prefix,
+        ));
+        errors.extend(validation::link_course_room(&rooms, candidate, &prefix));
+        let semester = validation::course_semester(candidate, &semesters, &prefix, &mut errors);
+        errors.extend(validation::validate_course(candidate, semester, &prefix));
+    }
+
+    if errors.is_empty() {
+        return Ok(());
     }
+
+    warn!("⚠️ 课程校验失败: {} 个字段错误", errors.len());
+    Err(ApiError::Validation(errors))
+}
+
+#[get("/courses")]
+pub async fn get_courses(
+    repo: Repository,
+    user: CurrentUser,
+    query: web::Query<CourseQuery>,
+) -> Result<HttpResponse, ApiError> {
+    info!("🔍 查询课程请求: {:?}", query);
+
+    let query = query.into_inner();
+    let scope = default_scope(&repo, &user).await?;
+    let courses = storage::run(&repo, move |repo| repo.find_courses(&scope, &query)).await?;
+    info!("✅ 返回 {} 门课程", courses.len());
+    Ok(HttpResponse::Ok().json(courses))
+}
+
+#[get("/courses/{id}")]
+pub async fn get_course(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let course_id = path.into_inner();
+    info!("🔍 获取课程请求: ID={}", course_id);
+
+    let lookup_id = course_id.clone();
+    let scope = user.scope();
+    let course = storage::run(&repo, move |repo| repo.get_course(&scope, &lookup_id))
+        .await?
+        .ok_or_else(|| course_not_found(&course_id))?;
+    Ok(HttpResponse::Ok().json(course))
 }
 
 #[post("/courses")]
-pub async fn create_course(course_req: web::Json<CreateCourseRequest>) -> Result<HttpResponse> {
+pub async fn create_course(
+    repo: Repository,
+    user: CurrentUser,
+    query: web::Query<ConflictQuery>,
+    course_req: web::Json<CreateCourseRequest>,
+) -> Result<HttpResponse, ApiError> {
     info!("➕ 创建课程请求: {}", course_req.name);
     debug!(
         "课程详情: 教师={:?}, 地点={:?}, 星期={}, 时间={}~{}",
@@ -31,100 +224,2651 @@ pub async fn create_course(course_req: web::Json<CreateCourseRequest>) -> Result
         course_req.end_time
     );
 
-    match crate::db_storage::insert_course(&course_req) {
-        Ok(created_course) => {
-            info!(
-                "✅ 课程创建成功: {} (ID: {})",
-                created_course.name, created_course.id
-            );
-            Ok(HttpResponse::Created().json(created_course))
-        }
-        Err(e) => {
-            error!("❌ 课程创建失败: {}", e);
-            Ok(HttpResponse::InternalServerError().json("Failed to create course"))
-        }
+    let scope = default_scope(&repo, &user).await?;
+    let created_course = insert_scoped_course(
+        &repo,
+        scope,
+        query.reject_conflicts,
+        course_req.into_inner(),
+    )
+    .await?;
+    info!(
+        "✅ 课程创建成功: {} (ID: {})",
+        created_course.name, created_course.id
+    );
+    Ok(HttpResponse::Created().json(created_course))
+}
+
+// 校验并写入一门课程，scope.timetable_id 为目标课表
+async fn insert_scoped_course(
+    repo: &Repository,
+    scope: CourseScope,
+    reject_conflicts: bool,
+    mut course_req: CreateCourseRequest,
+) -> Result<CourseResponse, ApiError> {
+    validation::resolve_weeks(course_req.week_rule.as_deref(), &mut course_req.weeks, "")
+        .map_err(|e| ApiError::Validation(vec![e]))?;
+
+    let mut candidate = conflicts::candidate_from_request("new".to_string(), &course_req);
+    ensure_valid(repo, std::slice::from_mut(&mut candidate), false).await?;
+    apply_derived_fields(&mut course_req, &candidate);
+    if reject_conflicts {
+        ensure_no_conflicts(repo, scope.clone(), &[candidate], true).await?;
     }
+
+    Ok(storage::run(repo, move |repo| repo.insert_course(&scope, &course_req)).await?)
 }
 
 #[put("/courses/{id}")]
 pub async fn update_course(
+    repo: Repository,
+    user: CurrentUser,
     path: web::Path<String>,
+    query: web::Query<ConflictQuery>,
     update_req: web::Json<UpdateCourseRequest>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, ApiError> {
     let course_id = path.into_inner();
     info!("📝 更新课程请求: ID={}", course_id);
 
-    match crate::db_storage::update_course(&course_id, &update_req) {
-        Ok(Some(updated_course)) => {
-            info!("✅ 课程更新成功: {}", updated_course.name);
-            Ok(HttpResponse::Ok().json(updated_course))
-        }
-        Ok(None) => {
-            warn!("⚠️ 课程未找到: ID={}", course_id);
-            Ok(HttpResponse::NotFound().json("Course not found"))
-        }
-        Err(e) => {
-            error!("❌ 课程更新失败: {}", e);
-            Ok(HttpResponse::InternalServerError().json("Failed to update course"))
-        }
+    let mut update_req = update_req.into_inner();
+    if update_req.weeks.is_some() || update_req.week_rule.is_some() {
+        let mut weeks = update_req.weeks.take().unwrap_or_default();
+        validation::resolve_weeks(update_req.week_rule.as_deref(), &mut weeks, "")
+            .map_err(|e| ApiError::Validation(vec![e]))?;
+        update_req.weeks = Some(weeks);
+    }
+
+    // 合并到已有课程后再校验，start_time/end_time 等字段可能只更新其中之一
+    let lookup_id = course_id.clone();
+    let scope = user.scope();
+    let existing_course = storage::run(&repo, move |repo| repo.get_course(&scope, &lookup_id))
+        .await?
+        .ok_or_else(|| course_not_found(&course_id))?;
+
+    let mut candidate = conflicts::candidate_from_update(&existing_course, &update_req);
+    ensure_valid(&repo, std::slice::from_mut(&mut candidate), false).await?;
+    if candidate.start_period.is_some() {
+        // 学期、校区或节次变化后时间需重新推导，节次一并写入以免被当作改为按时间定义
+        update_req.start_period = candidate.start_period;
+        update_req.end_period = candidate.end_period;
+        update_req.start_time = Some(candidate.start_time.clone());
+        update_req.end_time = Some(candidate.end_time.clone());
     }
+    if update_req.teacher.is_some() || update_req.teacher_id.is_some() {
+        update_req.teacher = candidate.teacher.clone();
+        update_req.teacher_id = candidate.teacher_id.clone();
+    }
+    if update_req.location.is_some() || update_req.room_id.is_some() {
+        update_req.location = candidate.location.clone();
+        update_req.room_id = candidate.room_id.clone();
+    }
+    if query.reject_conflicts {
+        // 只与同一课表中的课程检查冲突
+        let conflict_scope = CourseScope {
+            owner_id: user.owner_id(),
+            timetable_id: existing_course.timetable_id.clone(),
+        };
+        ensure_no_conflicts(&repo, conflict_scope, &[candidate], true).await?;
+    }
+
+    let target_id = course_id.clone();
+    let scope = user.scope();
+    let updated_course = storage::run(&repo, move |repo| {
+        repo.update_course(&scope, &target_id, &update_req)
+    })
+    .await?
+    .ok_or_else(|| course_not_found(&course_id))?;
+
+    info!("✅ 课程更新成功: {}", updated_course.name);
+    Ok(HttpResponse::Ok().json(updated_course))
 }
 
 #[delete("/courses/{id}")]
-pub async fn delete_course(path: web::Path<String>) -> Result<HttpResponse> {
+pub async fn delete_course(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
     let course_id = path.into_inner();
     info!("🗑️ 删除课程请求: ID={}", course_id);
 
-    match crate::db_storage::delete_course(&course_id) {
-        Ok(true) => {
-            info!("✅ 课程删除成功: ID={}", course_id);
-            Ok(HttpResponse::Ok().json("Course deleted successfully"))
-        }
-        Ok(false) => {
-            warn!("⚠️ 要删除的课程未找到: ID={}", course_id);
-            Ok(HttpResponse::NotFound().json("Course not found"))
-        }
-        Err(e) => {
-            error!("❌ 课程删除失败: {}", e);
-            Ok(HttpResponse::InternalServerError().json("Failed to delete course"))
-        }
+    let target_id = course_id.clone();
+    let scope = user.scope();
+    if !storage::run(&repo, move |repo| repo.delete_course(&scope, &target_id)).await? {
+        return Err(course_not_found(&course_id));
+    }
+
+    info!("✅ 课程删除成功: ID={}", course_id);
+    Ok(HttpResponse::Ok().json("Course deleted successfully"))
+}
+
+// 未指定课表的接口（/schedule、/courses 等）使用当前用户的默认课表，不存在时自动创建
+// 学期、教师、教室和节次由所有用户共用，只有管理员可以修改
+fn ensure_registry_admin(config: &AuthConfig, user: &CurrentUser) -> Result<(), ApiError> {
+    if config.can_manage_registries(user) {
+        return Ok(());
+    }
+    warn!("⚠️ 非管理员尝试修改共用数据: 用户={:?}", user.owner_id());
+    Err(ApiError::Forbidden(
+        "Only administrators can modify semesters, teachers, rooms and periods".to_string(),
+    ))
+}
+
+async fn default_scope(repo: &Repository, user: &CurrentUser) -> Result<CourseScope, ApiError> {
+    let owner_id = user.owner_id();
+    let timetable = storage::run(repo, move |repo| {
+        repo.default_timetable(owner_id.as_deref())
+    })
+    .await?;
+    Ok(CourseScope {
+        owner_id: user.owner_id(),
+        timetable_id: Some(timetable.id),
+    })
+}
+
+fn course_not_found(course_id: &str) -> ApiError {
+    warn!("⚠️ 课程未找到: ID={}", course_id);
+    ApiError::NotFound(format!("Course {} not found", course_id))
+}
+
+#[get("/courses/{id}/reminder")]
+pub async fn get_course_reminder(
+    repo: Repository,
+    user: CurrentUser,
+    config: web::Data<ReminderConfig>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let course_id = path.into_inner();
+    info!("⏰ 获取课程提醒设置请求: 课程ID={}", course_id);
+
+    owned_course(&repo, &user, &course_id).await?;
+    let lookup_id = course_id.clone();
+    let reminder = storage::run(&repo, move |repo| repo.get_course_reminder(&lookup_id)).await?;
+    Ok(HttpResponse::Ok().json(reminder_response(&config, course_id, reminder.as_ref())))
+}
+
+#[put("/courses/{id}/reminder")]
+pub async fn update_course_reminder(
+    repo: Repository,
+    user: CurrentUser,
+    config: web::Data<ReminderConfig>,
+    path: web::Path<String>,
+    reminder_req: web::Json<UpdateCourseReminderRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let course_id = path.into_inner();
+    info!("⏰ 更新课程提醒设置请求: 课程ID={}", course_id);
+
+    let errors = validation::validate_course_reminder(reminder_req.minutes_before);
+    if !errors.is_empty() {
+        warn!("⚠️ 课程提醒设置校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    owned_course(&repo, &user, &course_id).await?;
+    let new_reminder = NewCourseReminder {
+        course_id: course_id.clone(),
+        enabled: reminder_req.enabled.unwrap_or(true),
+        minutes_before: reminder_req.minutes_before,
+    };
+    let reminder = storage::run(&repo, move |repo| {
+        repo.upsert_course_reminder(&new_reminder)
+    })
+    .await?;
+    info!("✅ 课程提醒设置已更新: 课程ID={}", course_id);
+    Ok(HttpResponse::Ok().json(reminder_response(&config, course_id, Some(&reminder))))
+}
+
+// 删除单门课程的设置，恢复为全局提醒设置
+#[delete("/courses/{id}/reminder")]
+pub async fn delete_course_reminder(
+    repo: Repository,
+    user: CurrentUser,
+    config: web::Data<ReminderConfig>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let course_id = path.into_inner();
+    info!("⏰ 重置课程提醒设置请求: 课程ID={}", course_id);
+
+    owned_course(&repo, &user, &course_id).await?;
+    let target_id = course_id.clone();
+    storage::run(&repo, move |repo| repo.delete_course_reminder(&target_id)).await?;
+    Ok(HttpResponse::Ok().json(reminder_response(&config, course_id, None)))
+}
+
+async fn owned_course(
+    repo: &Repository,
+    user: &CurrentUser,
+    course_id: &str,
+) -> Result<CourseResponse, ApiError> {
+    let scope = user.scope();
+    let lookup_id = course_id.to_string();
+    storage::run(repo, move |repo| repo.get_course(&scope, &lookup_id))
+        .await?
+        .ok_or_else(|| course_not_found(course_id))
+}
+
+fn reminder_response(
+    config: &ReminderConfig,
+    course_id: String,
+    reminder: Option<&CourseReminder>,
+) -> CourseReminderResponse {
+    CourseReminderResponse {
+        course_id,
+        enabled: reminder.is_none_or(|reminder| reminder.enabled),
+        minutes_before: reminder
+            .and_then(|reminder| reminder.minutes_before)
+            .unwrap_or(config.minutes_before),
+        uses_default: reminder.is_none_or(|reminder| reminder.minutes_before.is_none()),
+    }
+}
+
+#[get("/timetables")]
+pub async fn get_timetables(repo: Repository, user: CurrentUser) -> Result<HttpResponse, ApiError> {
+    info!("📒 获取课表列表请求");
+
+    // 确保默认课表存在，新用户也能看到自己的默认课表
+    default_scope(&repo, &user).await?;
+    let owner_id = user.owner_id();
+    let timetables =
+        storage::run(&repo, move |repo| repo.list_timetables(owner_id.as_deref())).await?;
+    Ok(HttpResponse::Ok().json(timetables))
+}
+
+#[get("/timetables/{id}")]
+pub async fn get_timetable(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let timetable_id = path.into_inner();
+    info!("🔍 获取课表请求: ID={}", timetable_id);
+
+    let timetable = owned_timetable(&repo, &user, &timetable_id).await?;
+    Ok(HttpResponse::Ok().json(timetable))
+}
+
+#[post("/timetables")]
+pub async fn create_timetable(
+    repo: Repository,
+    user: CurrentUser,
+    timetable_req: web::Json<CreateTimetableRequest>,
+) -> Result<HttpResponse, ApiError> {
+    info!("➕ 创建课表请求: {}", timetable_req.name);
+
+    let errors = validation::validate_timetable(Some(&timetable_req.name), None);
+    if !errors.is_empty() {
+        warn!("⚠️ 课表校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    // 先创建默认课表，新建的课表不会抢先成为默认课表
+    default_scope(&repo, &user).await?;
+    let owner_id = user.owner_id();
+    let created_timetable = storage::run(&repo, move |repo| {
+        repo.insert_timetable(owner_id.as_deref(), &timetable_req)
+    })
+    .await?;
+    info!(
+        "✅ 课表创建成功: {} (ID: {})",
+        created_timetable.name, created_timetable.id
+    );
+    Ok(HttpResponse::Created().json(created_timetable))
+}
+
+#[put("/timetables/{id}")]
+pub async fn update_timetable(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+    update_req: web::Json<UpdateTimetableRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let timetable_id = path.into_inner();
+    info!("📝 更新课表请求: ID={}", timetable_id);
+
+    let errors = validation::validate_timetable(update_req.name.as_deref(), update_req.is_default);
+    if !errors.is_empty() {
+        warn!("⚠️ 课表校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let target_id = timetable_id.clone();
+    let owner_id = user.owner_id();
+    let updated_timetable = storage::run(&repo, move |repo| {
+        repo.update_timetable(owner_id.as_deref(), &target_id, &update_req)
+    })
+    .await?
+    .ok_or_else(|| timetable_not_found(&timetable_id))?;
+
+    info!("✅ 课表更新成功: {}", updated_timetable.name);
+    Ok(HttpResponse::Ok().json(updated_timetable))
+}
+
+#[delete("/timetables/{id}")]
+pub async fn delete_timetable(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let timetable_id = path.into_inner();
+    info!("🗑️ 删除课表请求: ID={}", timetable_id);
+
+    // 默认课表承接 /schedule 等接口，不能删除，需先将其他课表设为默认
+    let timetable = owned_timetable(&repo, &user, &timetable_id).await?;
+    if timetable.is_default {
+        warn!("⚠️ 拒绝删除默认课表: ID={}", timetable_id);
+        return Err(ApiError::Conflict {
+            message: "Cannot delete the default timetable".to_string(),
+            details: None,
+        });
+    }
+
+    let target_id = timetable_id.clone();
+    let owner_id = user.owner_id();
+    if !storage::run(&repo, move |repo| {
+        repo.delete_timetable(owner_id.as_deref(), &target_id)
+    })
+    .await?
+    {
+        return Err(timetable_not_found(&timetable_id));
+    }
+
+    info!("✅ 课表删除成功: ID={}", timetable_id);
+    Ok(HttpResponse::Ok().json("Timetable deleted successfully"))
+}
+
+#[post("/timetables/{id}/duplicate")]
+pub async fn duplicate_timetable(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+    duplicate_req: web::Json<DuplicateTimetableRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let timetable_id = path.into_inner();
+    info!("📋 复制课表请求: ID={}", timetable_id);
+
+    let errors = validation::validate_timetable(duplicate_req.name.as_deref(), None);
+    if !errors.is_empty() {
+        warn!("⚠️ 课表校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let source = owned_timetable(&repo, &user, &timetable_id).await?;
+    let name = duplicate_req
+        .into_inner()
+        .name
+        .unwrap_or_else(|| format!("{} 副本", source.name));
+    let owner_id = user.owner_id();
+    let created_timetable = storage::run(&repo, move |repo| {
+        repo.duplicate_timetable(owner_id.as_deref(), &source.id, &name)
+    })
+    .await?
+    .ok_or_else(|| timetable_not_found(&timetable_id))?;
+
+    info!(
+        "✅ 课表复制成功: {} (ID: {})",
+        created_timetable.name, created_timetable.id
+    );
+    Ok(HttpResponse::Created().json(created_timetable))
+}
+
+#[get("/timetables/{id}/courses")]
+pub async fn get_timetable_courses(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+    query: web::Query<CourseQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let timetable_id = path.into_inner();
+    info!("🔍 查询课表课程请求: 课表={}, {:?}", timetable_id, query);
+
+    let scope = timetable_scope(&repo, &user, &timetable_id).await?;
+    let query = query.into_inner();
+    let courses = storage::run(&repo, move |repo| repo.find_courses(&scope, &query)).await?;
+    info!("✅ 返回 {} 门课程", courses.len());
+    Ok(HttpResponse::Ok().json(courses))
+}
+
+#[post("/timetables/{id}/courses")]
+pub async fn create_timetable_course(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+    query: web::Query<ConflictQuery>,
+    course_req: web::Json<CreateCourseRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let timetable_id = path.into_inner();
+    info!(
+        "➕ 创建课程请求: {} (课表={})",
+        course_req.name, timetable_id
+    );
+
+    let scope = timetable_scope(&repo, &user, &timetable_id).await?;
+    let created_course = insert_scoped_course(
+        &repo,
+        scope,
+        query.reject_conflicts,
+        course_req.into_inner(),
+    )
+    .await?;
+    info!(
+        "✅ 课程创建成功: {} (ID: {})",
+        created_course.name, created_course.id
+    );
+    Ok(HttpResponse::Created().json(created_course))
+}
+
+#[post("/timetables/{id}/push")]
+pub async fn push_timetable(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+    query: web::Query<ConflictQuery>,
+    push_req: web::Json<PushScheduleRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let timetable_id = path.into_inner();
+    info!(
+        "📤 推送课表请求: 课表={}, {} 门课程, 替换模式={}",
+        timetable_id,
+        push_req.courses.len(),
+        push_req.replace
+    );
+
+    let scope = timetable_scope(&repo, &user, &timetable_id).await?;
+    let created_courses =
+        push_scoped_courses(&repo, scope, query.reject_conflicts, push_req.into_inner()).await?;
+    info!("✅ 课表推送完成: 成功创建 {} 门课程", created_courses.len());
+    Ok(HttpResponse::Ok().json(Schedule {
+        courses: created_courses,
+    }))
+}
+
+// 取出当前用户的课表，属于其他用户的课表视为不存在
+async fn owned_timetable(
+    repo: &Repository,
+    user: &CurrentUser,
+    timetable_id: &str,
+) -> Result<Timetable, ApiError> {
+    let target_id = timetable_id.to_string();
+    let owner_id = user.owner_id();
+    storage::run(repo, move |repo| {
+        repo.get_timetable(owner_id.as_deref(), &target_id)
+    })
+    .await?
+    .ok_or_else(|| timetable_not_found(timetable_id))
+}
+
+async fn timetable_scope(
+    repo: &Repository,
+    user: &CurrentUser,
+    timetable_id: &str,
+) -> Result<CourseScope, ApiError> {
+    let timetable = owned_timetable(repo, user, timetable_id).await?;
+    Ok(CourseScope {
+        owner_id: user.owner_id(),
+        timetable_id: Some(timetable.id),
+    })
+}
+
+fn timetable_not_found(timetable_id: &str) -> ApiError {
+    warn!("⚠️ 课表未找到: ID={}", timetable_id);
+    ApiError::NotFound(format!("Timetable {} not found", timetable_id))
+}
+
+#[post("/share")]
+pub async fn create_share_link(
+    repo: Repository,
+    user: CurrentUser,
+    share_req: web::Json<CreateShareLinkRequest>,
+) -> Result<HttpResponse, ApiError> {
+    info!(
+        "🔗 创建分享链接请求: 课表={:?}, 有效期={:?} 天",
+        share_req.timetable_id, share_req.expires_in_days
+    );
+
+    let errors = validation::validate_share_link(share_req.expires_in_days);
+    if !errors.is_empty() {
+        warn!("⚠️ 分享链接校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let scope = match &share_req.timetable_id {
+        Some(timetable_id) => timetable_scope(&repo, &user, timetable_id).await?,
+        None => default_scope(&repo, &user).await?,
+    };
+    let share_link = NewShareLink {
+        id: Uuid::new_v4().to_string(),
+        token: auth::new_token(),
+        owner_id: scope.owner_id,
+        timetable_id: scope.timetable_id.unwrap_or_default(),
+        expires_at: share_req
+            .expires_in_days
+            .map(|days| Utc::now().naive_utc() + Duration::days(days)),
+    };
+    let created_share_link =
+        storage::run(&repo, move |repo| repo.insert_share_link(&share_link)).await?;
+
+    info!(
+        "✅ 分享链接创建成功: 课表 {} (ID: {})",
+        created_share_link.timetable_id, created_share_link.id
+    );
+    Ok(HttpResponse::Created().json(created_share_link))
+}
+
+#[get("/share")]
+pub async fn get_share_links(
+    repo: Repository,
+    user: CurrentUser,
+) -> Result<HttpResponse, ApiError> {
+    info!("🔗 获取分享链接列表请求");
+
+    let owner_id = user.owner_id();
+    let share_links = storage::run(&repo, move |repo| {
+        repo.list_share_links(owner_id.as_deref())
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(share_links))
+}
+
+#[delete("/share/{id}")]
+pub async fn delete_share_link(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let share_link_id = path.into_inner();
+    info!("🗑️ 撤销分享链接请求: ID={}", share_link_id);
+
+    let target_id = share_link_id.clone();
+    let owner_id = user.owner_id();
+    if !storage::run(&repo, move |repo| {
+        repo.delete_share_link(owner_id.as_deref(), &target_id)
+    })
+    .await?
+    {
+        warn!("⚠️ 分享链接未找到: ID={}", share_link_id);
+        return Err(ApiError::NotFound(format!(
+            "Share link {} not found",
+            share_link_id
+        )));
     }
+
+    info!("✅ 分享链接撤销成功: ID={}", share_link_id);
+    Ok(HttpResponse::Ok().json("Share link revoked successfully"))
+}
+
+// 通过分享令牌只读访问课表，无需登录
+#[get("/shared/{token}")]
+pub async fn get_shared_schedule(
+    repo: Repository,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    info!("🔗 获取分享课表请求");
+
+    let (_, scope) = shared_scope(&repo, path.into_inner()).await?;
+    let courses = storage::run(&repo, move |repo| repo.list_courses(&scope)).await?;
+    let schedule = Schedule { courses };
+    info!("✅ 返回 {} 门课程", schedule.courses.len());
+    Ok(HttpResponse::Ok().json(schedule))
+}
+
+#[get("/shared/{token}.ics")]
+pub async fn export_shared_schedule_ics(
+    repo: Repository,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    info!("📆 导出分享课表 iCalendar 请求");
+
+    let (timetable, scope) = shared_scope(&repo, path.into_inner()).await?;
+    let courses = storage::run(&repo, move |repo| repo.list_courses(&scope)).await?;
+    calendar_response(&repo, courses, &timetable.name).await
+}
+
+// 令牌不存在、已撤销或已过期时一律返回 404
+async fn shared_scope(
+    repo: &Repository,
+    token: String,
+) -> Result<(Timetable, CourseScope), ApiError> {
+    let not_found = || {
+        warn!("⚠️ 分享链接无效或已过期");
+        ApiError::NotFound("Share link not found or expired".to_string())
+    };
+
+    let share_link = storage::run(repo, move |repo| repo.get_share_link(&token))
+        .await?
+        .ok_or_else(not_found)?;
+    let owner_id = share_link.owner_id.clone();
+    let timetable_id = share_link.timetable_id.clone();
+    let timetable = storage::run(repo, move |repo| {
+        repo.get_timetable(owner_id.as_deref(), &timetable_id)
+    })
+    .await?
+    .ok_or_else(not_found)?;
+
+    Ok((
+        timetable,
+        CourseScope {
+            owner_id: share_link.owner_id,
+            timetable_id: Some(share_link.timetable_id),
+        },
+    ))
 }
 
 #[post("/schedule/push")]
-pub async fn push_schedule(push_req: web::Json<PushScheduleRequest>) -> Result<HttpResponse> {
+pub async fn push_schedule(
+    repo: Repository,
+    user: CurrentUser,
+    query: web::Query<ConflictQuery>,
+    push_req: web::Json<PushScheduleRequest>,
+) -> Result<HttpResponse, ApiError> {
     info!(
         "📤 推送课程表请求: {} 门课程, 替换模式={}",
         push_req.courses.len(),
         push_req.replace
     );
 
-    if push_req.replace {
-        info!("🔄 清空现有课程表");
-        match crate::db_storage::delete_all_courses() {
-            Ok(deleted_count) => {
-                info!("✅ 已清空 {} 门课程", deleted_count);
-            }
-            Err(e) => {
-                error!("❌ 清空课程表失败: {}", e);
-                return Ok(HttpResponse::InternalServerError().json("Failed to clear courses"));
-            }
-        }
+    let scope = default_scope(&repo, &user).await?;
+    let created_courses =
+        push_scoped_courses(&repo, scope, query.reject_conflicts, push_req.into_inner()).await?;
+    info!(
+        "✅ 课程表推送完成: 成功创建 {} 门课程",
+        created_courses.len()
+    );
+    Ok(HttpResponse::Ok().json(Schedule {
+        courses: created_courses,
+    }))
+}
+
+// 校验并按推送语义写入一批课程，scope.timetable_id 为目标课表
+async fn push_scoped_courses(
+    repo: &Repository,
+    scope: CourseScope,
+    reject_conflicts: bool,
+    mut push_req: PushScheduleRequest,
+) -> Result<Vec<CourseResponse>, ApiError> {
+    prepare_push(repo, &scope, reject_conflicts, &mut push_req).await?;
+    store_courses(repo, scope, push_req.courses, push_req.replace).await
+}
+
+// 校验待推送的课程：解析周次规则，校验后写回推导出的时间和关联，reject_conflicts 时检查冲突
+async fn prepare_push(
+    repo: &Repository,
+    scope: &CourseScope,
+    reject_conflicts: bool,
+    push_req: &mut PushScheduleRequest,
+) -> Result<(), ApiError> {
+    let rule_errors: Vec<_> = push_req
+        .courses
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(i, course_req)| {
+            let prefix = format!("courses[{}].", i);
+            validation::resolve_weeks(
+                course_req.week_rule.as_deref(),
+                &mut course_req.weeks,
+                &prefix,
+            )
+            .err()
+        })
+        .collect();
+    if !rule_errors.is_empty() {
+        return Err(ApiError::Validation(rule_errors));
     }
 
-    match crate::db_storage::insert_multiple_courses(&push_req.courses) {
-        Ok(created_courses) => {
-            info!(
-                "✅ 课程表推送完成: 成功创建 {} 门课程",
-                created_courses.len()
-            );
-            Ok(HttpResponse::Ok().json(Schedule {
-                courses: created_courses,
-            }))
-        }
-        Err(e) => {
-            error!("❌ 批量创建课程失败: {}", e);
-            Ok(HttpResponse::InternalServerError().json("Failed to create courses"))
+    // 推送的课程尚无 ID，用其在请求中的位置标识
+    let mut candidates: Vec<CourseResponse> = push_req
+        .courses
+        .iter()
+        .enumerate()
+        .map(|(i, course_req)| {
+            conflicts::candidate_from_request(format!("courses[{}]", i), course_req)
+        })
+        .collect();
+    ensure_valid(repo, &mut candidates, true).await?;
+    for (course_req, candidate) in push_req.courses.iter_mut().zip(&candidates) {
+        apply_derived_fields(course_req, candidate);
+    }
+    if reject_conflicts {
+        ensure_no_conflicts(repo, scope.clone(), &candidates, !push_req.replace).await?;
+    }
+    Ok(())
+}
+
+// 将校验时推导出的上课时间和关联的教师、教室写回请求
+fn apply_derived_fields(course_req: &mut CreateCourseRequest, candidate: &CourseResponse) {
+    course_req.start_time = candidate.start_time.clone();
+    course_req.end_time = candidate.end_time.clone();
+    course_req.end_period = candidate.end_period;
+    course_req.teacher = candidate.teacher.clone();
+    course_req.teacher_id = candidate.teacher_id.clone();
+    course_req.location = candidate.location.clone();
+    course_req.room_id = candidate.room_id.clone();
+}
+
+// 按推送语义写入课程：replace 为 true 时先清空现有课表，否则与现有课程合并
+async fn store_courses(
+    repo: &Repository,
+    scope: CourseScope,
+    courses: Vec<CreateCourseRequest>,
+    replace: bool,
+) -> Result<Vec<CourseResponse>, ApiError> {
+    if replace {
+        info!("🔄 清空现有课程表并写入 {} 门课程", courses.len());
+    }
+
+    Ok(storage::run(repo, move |repo| {
+        repo.insert_courses(&scope, &courses, replace)
+    })
+    .await?)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportIcsQuery {
+    #[serde(default)]
+    pub dry_run: bool, // 只返回解析结果，不写入数据库
+    #[serde(default)]
+    pub replace: bool, // 与 PushScheduleRequest.replace 语义相同
+    #[serde(default)]
+    pub reject_conflicts: bool, // 存在时间冲突时返回 409 而不是写入
+    pub semester_id: Option<String>, // 用于换算周次的学期，默认为当前学期
+}
+
+#[post("/schedule/import/ics")]
+pub async fn import_schedule_ics(
+    repo: Repository,
+    user: CurrentUser,
+    query: web::Query<ImportIcsQuery>,
+    body: String,
+) -> Result<HttpResponse, ApiError> {
+    info!(
+        "📥 导入 iCalendar 请求: {} 字节, 试运行={}, 替换模式={}",
+        body.len(),
+        query.dry_run,
+        query.replace
+    );
+
+    let semester_id = query.semester_id.clone();
+    let semester = storage::run(&repo, move |repo| match semester_id {
+        Some(semester_id) => repo.get_semester(&semester_id),
+        None => repo.get_active_semester(),
+    })
+    .await?
+    .ok_or_else(|| {
+        warn!("⚠️ 没有可用于换算周次的学期");
+        ApiError::BadRequest("No semester to map dates to weeks".to_string())
+    })?;
+
+    let import = crate::ics::import_calendar(&body, &semester);
+    info!(
+        "🔍 解析出 {} 门课程，跳过 {} 个事件",
+        import.courses.len(),
+        import.skipped.len()
+    );
+    for reason in &import.skipped {
+        debug!("跳过事件: {}", reason);
+    }
+
+    // 与推送课程表相同的校验，试运行时同样返回校验错误
+    let mut push_req = PushScheduleRequest {
+        courses: import.courses,
+        replace: query.replace,
+    };
+    let scope = default_scope(&repo, &user).await?;
+    prepare_push(&repo, &scope, query.reject_conflicts, &mut push_req).await?;
+
+    let created = if query.dry_run {
+        Vec::new()
+    } else {
+        store_courses(&repo, scope, push_req.courses.clone(), push_req.replace).await?
+    };
+
+    info!("✅ iCalendar 导入完成: 写入 {} 门课程", created.len());
+    Ok(HttpResponse::Ok().json(ImportScheduleResponse {
+        dry_run: query.dry_run,
+        replace: query.replace,
+        courses: push_req.courses,
+        created,
+        skipped: import.skipped,
+    }))
+}
+
+#[get("/semesters")]
+pub async fn get_semesters(repo: Repository) -> Result<HttpResponse, ApiError> {
+    info!("📅 获取学期列表请求");
+
+    let semesters = storage::run(&repo, |repo| repo.list_semesters()).await?;
+    Ok(HttpResponse::Ok().json(semesters))
+}
+
+#[get("/semesters/{id}")]
+pub async fn get_semester(
+    repo: Repository,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let semester_id = path.into_inner();
+    info!("🔍 获取学期请求: ID={}", semester_id);
+
+    let lookup_id = semester_id.clone();
+    let semester = storage::run(&repo, move |repo| repo.get_semester(&lookup_id))
+        .await?
+        .ok_or_else(|| semester_not_found(&semester_id))?;
+    Ok(HttpResponse::Ok().json(semester))
+}
+
+#[post("/semesters")]
+pub async fn create_semester(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    semester_req: web::Json<CreateSemesterRequest>,
+) -> Result<HttpResponse, ApiError> {
+    info!(
+        "➕ 创建学期请求: {} (开始日期={}, 共 {} 周)",
+        semester_req.name, semester_req.start_date, semester_req.total_weeks
+    );
+    ensure_registry_admin(&config, &user)?;
+
+    let errors =
+        validation::validate_semester(Some(&semester_req.name), Some(semester_req.total_weeks));
+    if !errors.is_empty() {
+        warn!("⚠️ 学期校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let created_semester =
+        storage::run(&repo, move |repo| repo.insert_semester(&semester_req)).await?;
+    info!(
+        "✅ 学期创建成功: {} (ID: {})",
+        created_semester.name, created_semester.id
+    );
+    Ok(HttpResponse::Created().json(created_semester))
+}
+
+#[put("/semesters/{id}")]
+pub async fn update_semester(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    path: web::Path<String>,
+    update_req: web::Json<UpdateSemesterRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let semester_id = path.into_inner();
+    info!("📝 更新学期请求: ID={}", semester_id);
+    ensure_registry_admin(&config, &user)?;
+
+    let errors = validation::validate_semester(update_req.name.as_deref(), update_req.total_weeks);
+    if !errors.is_empty() {
+        warn!("⚠️ 学期校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let target_id = semester_id.clone();
+    let updated_semester = storage::run(&repo, move |repo| {
+        repo.update_semester(&target_id, &update_req)
+    })
+    .await?
+    .ok_or_else(|| semester_not_found(&semester_id))?;
+
+    info!("✅ 学期更新成功: {}", updated_semester.name);
+    Ok(HttpResponse::Ok().json(updated_semester))
+}
+
+#[delete("/semesters/{id}")]
+pub async fn delete_semester(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let semester_id = path.into_inner();
+    info!("🗑️ 删除学期请求: ID={}", semester_id);
+    ensure_registry_admin(&config, &user)?;
+
+    let target_id = semester_id.clone();
+    let scope = user.scope();
+    if !storage::run(&repo, move |repo| repo.delete_semester(&scope, &target_id)).await? {
+        return Err(semester_not_found(&semester_id));
+    }
+
+    info!("✅ 学期删除成功: ID={}", semester_id);
+    Ok(HttpResponse::Ok().json("Semester deleted successfully"))
+}
+
+fn semester_not_found(semester_id: &str) -> ApiError {
+    warn!("⚠️ 学期未找到: ID={}", semester_id);
+    ApiError::NotFound(format!("Semester {} not found", semester_id))
+}
+
+#[get("/teachers")]
+pub async fn get_teachers(repo: Repository) -> Result<HttpResponse, ApiError> {
+    info!("👩‍🏫 获取教师列表请求");
+
+    let teachers = storage::run(&repo, |repo| repo.list_teachers()).await?;
+    Ok(HttpResponse::Ok().json(teachers))
+}
+
+#[get("/teachers/{id}")]
+pub async fn get_teacher(
+    repo: Repository,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let teacher_id = path.into_inner();
+    info!("🔍 获取教师请求: ID={}", teacher_id);
+
+    let lookup_id = teacher_id.clone();
+    let teacher = storage::run(&repo, move |repo| repo.get_teacher(&lookup_id))
+        .await?
+        .ok_or_else(|| teacher_not_found(&teacher_id))?;
+    Ok(HttpResponse::Ok().json(teacher))
+}
+
+#[post("/teachers")]
+pub async fn create_teacher(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    teacher_req: web::Json<CreateTeacherRequest>,
+) -> Result<HttpResponse, ApiError> {
+    info!("➕ 创建教师请求: {}", teacher_req.name);
+    ensure_registry_admin(&config, &user)?;
+
+    let errors =
+        validation::validate_teacher(Some(&teacher_req.name), teacher_req.email.as_deref());
+    if !errors.is_empty() {
+        warn!("⚠️ 教师校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let created_teacher =
+        storage::run(&repo, move |repo| repo.insert_teacher(&teacher_req)).await?;
+    info!(
+        "✅ 教师创建成功: {} (ID: {})",
+        created_teacher.name, created_teacher.id
+    );
+    Ok(HttpResponse::Created().json(created_teacher))
+}
+
+#[put("/teachers/{id}")]
+pub async fn update_teacher(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    path: web::Path<String>,
+    update_req: web::Json<UpdateTeacherRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let teacher_id = path.into_inner();
+    info!("📝 更新教师请求: ID={}", teacher_id);
+    ensure_registry_admin(&config, &user)?;
+
+    let errors =
+        validation::validate_teacher(update_req.name.as_deref(), update_req.email.as_deref());
+    if !errors.is_empty() {
+        warn!("⚠️ 教师校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let target_id = teacher_id.clone();
+    let scope = user.scope();
+    let updated_teacher = storage::run(&repo, move |repo| {
+        repo.update_teacher(&scope, &target_id, &update_req)
+    })
+    .await?
+    .ok_or_else(|| teacher_not_found(&teacher_id))?;
+
+    info!("✅ 教师更新成功: {}", updated_teacher.name);
+    Ok(HttpResponse::Ok().json(updated_teacher))
+}
+
+#[delete("/teachers/{id}")]
+pub async fn delete_teacher(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let teacher_id = path.into_inner();
+    info!("🗑️ 删除教师请求: ID={}", teacher_id);
+    ensure_registry_admin(&config, &user)?;
+
+    let target_id = teacher_id.clone();
+    let scope = user.scope();
+    if !storage::run(&repo, move |repo| repo.delete_teacher(&scope, &target_id)).await? {
+        return Err(teacher_not_found(&teacher_id));
+    }
+
+    info!("✅ 教师删除成功: ID={}", teacher_id);
+    Ok(HttpResponse::Ok().json("Teacher deleted successfully"))
+}
+
+#[get("/teachers/{id}/schedule")]
+pub async fn get_teacher_schedule(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let teacher_id = path.into_inner();
+    info!("📋 获取教师课表请求: ID={}", teacher_id);
+
+    let lookup_id = teacher_id.clone();
+    let teacher = storage::run(&repo, move |repo| repo.get_teacher(&lookup_id))
+        .await?
+        .ok_or_else(|| teacher_not_found(&teacher_id))?;
+
+    let query = CourseQuery {
+        teacher_id: Some(teacher.id.clone()),
+        ..Default::default()
+    };
+    let scope = default_scope(&repo, &user).await?;
+    let courses = storage::run(&repo, move |repo| repo.find_courses(&scope, &query)).await?;
+    info!("✅ 教师 {} 共 {} 门课程", teacher.name, courses.len());
+    Ok(HttpResponse::Ok().json(TeacherScheduleResponse { teacher, courses }))
+}
+
+fn teacher_not_found(teacher_id: &str) -> ApiError {
+    warn!("⚠️ 教师未找到: ID={}", teacher_id);
+    ApiError::NotFound(format!("Teacher {} not found", teacher_id))
+}
+
+#[get("/rooms")]
+pub async fn get_rooms(repo: Repository) -> Result<HttpResponse, ApiError> {
+    info!("🏫 获取教室列表请求");
+
+    let rooms = storage::run(&repo, |repo| repo.list_rooms()).await?;
+    Ok(HttpResponse::Ok().json(rooms))
+}
+
+#[get("/rooms/free")]
+pub async fn get_free_rooms(
+    repo: Repository,
+    _user: CurrentUser,
+    query: web::Query<FreeRoomQuery>,
+) -> Result<HttpResponse, ApiError> {
+    info!(
+        "🔍 查询空闲教室请求: 星期{} 第 {:?} 周 {}~{}",
+        query.weekday, query.week, query.start, query.end
+    );
+
+    let errors = validation::validate_slot(query.weekday, query.week, query.start, query.end);
+    if !errors.is_empty() {
+        warn!("⚠️ 空闲教室查询校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let query = query.into_inner();
+    let semester = match query.semester_id.clone() {
+        Some(semester_id) => {
+            let target_id = semester_id.clone();
+            Some(
+                storage::run(&repo, move |repo| repo.get_semester(&target_id))
+                    .await?
+                    .ok_or_else(|| semester_not_found(&semester_id))?,
+            )
+        }
+        None => storage::run(&repo, |repo| repo.get_active_semester()).await?,
+    };
+
+    let rooms = storage::run(&repo, |repo| repo.list_rooms()).await?;
+    // 教室是共用的，占用情况要看所有用户、所有课表的课程
+    let courses = storage::run(&repo, |repo| repo.list_all_courses()).await?;
+    let exceptions = storage::run(&repo, |repo| {
+        repo.list_exceptions(&CourseExceptionQuery::default())
+    })
+    .await?;
+
+    let occupied =
+        crate::rooms::occupied_rooms(&rooms, &courses, &exceptions, semester.as_ref(), &query);
+    let free_rooms: Vec<_> = rooms
+        .into_iter()
+        .filter(|r| !occupied.contains(&r.id))
+        .filter(|r| query.campus.is_none() || r.campus == query.campus)
+        .filter(|r| {
+            query
+                .min_capacity
+                .is_none_or(|min| r.capacity.is_some_and(|capacity| capacity >= min))
+        })
+        .collect();
+
+    info!(
+        "✅ 共 {} 间空闲教室（{} 间被占用）",
+        free_rooms.len(),
+        occupied.len()
+    );
+    Ok(HttpResponse::Ok().json(free_rooms))
+}
+
+#[get("/rooms/{id}")]
+pub async fn get_room(repo: Repository, path: web::Path<String>) -> Result<HttpResponse, ApiError> {
+    let room_id = path.into_inner();
+    info!("🔍 获取教室请求: ID={}", room_id);
+
+    let lookup_id = room_id.clone();
+    let room = storage::run(&repo, move |repo| repo.get_room(&lookup_id))
+        .await?
+        .ok_or_else(|| room_not_found(&room_id))?;
+    Ok(HttpResponse::Ok().json(room))
+}
+
+#[post("/rooms")]
+pub async fn create_room(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    room_req: web::Json<CreateRoomRequest>,
+) -> Result<HttpResponse, ApiError> {
+    info!(
+        "➕ 创建教室请求: {} {} (校区={:?})",
+        room_req.building, room_req.room_number, room_req.campus
+    );
+    ensure_registry_admin(&config, &user)?;
+
+    let errors = validation::validate_room(
+        Some(&room_req.building),
+        Some(&room_req.room_number),
+        room_req.capacity,
+    );
+    if !errors.is_empty() {
+        warn!("⚠️ 教室校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let created_room = storage::run(&repo, move |repo| repo.insert_room(&room_req)).await?;
+    info!(
+        "✅ 教室创建成功: {} (ID: {})",
+        created_room.label(),
+        created_room.id
+    );
+    Ok(HttpResponse::Created().json(created_room))
+}
+
+#[put("/rooms/{id}")]
+pub async fn update_room(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    path: web::Path<String>,
+    update_req: web::Json<UpdateRoomRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = path.into_inner();
+    info!("📝 更新教室请求: ID={}", room_id);
+    ensure_registry_admin(&config, &user)?;
+
+    let errors = validation::validate_room(
+        update_req.building.as_deref(),
+        update_req.room_number.as_deref(),
+        update_req.capacity,
+    );
+    if !errors.is_empty() {
+        warn!("⚠️ 教室校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let target_id = room_id.clone();
+    let scope = user.scope();
+    let updated_room = storage::run(&repo, move |repo| {
+        repo.update_room(&scope, &target_id, &update_req)
+    })
+    .await?
+    .ok_or_else(|| room_not_found(&room_id))?;
+
+    info!("✅ 教室更新成功: {}", updated_room.label());
+    Ok(HttpResponse::Ok().json(updated_room))
+}
+
+#[delete("/rooms/{id}")]
+pub async fn delete_room(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = path.into_inner();
+    info!("🗑️ 删除教室请求: ID={}", room_id);
+    ensure_registry_admin(&config, &user)?;
+
+    let target_id = room_id.clone();
+    let scope = user.scope();
+    if !storage::run(&repo, move |repo| repo.delete_room(&scope, &target_id)).await? {
+        return Err(room_not_found(&room_id));
+    }
+
+    info!("✅ 教室删除成功: ID={}", room_id);
+    Ok(HttpResponse::Ok().json("Room deleted successfully"))
+}
+
+fn room_not_found(room_id: &str) -> ApiError {
+    warn!("⚠️ 教室未找到: ID={}", room_id);
+    ApiError::NotFound(format!("Room {} not found", room_id))
+}
+
+#[get("/periods")]
+pub async fn get_periods(repo: Repository) -> Result<HttpResponse, ApiError> {
+    info!("⏰ 获取节次列表请求");
+
+    let periods = storage::run(&repo, |repo| repo.list_periods()).await?;
+    Ok(HttpResponse::Ok().json(periods))
+}
+
+#[post("/periods")]
+pub async fn create_period(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    period_req: web::Json<CreatePeriodRequest>,
+) -> Result<HttpResponse, ApiError> {
+    info!(
+        "➕ 创建节次请求: 第 {} 节 {}~{} (学期={:?}, 校区={:?})",
+        period_req.period_index,
+        period_req.start_time,
+        period_req.end_time,
+        period_req.semester_id,
+        period_req.campus
+    );
+    ensure_registry_admin(&config, &user)?;
+
+    let errors = validation::validate_period(
+        Some(period_req.period_index),
+        Some(&period_req.start_time),
+        Some(&period_req.end_time),
+    );
+    if !errors.is_empty() {
+        warn!("⚠️ 节次校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let scope = user.scope();
+    let created_period =
+        storage::run(&repo, move |repo| repo.insert_period(&scope, &period_req)).await?;
+    info!("✅ 节次创建成功: ID={}", created_period.id);
+    Ok(HttpResponse::Created().json(created_period))
+}
+
+#[put("/periods/{id}")]
+pub async fn update_period(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    path: web::Path<String>,
+    update_req: web::Json<UpdatePeriodRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let period_id = path.into_inner();
+    info!("📝 更新节次请求: ID={}", period_id);
+    ensure_registry_admin(&config, &user)?;
+
+    // 只更新起止时间之一时，需与另一端合并后再检查先后
+    let periods = storage::run(&repo, |repo| repo.list_periods()).await?;
+    let existing_period = periods
+        .iter()
+        .find(|p| p.id == period_id)
+        .ok_or_else(|| period_not_found(&period_id))?;
+    let errors = validation::validate_period(
+        update_req.period_index,
+        Some(
+            update_req
+                .start_time
+                .as_ref()
+                .unwrap_or(&existing_period.start_time),
+        ),
+        Some(
+            update_req
+                .end_time
+                .as_ref()
+                .unwrap_or(&existing_period.end_time),
+        ),
+    );
+    if !errors.is_empty() {
+        warn!("⚠️ 节次校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let target_id = period_id.clone();
+    let scope = user.scope();
+    let updated_period = storage::run(&repo, move |repo| {
+        repo.update_period(&scope, &target_id, &update_req)
+    })
+    .await?
+    .ok_or_else(|| period_not_found(&period_id))?;
+
+    info!("✅ 节次更新成功: ID={}", updated_period.id);
+    Ok(HttpResponse::Ok().json(updated_period))
+}
+
+#[delete("/periods/{id}")]
+pub async fn delete_period(
+    repo: Repository,
+    config: web::Data<AuthConfig>,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let period_id = path.into_inner();
+    info!("🗑️ 删除节次请求: ID={}", period_id);
+    ensure_registry_admin(&config, &user)?;
+
+    let target_id = period_id.clone();
+    let scope = user.scope();
+    if !storage::run(&repo, move |repo| repo.delete_period(&scope, &target_id)).await? {
+        return Err(period_not_found(&period_id));
+    }
+
+    info!("✅ 节次删除成功: ID={}", period_id);
+    Ok(HttpResponse::Ok().json("Period deleted successfully"))
+}
+
+fn period_not_found(period_id: &str) -> ApiError {
+    warn!("⚠️ 节次未找到: ID={}", period_id);
+    ApiError::NotFound(format!("Period {} not found", period_id))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CurrentWeekQuery {
+    pub date: Option<NaiveDate>, // 默认为服务器本地日期
+}
+
+#[get("/semesters/current/week")]
+pub async fn get_current_week(
+    repo: Repository,
+    query: web::Query<CurrentWeekQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let date = query.date.unwrap_or_else(|| Local::now().date_naive());
+    info!("📆 获取当前教学周请求: 日期={}", date);
+
+    let semester = storage::run(&repo, |repo| repo.get_active_semester())
+        .await?
+        .ok_or_else(|| {
+            warn!("⚠️ 尚未设置当前学期");
+            ApiError::NoActiveSemester
+        })?;
+
+    let week = semester.week_of(date);
+    info!("✅ {} 为 {} 第 {} 周", date, semester.name, week);
+    Ok(HttpResponse::Ok().json(CurrentWeekResponse {
+        in_semester: semester.contains_week(week),
+        semester_id: semester.id,
+        semester_name: semester.name,
+        date,
+        week,
+        total_weeks: semester.total_weeks,
+    }))
+}
+
+#[get("/calendar/overrides")]
+pub async fn get_calendar_overrides(
+    repo: Repository,
+    query: web::Query<CalendarOverrideQuery>,
+) -> Result<HttpResponse, ApiError> {
+    info!("🗓️ 获取日历调整列表请求: {:?} ~ {:?}", query.from, query.to);
+
+    let CalendarOverrideQuery { from, to } = query.into_inner();
+    let overrides = storage::run(&repo, move |repo| repo.list_overrides(from, to)).await?;
+    Ok(HttpResponse::Ok().json(overrides))
+}
+
+#[post("/calendar/overrides")]
+pub async fn create_calendar_override(
+    repo: Repository,
+    override_req: web::Json<CreateCalendarOverrideRequest>,
+) -> Result<HttpResponse, ApiError> {
+    info!(
+        "➕ 创建日历调整请求: {} {} (星期={:?}, 周次={:?})",
+        override_req.date,
+        override_req.kind.as_str(),
+        override_req.weekday,
+        override_req.week
+    );
+
+    let errors =
+        validation::validate_override(override_req.kind, override_req.weekday, override_req.week);
+    if !errors.is_empty() {
+        warn!("⚠️ 日历调整校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let created_override =
+        storage::run(&repo, move |repo| repo.insert_override(&override_req)).await?;
+    info!("✅ 日历调整创建成功: ID={}", created_override.id);
+    Ok(HttpResponse::Created().json(created_override))
+}
+
+#[put("/calendar/overrides/{id}")]
+pub async fn update_calendar_override(
+    repo: Repository,
+    path: web::Path<String>,
+    update_req: web::Json<UpdateCalendarOverrideRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let override_id = path.into_inner();
+    info!("📝 更新日历调整请求: ID={}", override_id);
+
+    // 未修改 kind 时，与已有的 weekday/week 合并后再校验
+    let target_id = override_id.clone();
+    let existing_override = storage::run(&repo, move |repo| repo.get_override(&target_id))
+        .await?
+        .ok_or_else(|| override_not_found(&override_id))?;
+    let errors = match update_req.kind {
+        Some(kind) => validation::validate_override(kind, update_req.weekday, update_req.week),
+        None => validation::validate_override(
+            OverrideKind::from_db(&existing_override.kind).unwrap_or(OverrideKind::Custom),
+            update_req.weekday.or(existing_override.weekday),
+            update_req.week.or(existing_override.week),
+        ),
+    };
+    if !errors.is_empty() {
+        warn!("⚠️ 日历调整校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let target_id = override_id.clone();
+    let updated_override = storage::run(&repo, move |repo| {
+        repo.update_override(&target_id, &update_req)
+    })
+    .await?
+    .ok_or_else(|| override_not_found(&override_id))?;
+
+    info!("✅ 日历调整更新成功: ID={}", updated_override.id);
+    Ok(HttpResponse::Ok().json(updated_override))
+}
+
+#[delete("/calendar/overrides/{id}")]
+pub async fn delete_calendar_override(
+    repo: Repository,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let override_id = path.into_inner();
+    info!("🗑️ 删除日历调整请求: ID={}", override_id);
+
+    let target_id = override_id.clone();
+    if !storage::run(&repo, move |repo| repo.delete_override(&target_id)).await? {
+        return Err(override_not_found(&override_id));
+    }
+
+    info!("✅ 日历调整删除成功: ID={}", override_id);
+    Ok(HttpResponse::Ok().json("Calendar override deleted successfully"))
+}
+
+fn override_not_found(override_id: &str) -> ApiError {
+    warn!("⚠️ 日历调整未找到: ID={}", override_id);
+    ApiError::NotFound(format!("Calendar override {} not found", override_id))
+}
+
+#[get("/course-exceptions")]
+pub async fn get_course_exceptions(
+    repo: Repository,
+    user: CurrentUser,
+    query: web::Query<CourseExceptionQuery>,
+) -> Result<HttpResponse, ApiError> {
+    info!(
+        "📌 获取课程单次调整列表请求: 课程={:?}, 周次={:?}",
+        query.course_id, query.week
+    );
+
+    // 只返回当前用户课程的单次调整
+    let scope = user.scope();
+    let courses = storage::run(&repo, move |repo| repo.list_courses(&scope)).await?;
+    let exceptions: Vec<_> = storage::run(&repo, move |repo| repo.list_exceptions(&query))
+        .await?
+        .into_iter()
+        .filter(|e| courses.iter().any(|c| c.id == e.course_id))
+        .collect();
+    Ok(HttpResponse::Ok().json(exceptions))
+}
+
+#[post("/course-exceptions")]
+pub async fn create_course_exception(
+    repo: Repository,
+    user: CurrentUser,
+    exception_req: web::Json<CreateCourseExceptionRequest>,
+) -> Result<HttpResponse, ApiError> {
+    info!(
+        "➕ 创建课程单次调整请求: 课程={} 第 {} 周 {}",
+        exception_req.course_id,
+        exception_req.week,
+        exception_req.action.as_str()
+    );
+
+    let course_id = exception_req.course_id.clone();
+    let scope = user.scope();
+    let course = storage::run(&repo, move |repo| repo.get_course(&scope, &course_id))
+        .await?
+        .ok_or_else(|| course_not_found(&exception_req.course_id))?;
+    let errors = validation::validate_exception(
+        &course,
+        &validation::ExceptionFields {
+            week: exception_req.week,
+            action: exception_req.action,
+            weekday: exception_req.weekday,
+            start_time: exception_req.start_time.as_deref(),
+            end_time: exception_req.end_time.as_deref(),
+            location: exception_req.location.as_deref(),
+        },
+    );
+    if !errors.is_empty() {
+        warn!("⚠️ 课程单次调整校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let created_exception =
+        storage::run(&repo, move |repo| repo.insert_exception(&exception_req)).await?;
+    info!("✅ 课程单次调整创建成功: ID={}", created_exception.id);
+    Ok(HttpResponse::Created().json(created_exception))
+}
+
+#[put("/course-exceptions/{id}")]
+pub async fn update_course_exception(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+    update_req: web::Json<UpdateCourseExceptionRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let exception_id = path.into_inner();
+    info!("📝 更新课程单次调整请求: ID={}", exception_id);
+
+    let (existing_exception, course) = owned_exception(&repo, &user, &exception_id).await?;
+
+    // 未修改 action 时，与已有的调整内容合并后再校验
+    let week = update_req.week.unwrap_or(existing_exception.week);
+    let fields = match update_req.action {
+        Some(action) => validation::ExceptionFields {
+            week,
+            action,
+            weekday: update_req.weekday,
+            start_time: update_req.start_time.as_deref(),
+            end_time: update_req.end_time.as_deref(),
+            location: update_req.location.as_deref(),
+        },
+        None => validation::ExceptionFields {
+            week,
+            action: ExceptionAction::from_db(&existing_exception.action)
+                .unwrap_or(ExceptionAction::Reschedule),
+            weekday: update_req.weekday.or(existing_exception.weekday),
+            start_time: update_req
+                .start_time
+                .as_deref()
+                .or(existing_exception.start_time.as_deref()),
+            end_time: update_req
+                .end_time
+                .as_deref()
+                .or(existing_exception.end_time.as_deref()),
+            location: update_req
+                .location
+                .as_deref()
+                .or(existing_exception.location.as_deref()),
+        },
+    };
+    let errors = validation::validate_exception(&course, &fields);
+    if !errors.is_empty() {
+        warn!("⚠️ 课程单次调整校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let target_id = exception_id.clone();
+    let updated_exception = storage::run(&repo, move |repo| {
+        repo.update_exception(&target_id, &update_req)
+    })
+    .await?
+    .ok_or_else(|| exception_not_found(&exception_id))?;
+
+    info!("✅ 课程单次调整更新成功: ID={}", updated_exception.id);
+    Ok(HttpResponse::Ok().json(updated_exception))
+}
+
+#[delete("/course-exceptions/{id}")]
+pub async fn delete_course_exception(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let exception_id = path.into_inner();
+    info!("🗑️ 删除课程单次调整请求: ID={}", exception_id);
+
+    owned_exception(&repo, &user, &exception_id).await?;
+    let target_id = exception_id.clone();
+    if !storage::run(&repo, move |repo| repo.delete_exception(&target_id)).await? {
+        return Err(exception_not_found(&exception_id));
+    }
+
+    info!("✅ 课程单次调整删除成功: ID={}", exception_id);
+    Ok(HttpResponse::Ok().json("Course exception deleted successfully"))
+}
+
+// 取出单次调整及其课程，课程不属于当前用户时视为不存在
+async fn owned_exception(
+    repo: &Repository,
+    user: &CurrentUser,
+    exception_id: &str,
+) -> Result<(CourseException, CourseResponse), ApiError> {
+    let target_id = exception_id.to_string();
+    let exception = storage::run(repo, move |repo| repo.get_exception(&target_id))
+        .await?
+        .ok_or_else(|| exception_not_found(exception_id))?;
+
+    let course_id = exception.course_id.clone();
+    let scope = user.scope();
+    let course = storage::run(repo, move |repo| repo.get_course(&scope, &course_id))
+        .await?
+        .ok_or_else(|| exception_not_found(exception_id))?;
+    Ok((exception, course))
+}
+
+fn exception_not_found(exception_id: &str) -> ApiError {
+    warn!("⚠️ 课程单次调整未找到: ID={}", exception_id);
+    ApiError::NotFound(format!("Course exception {} not found", exception_id))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DayScheduleQuery {
+    pub semester_id: Option<String>, // 默认为当前学期
+}
+
+#[get("/schedule/date/{date}")]
+pub async fn get_day_schedule(
+    repo: Repository,
+    user: CurrentUser,
+    path: web::Path<NaiveDate>,
+    query: web::Query<DayScheduleQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let date = path.into_inner();
+    info!("📅 获取日期课程安排请求: 日期={}", date);
+
+    let semester = match query.into_inner().semester_id {
+        Some(semester_id) => {
+            let target_id = semester_id.clone();
+            storage::run(&repo, move |repo| repo.get_semester(&target_id))
+                .await?
+                .ok_or_else(|| semester_not_found(&semester_id))?
+        }
+        None => storage::run(&repo, |repo| repo.get_active_semester())
+            .await?
+            .ok_or_else(|| {
+                warn!("⚠️ 尚未设置当前学期");
+                ApiError::NoActiveSemester
+            })?,
+    };
+
+    let calendar_override =
+        storage::run(&repo, move |repo| repo.get_override_by_date(date)).await?;
+    let slot = calendar::schedule_slot(&semester, date, calendar_override.as_ref());
+
+    // 调课可能把其他星期的课调入当天，因此取全部课程
+    let scope = default_scope(&repo, &user).await?;
+    let all_courses = storage::run(&repo, move |repo| repo.list_courses(&scope)).await?;
+    let exceptions = storage::run(&repo, |repo| {
+        repo.list_exceptions(&CourseExceptionQuery::default())
+    })
+    .await?;
+    let (courses, exceptions) = calendar::day_courses(
+        &semester,
+        date,
+        calendar_override.as_ref(),
+        &all_courses,
+        &exceptions,
+    );
+
+    let week = semester.week_of(date);
+    info!(
+        "✅ {} 为 {} 第 {} 周，共 {} 门课程",
+        date,
+        semester.name,
+        week,
+        courses.len()
+    );
+    Ok(HttpResponse::Ok().json(DayScheduleResponse {
+        date,
+        semester_id: semester.id.clone(),
+        week,
+        weekday: date.weekday().number_from_monday() as i32,
+        in_semester: semester.contains_week(week),
+        calendar_override,
+        schedule_week: slot.map(|(week, _)| week),
+        schedule_weekday: slot.map(|(_, weekday)| weekday),
+        courses,
+        exceptions,
+    }))
+}
+
+#[get("/schedule/free-slots")]
+pub async fn get_free_slots(
+    repo: Repository,
+    user: CurrentUser,
+    query: web::Query<FreeSlotQuery>,
+) -> Result<HttpResponse, ApiError> {
+    info!(
+        "🔍 查询空闲时段请求: 第 {:?} 周 星期{:?} 范围={:?} 至少 {} 分钟",
+        query.week, query.weekday, query.between, query.min_minutes
+    );
+
+    let query = query.into_inner();
+    let semester = match query.semester_id.clone() {
+        Some(semester_id) => {
+            let target_id = semester_id.clone();
+            Some(
+                storage::run(&repo, move |repo| repo.get_semester(&target_id))
+                    .await?
+                    .ok_or_else(|| semester_not_found(&semester_id))?,
+            )
+        }
+        None => storage::run(&repo, |repo| repo.get_active_semester()).await?,
+    };
+
+    let (start, end) =
+        validation::validate_free_slots(&query, semester.as_ref()).map_err(|errors| {
+            warn!("⚠️ 空闲时段查询校验失败: {} 个字段错误", errors.len());
+            ApiError::Validation(errors)
+        })?;
+
+    let week = match (query.week, &semester) {
+        (Some(week), _) => week,
+        (None, Some(semester)) => semester.week_of(Local::now().date_naive()),
+        (None, None) => {
+            warn!("⚠️ 未指定周次且尚未设置当前学期");
+            return Err(ApiError::Validation(vec![validation::FieldError {
+                field: "week".to_string(),
+                message: "is required when there is no active semester".to_string(),
+            }]));
+        }
+    };
+    let weekdays: Vec<i32> = match query.weekday {
+        Some(weekday) => vec![weekday],
+        None => (1..=7).collect(),
+    };
+
+    let scope = default_scope(&repo, &user).await?;
+    let courses = storage::run(&repo, move |repo| repo.list_courses(&scope)).await?;
+    let exceptions = storage::run(&repo, |repo| {
+        repo.list_exceptions(&CourseExceptionQuery::default())
+    })
+    .await?;
+    // 有学期时按具体日期取课，节假日、调休和单次调整都会生效
+    let dates = match &semester {
+        Some(semester) => Some(
+            weekdays
+                .iter()
+                .map(|weekday| semester.date_of(week, *weekday))
+                .collect::<Option<Vec<NaiveDate>>>()
+                .ok_or_else(|| {
+                    warn!("⚠️ 第 {} 周超出日期范围", week);
+                    ApiError::Validation(vec![validation::FieldError {
+                        field: "week".to_string(),
+                        message: "is out of the supported date range".to_string(),
+                    }])
+                })?,
+        ),
+        None => None,
+    };
+    let overrides = match &dates {
+        Some(dates) => {
+            let (from, to) = (dates.first().copied(), dates.last().copied());
+            storage::run(&repo, move |repo| repo.list_overrides(from, to)).await?
+        }
+        None => Vec::new(),
+    };
+
+    let days: Vec<DayFreeSlots> = weekdays
+        .into_iter()
+        .enumerate()
+        .map(|(i, weekday)| {
+            let (date, day) = match (&semester, &dates) {
+                (Some(semester), Some(dates)) => {
+                    let date = dates[i];
+                    let calendar_override = overrides.iter().find(|o| o.date == date);
+                    let (day, _) = calendar::day_courses(
+                        semester,
+                        date,
+                        calendar_override,
+                        &courses,
+                        &exceptions,
+                    );
+                    (Some(date), day)
+                }
+                _ => {
+                    let day: Vec<CourseResponse> = courses
+                        .iter()
+                        .filter(|c| c.weekday == weekday && c.weeks.contains(&week))
+                        .cloned()
+                        .collect();
+                    (None, day)
+                }
+            };
+            DayFreeSlots {
+                weekday,
+                date,
+                slots: calendar::free_slots(&day, start, end, query.min_minutes),
+            }
+        })
+        .collect();
+
+    info!(
+        "✅ 第 {} 周共 {} 个空闲时段",
+        week,
+        days.iter().map(|d| d.slots.len()).sum::<usize>()
+    );
+    Ok(HttpResponse::Ok().json(FreeSlotsResponse {
+        semester_id: semester.map(|s| s.id),
+        week,
+        start: start.format("%H:%M").to_string(),
+        end: end.format("%H:%M").to_string(),
+        min_minutes: query.min_minutes,
+        days,
+    }))
+}
+
+#[post("/auth/register")]
+pub async fn register(
+    repo: Repository,
+    register_req: web::Json<RegisterRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let mut register_req = register_req.into_inner();
+    register_req.username = register_req.username.trim().to_string();
+    info!("👤 注册用户请求: {}", register_req.username);
+
+    let errors = validation::validate_registration(&register_req.username, &register_req.password);
+    if !errors.is_empty() {
+        warn!("⚠️ 注册校验失败: {} 个字段错误", errors.len());
+        return Err(ApiError::Validation(errors));
+    }
+
+    let password = register_req.password.clone();
+    let password_hash = web::block(move || auth::hash_password(&password))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .map_err(|e| ApiError::Internal(format!("Password hashing failed: {}", e)))?;
+
+    let user = storage::run(&repo, move |repo| {
+        repo.insert_user(&register_req, &password_hash)
+    })
+    .await?;
+    info!("✅ 用户注册成功: {} (ID: {})", user.username, user.id);
+    Ok(HttpResponse::Created().json(user))
+}
+
+#[post("/auth/login")]
+pub async fn login(
+    repo: Repository,
+    login_req: web::Json<LoginRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let login_req = login_req.into_inner();
+    let username = login_req.username.trim().to_string();
+    info!("🔑 登录请求: {}", username);
+
+    let lookup_name = username.clone();
+    let user = storage::run(&repo, move |repo| repo.get_user_by_username(&lookup_name)).await?;
+    // 用户不存在与密码错误返回相同的错误，避免泄露用户名是否已注册
+    let verified = match &user {
+        Some(user) => {
+            let password_hash = user.password_hash.clone();
+            web::block(move || auth::verify_password(&login_req.password, &password_hash))
+                .await
+                .map_err(|e| ApiError::Internal(e.to_string()))?
+        }
+        None => false,
+    };
+    let Some(user) = user.filter(|_| verified) else {
+        warn!("⚠️ 登录失败: {}", username);
+        return Err(ApiError::Unauthorized(
+            "Invalid username or password".to_string(),
+        ));
+    };
+
+    let token = auth::new_token();
+    let session = NewUserSession {
+        token_hash: auth::token_hash(&token),
+        user_id: user.id.clone(),
+        expires_at: Utc::now().naive_utc() + Duration::days(auth::SESSION_DAYS),
+    };
+    let expires_at = session.expires_at;
+    storage::run(&repo, move |repo| repo.insert_session(&session)).await?;
+
+    info!("✅ 登录成功: {} (ID: {})", user.username, user.id);
+    Ok(HttpResponse::Ok().json(LoginResponse {
+        token,
+        expires_at,
+        user,
+    }))
+}
+
+#[post("/auth/logout")]
+pub async fn logout(repo: Repository, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+    info!("🚪 退出登录请求");
+
+    let token = auth::bearer_token(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Authentication required".to_string()))?;
+    let hash = auth::token_hash(&token);
+    storage::run(&repo, move |repo| repo.delete_session(&hash)).await?;
+
+    info!("✅ 已退出登录");
+    Ok(HttpResponse::Ok().json("Logged out successfully"))
+}
+
+#[get("/auth/me")]
+pub async fn get_current_user(user: CurrentUser) -> Result<HttpResponse, ApiError> {
+    let user = user
+        .0
+        .ok_or_else(|| ApiError::Unauthorized("Authentication required".to_string()))?;
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[post("/schedule/common-free")]
+pub async fn get_common_free_slots(
+    repo: Repository,
+    user: CurrentUser,
+    common_req: web::Json<CommonFreeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    info!(
+        "🤝 查询共同空闲时段请求: {} 个课表, {} 个分享令牌, 第 {}~{} 周, 至少 {} 分钟",
+        common_req.timetable_ids.len(),
+        common_req.share_tokens.len(),
+        common_req.from_week,
+        common_req.to_week,
+        common_req.min_minutes
+    );
+
+    let (start, end) = validation::validate_common_free(&common_req).map_err(|errors| {
+        warn!("⚠️ 共同空闲时段查询校验失败: {} 个字段错误", errors.len());
+        ApiError::Validation(errors)
+    })?;
+
+    let common_req = common_req.into_inner();
+    let mut scopes = Vec::new();
+    for timetable_id in &common_req.timetable_ids {
+        scopes.push(timetable_scope(&repo, &user, timetable_id).await?);
+    }
+    for token in common_req.share_tokens {
+        scopes.push(shared_scope(&repo, token).await?.1);
+    }
+    // 同一课表可能既以 ID 又以分享令牌给出，只计算一次
+    let mut seen = std::collections::BTreeSet::new();
+    scopes.retain(|scope| seen.insert(scope.timetable_id.clone()));
+
+    let timetables = scopes.len();
+    let mut courses = Vec::new();
+    for scope in scopes {
+        courses.extend(storage::run(&repo, move |repo| repo.list_courses(&scope)).await?);
+    }
+
+    let slots = calendar::common_free_slots(
+        &courses,
+        common_req.from_week,
+        common_req.to_week,
+        start,
+        end,
+        common_req.min_minutes,
+    );
+    info!("✅ {} 个课表共 {} 个共同空闲时段", timetables, slots.len());
+    Ok(HttpResponse::Ok().json(CommonFreeResponse {
+        timetables,
+        from_week: common_req.from_week,
+        to_week: common_req.to_week,
+        start: start.format("%H:%M").to_string(),
+        end: end.format("%H:%M").to_string(),
+        min_minutes: common_req.min_minutes,
+        slots,
+    }))
+}
+
+// SSE 保活间隔，避免代理因空闲断开连接
+const EVENT_KEEPALIVE: std::time::Duration = std::time::Duration::from_secs(15);
+
+#[get("/events")]
+pub async fn get_events(user: CurrentUser, events: web::Data<EventBus>) -> HttpResponse {
+    let owner_id = user.owner_id();
+    info!("📡 订阅课程变更事件: {:?}", owner_id);
+
+    let receiver = events.subscribe();
+    // 只推送当前用户自己课表的事件，空闲时发送注释行保活
+    let stream = stream::unfold(receiver, move |mut receiver| {
+        let owner_id = owner_id.clone();
+        async move {
+            loop {
+                let chunk = match tokio::time::timeout(EVENT_KEEPALIVE, receiver.recv()).await {
+                    Err(_) => ": ping\n\n".to_string(),
+                    Ok(Ok(event)) if event.owner_id == owner_id => event.to_sse(),
+                    Ok(Ok(_)) => continue,
+                    Ok(Err(RecvError::Lagged(skipped))) => {
+                        warn!("⚠️ 事件订阅者处理过慢，丢弃了 {} 条事件", skipped);
+                        continue;
+                    }
+                    Ok(Err(RecvError::Closed)) => return None,
+                };
+                return Some((Ok::<_, actix_web::Error>(web::Bytes::from(chunk)), receiver));
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(
+            stream::once(async {
+                Ok::<_, actix_web::Error>(web::Bytes::from_static(b": connected\n\n"))
+            })
+            .chain(stream),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database;
+    use crate::db_storage::SqliteRepository;
+    use crate::errors;
+    use crate::storage::{MemoryRepository, ScheduleRepository};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    // 与 main.rs 相同的错误处理配置，只注册课程和共用数据相关接口
+    macro_rules! test_app {
+        ($repo:expr) => {
+            test_app!($repo, AuthConfig::default())
+        };
+        ($repo:expr, $config:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::from($repo))
+                    .app_data(web::Data::new($config))
+                    .app_data(web::Data::new(EventBus::default()))
+                    .app_data(web::JsonConfig::default().error_handler(errors::json_error_handler))
+                    .app_data(
+                        web::QueryConfig::default().error_handler(errors::query_error_handler),
+                    )
+                    .service(
+                        web::scope("/api/v1")
+                            .wrap(actix_web::middleware::from_fn(auth::authenticate))
+                            .service(get_courses)
+                            .service(get_course)
+                            .service(create_course)
+                            .service(update_course)
+                            .service(delete_course)
+                            .service(register)
+                            .service(login)
+                            .service(create_semester)
+                            .service(update_semester)
+                            .service(delete_semester)
+                            .service(create_teacher)
+                            .service(update_teacher)
+                            .service(delete_teacher)
+                            .service(create_room)
+                            .service(update_room)
+                            .service(delete_room)
+                            .service(create_period)
+                            .service(update_period)
+                            .service(import_schedule_ics),
+                    ),
+            )
+            .await
+        };
+    }
+
+    // 同一组用例分别在两种存储后端上运行
+    fn repositories() -> Vec<(&'static str, Arc<dyn ScheduleRepository>)> {
+        vec![
+            (
+                "memory",
+                Arc::new(MemoryRepository::new(EventBus::default())),
+            ),
+            (
+                "sqlite",
+                Arc::new(SqliteRepository::new(
+                    database::create_memory_pool(),
+                    EventBus::default(),
+                )),
+            ),
+        ]
+    }
+
+    fn math() -> Value {
+        json!({
+            "name": "高等数学",
+            "teacher": "张老师",
+            "location": "A101",
+            "weekday": 1,
+            "start_time": "08:00:00",
+            "end_time": "09:40:00",
+            "week_rule": "1-16",
+        })
+    }
+
+    // 去掉各后端生成方式不同的字段，便于比较两种后端的返回
+    fn without_ids(mut course: Value) -> Value {
+        for key in ["id", "owner_id", "timetable_id"] {
+            course.as_object_mut().unwrap().remove(key);
+        }
+        course
+    }
+
+    #[actix_web::test]
+    async fn course_crud_round_trip() {
+        let mut created = Vec::new();
+        for (backend, repo) in repositories() {
+            let app = test_app!(repo);
+
+            let req = test::TestRequest::post()
+                .uri("/api/v1/courses")
+                .set_json(math())
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::CREATED, "{}", backend);
+            let course: Value = test::read_body_json(resp).await;
+            let id = course["id"].as_str().unwrap().to_string();
+            assert_eq!(course["weeks"].as_array().unwrap().len(), 16, "{}", backend);
+
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/v1/courses/{}", id))
+                .to_request();
+            let fetched: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(fetched, course, "{}", backend);
+
+            let req = test::TestRequest::put()
+                .uri(&format!("/api/v1/courses/{}", id))
+                .set_json(json!({ "name": "线性代数", "weekday": 3 }))
+                .to_request();
+            let updated: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(updated["name"], "线性代数", "{}", backend);
+            assert_eq!(updated["weekday"], 3, "{}", backend);
+            assert_eq!(updated["start_time"], "08:00:00", "{}", backend);
+
+            let req = test::TestRequest::get().uri("/api/v1/courses").to_request();
+            let courses: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(courses, vec![updated.clone()], "{}", backend);
+
+            let req = test::TestRequest::delete()
+                .uri(&format!("/api/v1/courses/{}", id))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", backend);
+
+            let req = test::TestRequest::get().uri("/api/v1/courses").to_request();
+            let courses: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+            assert!(courses.is_empty(), "{}", backend);
+
+            created.push(without_ids(updated));
+        }
+        assert_eq!(created[0], created[1]);
+    }
+
+    #[actix_web::test]
+    async fn missing_course_returns_not_found() {
+        for (backend, repo) in repositories() {
+            let app = test_app!(repo);
+            let uri = "/api/v1/courses/missing";
+            let requests = [
+                test::TestRequest::get().uri(uri).to_request(),
+                test::TestRequest::put()
+                    .uri(uri)
+                    .set_json(json!({ "name": "线性代数" }))
+                    .to_request(),
+                test::TestRequest::delete().uri(uri).to_request(),
+            ];
+            for req in requests {
+                let resp = test::call_service(&app, req).await;
+                assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{}", backend);
+                let body: Value = test::read_body_json(resp).await;
+                assert_eq!(body["code"], "NOT_FOUND", "{}", backend);
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn invalid_course_returns_field_errors() {
+        let mut responses = Vec::new();
+        for (backend, repo) in repositories() {
+            let app = test_app!(repo);
+
+            let mut course = math();
+            course["weekday"] = json!(8);
+            course["end_time"] = json!("07:00:00");
+            let req = test::TestRequest::post()
+                .uri("/api/v1/courses")
+                .set_json(course)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(
+                resp.status(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "{}",
+                backend
+            );
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["code"], "VALIDATION_FAILED", "{}", backend);
+            let fields: Vec<&str> = body["details"]["fields"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|f| f["field"].as_str().unwrap())
+                .collect();
+            assert!(fields.contains(&"weekday"), "{}: {:?}", backend, fields);
+            assert!(fields.contains(&"end_time"), "{}: {:?}", backend, fields);
+
+            // 校验失败的更新不能改动已有课程
+            let req = test::TestRequest::post()
+                .uri("/api/v1/courses")
+                .set_json(math())
+                .to_request();
+            let created: Value = test::call_and_read_body_json(&app, req).await;
+            let uri = format!("/api/v1/courses/{}", created["id"].as_str().unwrap());
+            let req = test::TestRequest::put()
+                .uri(&uri)
+                .set_json(json!({ "week_rule": "16-1" }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(
+                resp.status(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "{}",
+                backend
+            );
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            let fetched: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(fetched, created, "{}", backend);
+
+            responses.push(body);
+        }
+        assert_eq!(responses[0], responses[1]);
+    }
+
+    #[actix_web::test]
+    async fn conflicting_course_is_rejected_on_request() {
+        for (backend, repo) in repositories() {
+            let app = test_app!(repo);
+
+            let req = test::TestRequest::post()
+                .uri("/api/v1/courses")
+                .set_json(math())
+                .to_request();
+            let existing: Value = test::call_and_read_body_json(&app, req).await;
+
+            let mut overlapping = math();
+            overlapping["name"] = json!("大学物理");
+            overlapping["start_time"] = json!("09:00:00");
+            overlapping["end_time"] = json!("10:40:00");
+            let req = test::TestRequest::post()
+                .uri("/api/v1/courses?reject_conflicts=true")
+                .set_json(overlapping)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::CONFLICT, "{}", backend);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(
+                body["details"]["conflicting_course_ids"],
+                json!([existing["id"]]),
+                "{}",
+                backend
+            );
+
+            let req = test::TestRequest::get().uri("/api/v1/courses").to_request();
+            let courses: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(courses.len(), 1, "{}", backend);
+        }
+    }
+
+    fn admin_config() -> AuthConfig {
+        AuthConfig {
+            required: false,
+            admins: vec!["admin".to_string()],
+        }
+    }
+
+    // 注册并登录，返回 Authorization 头的值
+    macro_rules! sign_in {
+        ($app:expr, $username:expr) => {{
+            let credentials = json!({ "username": $username, "password": "password123" });
+            let req = test::TestRequest::post()
+                .uri("/api/v1/auth/register")
+                .set_json(&credentials)
+                .to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let req = test::TestRequest::post()
+                .uri("/api/v1/auth/login")
+                .set_json(&credentials)
+                .to_request();
+            let session: Value = test::call_and_read_body_json(&$app, req).await;
+            format!("Bearer {}", session["token"].as_str().unwrap())
+        }};
+    }
+
+    #[actix_web::test]
+    async fn ordinary_users_cannot_modify_registries() {
+        for (backend, repo) in repositories() {
+            let app = test_app!(repo, admin_config());
+            let alice = sign_in!(app, "alice");
+            let admin = sign_in!(app, "admin");
+            let teacher = json!({ "name": "张老师" });
+
+            for token in [Some(alice), None] {
+                let mut req = test::TestRequest::post()
+                    .uri("/api/v1/teachers")
+                    .set_json(&teacher);
+                if let Some(token) = token {
+                    req = req.insert_header(("Authorization", token));
+                }
+                let resp = test::call_service(&app, req.to_request()).await;
+                assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{}", backend);
+                let body: Value = test::read_body_json(resp).await;
+                assert_eq!(body["code"], "FORBIDDEN", "{}", backend);
+            }
+
+            let req = test::TestRequest::post()
+                .uri("/api/v1/teachers")
+                .insert_header(("Authorization", admin))
+                .set_json(&teacher)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::CREATED, "{}", backend);
+        }
+    }
+
+    #[actix_web::test]
+    async fn registry_writes_leave_other_users_courses_untouched() {
+        for (backend, repo) in repositories() {
+            let app = test_app!(repo, admin_config());
+            let alice = sign_in!(app, "alice");
+            let admin = sign_in!(app, "admin");
+
+            let mut ids = Vec::new();
+            for (uri, body) in [
+                (
+                    "/api/v1/semesters",
+                    json!({ "name": "秋季学期", "start_date": "2026-09-07", "total_weeks": 20 }),
+                ),
+                ("/api/v1/teachers", json!({ "name": "张老师" })),
+                (
+                    "/api/v1/rooms",
+                    json!({ "building": "A", "room_number": "101" }),
+                ),
+                (
+                    "/api/v1/periods",
+                    json!({ "period_index": 1, "start_time": "08:00:00", "end_time": "08:45:00" }),
+                ),
+            ] {
+                let req = test::TestRequest::post()
+                    .uri(uri)
+                    .insert_header(("Authorization", admin.clone()))
+                    .set_json(body)
+                    .to_request();
+                let created: Value = test::call_and_read_body_json(&app, req).await;
+                ids.push(created["id"].as_str().unwrap().to_string());
+            }
+            let [semester_id, teacher_id, room_id, period_id] = ids.try_into().unwrap();
+
+            let course = json!({
+                "name": "高等数学",
+                "weekday": 1,
+                "weeks": [1],
+                "start_period": 1,
+                "semester_id": semester_id,
+                "teacher_id": teacher_id,
+                "room_id": room_id,
+            });
+            let mut courses = Vec::new();
+            for token in [&alice, &admin] {
+                let req = test::TestRequest::post()
+                    .uri("/api/v1/courses")
+                    .insert_header(("Authorization", token.clone()))
+                    .set_json(&course)
+                    .to_request();
+                let created: Value = test::call_and_read_body_json(&app, req).await;
+                assert_eq!(created["start_time"], "08:00:00", "{}", backend);
+                courses.push(created);
+            }
+
+            for (uri, body) in [
+                (
+                    format!("/api/v1/teachers/{}", teacher_id),
+                    json!({ "name": "李老师" }),
+                ),
+                (
+                    format!("/api/v1/rooms/{}", room_id),
+                    json!({ "room_number": "102" }),
+                ),
+                (
+                    format!("/api/v1/periods/{}", period_id),
+                    json!({ "start_time": "08:10:00", "end_time": "08:55:00" }),
+                ),
+            ] {
+                let req = test::TestRequest::put()
+                    .uri(&uri)
+                    .insert_header(("Authorization", admin.clone()))
+                    .set_json(body)
+                    .to_request();
+                let resp = test::call_service(&app, req).await;
+                assert_eq!(resp.status(), StatusCode::OK, "{}: {}", backend, uri);
+            }
+
+            // 仍被 alice 的课程引用的共用数据不能删除
+            for uri in [
+                format!("/api/v1/semesters/{}", semester_id),
+                format!("/api/v1/teachers/{}", teacher_id),
+                format!("/api/v1/rooms/{}", room_id),
+            ] {
+                let req = test::TestRequest::delete()
+                    .uri(&uri)
+                    .insert_header(("Authorization", admin.clone()))
+                    .to_request();
+                let resp = test::call_service(&app, req).await;
+                assert_eq!(
+                    resp.status(),
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "{}: {}",
+                    backend,
+                    uri
+                );
+            }
+
+            let fetch = |token: &String, course: &Value| {
+                test::TestRequest::get()
+                    .uri(&format!(
+                        "/api/v1/courses/{}",
+                        course["id"].as_str().unwrap()
+                    ))
+                    .insert_header(("Authorization", token.clone()))
+                    .to_request()
+            };
+            let alice_course: Value =
+                test::call_and_read_body_json(&app, fetch(&alice, &courses[0])).await;
+            assert_eq!(alice_course, courses[0], "{}", backend);
+
+            let admin_course: Value =
+                test::call_and_read_body_json(&app, fetch(&admin, &courses[1])).await;
+            assert_eq!(admin_course["teacher"], "李老师", "{}", backend);
+            assert_eq!(admin_course["location"], "A 102", "{}", backend);
+            assert_eq!(admin_course["start_time"], "08:10:00", "{}", backend);
+            assert_eq!(admin_course["end_time"], "08:55:00", "{}", backend);
+        }
+    }
+
+    fn weekly_event(summary: &str, start: &str, end: &str) -> String {
+        [
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "BEGIN:VEVENT",
+            "UID:course-1",
+            &format!("SUMMARY:{}", summary),
+            &format!("DTSTART:20260907T{}", start),
+            &format!("DTEND:20260907T{}", end),
+            "RRULE:FREQ=WEEKLY;COUNT=4",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n")
+    }
+
+    #[actix_web::test]
+    async fn ics_import_validates_courses_like_push() {
+        for (backend, repo) in repositories() {
+            let app = test_app!(repo);
+
+            let req = test::TestRequest::post()
+                .uri("/api/v1/semesters")
+                .set_json(json!({
+                    "name": "秋季学期",
+                    "start_date": "2026-09-07",
+                    "total_weeks": 20,
+                    "is_active": true,
+                }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::CREATED, "{}", backend);
+
+            // 名称只有空白的事件能被解析，但不能通过课程校验，试运行也一样
+            for uri in [
+                "/api/v1/schedule/import/ics?dry_run=true",
+                "/api/v1/schedule/import/ics",
+            ] {
+                let req = test::TestRequest::post()
+                    .uri(uri)
+                    .set_payload(weekly_event("  ", "080000", "094000"))
+                    .to_request();
+                let resp = test::call_service(&app, req).await;
+                assert_eq!(
+                    resp.status(),
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "{}: {}",
+                    backend,
+                    uri
+                );
+                let body: Value = test::read_body_json(resp).await;
+                assert_eq!(
+                    body["details"]["fields"][0]["field"], "courses[0].name",
+                    "{}",
+                    backend
+                );
+            }
+
+            let req = test::TestRequest::get().uri("/api/v1/courses").to_request();
+            let courses: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+            assert!(courses.is_empty(), "{}", backend);
+
+            let req = test::TestRequest::post()
+                .uri("/api/v1/courses")
+                .set_json(math())
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::CREATED, "{}", backend);
+
+            let req = test::TestRequest::post()
+                .uri("/api/v1/schedule/import/ics?reject_conflicts=true")
+                .set_payload(weekly_event("大学物理", "090000", "104000"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::CONFLICT, "{}", backend);
+
+            let req = test::TestRequest::post()
+                .uri("/api/v1/schedule/import/ics")
+                .set_payload(weekly_event("大学物理", "090000", "104000"))
+                .to_request();
+            let import: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(
+                import["created"][0]["weeks"],
+                json!([1, 2, 3, 4]),
+                "{}",
+                backend
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn concurrent_first_requests_share_one_default_timetable() {
+        let path = std::env::temp_dir().join(format!("timetables-{}.db", uuid::Uuid::new_v4()));
+        let sqlite: Arc<dyn ScheduleRepository> = Arc::new(SqliteRepository::new(
+            database::create_file_pool(&path),
+            EventBus::default(),
+        ));
+        let memory: Arc<dyn ScheduleRepository> =
+            Arc::new(MemoryRepository::new(EventBus::default()));
+
+        for (backend, repo) in [("memory", memory), ("sqlite", sqlite)] {
+            // 每个新用户都同时发出多个首次请求，竞争创建默认课表
+            for round in 0..20 {
+                let register_req = RegisterRequest {
+                    username: format!("user{}", round),
+                    password: "password".to_string(),
+                    display_name: None,
+                };
+                let owner_id = repo.insert_user(&register_req, "hash").unwrap().id;
+                let barrier = std::sync::Barrier::new(8);
+                let ids: Vec<String> = std::thread::scope(|s| {
+                    let handles: Vec<_> = (0..8)
+                        .map(|_| {
+                            s.spawn(|| {
+                                barrier.wait();
+                                repo.default_timetable(Some(&owner_id))
+                                    .map(|timetable| timetable.id)
+                            })
+                        })
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|handle| handle.join().unwrap().expect(backend))
+                        .collect()
+                });
+                assert!(ids.iter().all(|id| *id == ids[0]), "{}", backend);
+                let timetables = repo.list_timetables(Some(&owner_id)).unwrap();
+                assert_eq!(timetables.len(), 1, "{}", backend);
+            }
+        }
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[actix_web::test]
+    async fn invalid_semester_returns_field_errors() {
+        for (backend, repo) in repositories() {
+            let app = test_app!(repo);
+
+            let req = test::TestRequest::post()
+                .uri("/api/v1/semesters")
+                .set_json(json!({
+                    "name": " ",
+                    "start_date": "2026-09-07",
+                    "total_weeks": 0,
+                }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(
+                resp.status(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "{}",
+                backend
+            );
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["code"], "VALIDATION_FAILED", "{}", backend);
+            assert_eq!(body["details"]["fields"][0]["field"], "name", "{}", backend);
+            assert_eq!(
+                body["details"]["fields"][1]["field"], "total_weeks",
+                "{}",
+                backend
+            );
+
+            let req = test::TestRequest::post()
+                .uri("/api/v1/semesters")
+                .set_json(json!({
+                    "name": "秋季学期",
+                    "start_date": "2026-09-07",
+                    "total_weeks": 20,
+                }))
+                .to_request();
+            let semester: Value = test::call_and_read_body_json(&app, req).await;
+
+            let req = test::TestRequest::put()
+                .uri(&format!(
+                    "/api/v1/semesters/{}",
+                    semester["id"].as_str().unwrap()
+                ))
+                .set_json(json!({ "total_weeks": -1 }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(
+                resp.status(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "{}",
+                backend
+            );
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(
+                body["details"]["fields"][0]["field"], "total_weeks",
+                "{}",
+                backend
+            );
         }
     }
 }
@@ -0,0 +1,894 @@
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use log::{debug, warn};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::calendar::apply_exception;
+use crate::models::{
+    parse_time, CourseException, CourseResponse, CreateCourseRequest, ExceptionAction, Semester,
+};
+use crate::week_rule::format_week_rule;
+
+const PRODID: &str = "-//class-schedule//Class Schedule Backend//ZH";
+const UID_DOMAIN: &str = "class-schedule";
+// 单个重复事件最多展开的次数，防止无结束条件的规则无限展开
+const MAX_OCCURRENCES: usize = 1000;
+// 重复间隔最多一年，更大的间隔在学期内不会重复
+const MAX_INTERVAL: i64 = 52;
+
+// 将课程表导出为 iCalendar 文本
+// 课程优先使用自身关联的学期，未关联时使用当前学期；两者都没有的课程无法确定日期，将被跳过
+// 单次停课从重复规则中排除，单次调课导出为带 RECURRENCE-ID 的修改实例
+pub fn export_calendar(
+    courses: &[CourseResponse],
+    semesters: &[Semester],
+    exceptions: &[CourseException],
+    calendar_name: &str,
+) -> String {
+    let mut exceptions_by_course: HashMap<&str, Vec<&CourseException>> = HashMap::new();
+    for exception in exceptions {
+        exceptions_by_course
+            .entry(exception.course_id.as_str())
+            .or_default()
+            .push(exception);
+    }
+    let semesters_by_id: HashMap<&str, &Semester> =
+        semesters.iter().map(|s| (s.id.as_str(), s)).collect();
+    let active_semester = semesters.iter().find(|s| s.is_active);
+    let dtstamp = format_datetime(Utc::now().naive_utc()) + "Z";
+
+    let mut writer = IcsWriter::default();
+    writer.property("BEGIN", "VCALENDAR");
+    writer.property("VERSION", "2.0");
+    writer.property("PRODID", PRODID);
+    writer.property("CALSCALE", "GREGORIAN");
+    writer.property("METHOD", "PUBLISH");
+    writer.property("X-WR-CALNAME", &escape_text(calendar_name));
+
+    for course in courses {
+        let semester = course
+            .semester_id
+            .as_deref()
+            .and_then(|id| semesters_by_id.get(id).copied())
+            .or(active_semester);
+
+        match semester {
+            Some(semester) => write_course_event(
+                &mut writer,
+                course,
+                semester,
+                exceptions_by_course
+                    .get(course.id.as_str())
+                    .map(Vec::as_slice)
+                    .unwrap_or_default(),
+                &dtstamp,
+            ),
+            None => warn!(
+                "⚠️ 课程 {} (ID: {}) 没有可用的学期，跳过导出",
+                course.name, course.id
+            ),
+        }
+    }
+
+    writer.property("END", "VCALENDAR");
+    writer.finish()
+}
+
+fn write_course_event(
+    writer: &mut IcsWriter,
+    course: &CourseResponse,
+    semester: &Semester,
+    exceptions: &[&CourseException],
+    dtstamp: &str,
+) {
+    let (Some(start_time), Some(end_time)) =
+        (parse_time(&course.start_time), parse_time(&course.end_time))
+    else {
+        warn!(
+            "⚠️ 课程 {} (ID: {}) 时间格式无效，跳过导出",
+            course.name, course.id
+        );
+        return;
+    };
+
+    let mut all_weeks: Vec<i32> = course.weeks.iter().copied().filter(|w| *w >= 1).collect();
+    all_weeks.sort_unstable();
+    all_weeks.dedup();
+
+    // 只处理课程实际上课周次的调整；停课的周次不再出现在重复规则中
+    let exception_of = |week: i32| exceptions.iter().find(|e| e.week == week).copied();
+    let is_cancelled = |week: i32| {
+        exception_of(week)
+            .is_some_and(|e| ExceptionAction::from_db(&e.action) == Some(ExceptionAction::Cancel))
+    };
+    let weeks: Vec<i32> = all_weeks
+        .iter()
+        .copied()
+        .filter(|week| !is_cancelled(*week))
+        .collect();
+    let (Some(&first_week), Some(&last_week)) = (weeks.first(), weeks.last()) else {
+        debug!("课程 {} 没有任何需要上课的周次，跳过导出", course.name);
+        return;
+    };
+
+    let Some(first_date) = semester.date_of(first_week, course.weekday) else {
+        warn!(
+            "⚠️ 课程 {} (ID: {}) 的周次超出日期范围，跳过导出",
+            course.name, course.id
+        );
+        return;
+    };
+
+    writer.property("BEGIN", "VEVENT");
+    writer.property("UID", &format!("{}@{}", course.id, UID_DOMAIN));
+    writer.property("DTSTAMP", dtstamp);
+    writer.property("DTSTART", &format_datetime(first_date.and_time(start_time)));
+    writer.property("DTEND", &format_datetime(first_date.and_time(end_time)));
+
+    if weeks.len() > 1 {
+        // 周次等间隔（如单双周）时用 INTERVAL 表达，否则按每周重复并排除缺失的周
+        let step = weeks[1] - weeks[0];
+        let evenly_spaced = weeks.windows(2).all(|pair| pair[1] - pair[0] == step);
+
+        if evenly_spaced {
+            writer.property(
+                "RRULE",
+                &format!("FREQ=WEEKLY;INTERVAL={};COUNT={}", step, weeks.len()),
+            );
+        } else {
+            writer.property(
+                "RRULE",
+                &format!("FREQ=WEEKLY;COUNT={}", last_week - first_week + 1),
+            );
+
+            let excluded: Vec<String> = (first_week..=last_week)
+                .filter(|week| weeks.binary_search(week).is_err())
+                .filter_map(|week| semester.date_of(week, course.weekday))
+                .map(|date| format_datetime(date.and_time(start_time)))
+                .collect();
+            writer.property("EXDATE", &excluded.join(","));
+        }
+    }
+
+    writer.property("SUMMARY", &escape_text(&course.name));
+    if let Some(location) = &course.location {
+        writer.property("LOCATION", &escape_text(location));
+    }
+    writer.property(
+        "DESCRIPTION",
+        &escape_text(&describe_course(course, &all_weeks)),
+    );
+    writer.property("END", "VEVENT");
+
+    for &week in &weeks {
+        let (Some(exception), Some(date)) =
+            (exception_of(week), semester.date_of(week, course.weekday))
+        else {
+            continue;
+        };
+        write_rescheduled_event(
+            writer,
+            course,
+            semester,
+            exception,
+            &all_weeks,
+            date.and_time(start_time),
+            dtstamp,
+        );
+    }
+}
+
+// 单次调课：以原来的上课时间为 RECURRENCE-ID 覆盖重复事件中的这一次
+fn write_rescheduled_event(
+    writer: &mut IcsWriter,
+    course: &CourseResponse,
+    semester: &Semester,
+    exception: &CourseException,
+    all_weeks: &[i32],
+    recurrence_id: NaiveDateTime,
+    dtstamp: &str,
+) {
+    let rescheduled = apply_exception(course, exception);
+    let (Some(start_time), Some(end_time)) = (
+        parse_time(&rescheduled.start_time),
+        parse_time(&rescheduled.end_time),
+    ) else {
+        warn!(
+            "⚠️ 课程 {} 第 {} 周的调课时间格式无效，跳过导出",
+            course.name, exception.week
+        );
+        return;
+    };
+    let Some(date) = semester.date_of(exception.week, rescheduled.weekday) else {
+        return;
+    };
+
+    writer.property("BEGIN", "VEVENT");
+    writer.property("UID", &format!("{}@{}", course.id, UID_DOMAIN));
+    writer.property("DTSTAMP", dtstamp);
+    writer.property("RECURRENCE-ID", &format_datetime(recurrence_id));
+    writer.property("DTSTART", &format_datetime(date.and_time(start_time)));
+    writer.property("DTEND", &format_datetime(date.and_time(end_time)));
+    writer.property("SUMMARY", &escape_text(&rescheduled.name));
+    if let Some(location) = &rescheduled.location {
+        writer.property("LOCATION", &escape_text(location));
+    }
+    let mut description = describe_course(&rescheduled, all_weeks);
+    if let Some(note) = &exception.note {
+        description.push_str(&format!("\n调课说明: {}", note));
+    }
+    writer.property("DESCRIPTION", &escape_text(&description));
+    writer.property("END", "VEVENT");
+}
+
+fn describe_course(course: &CourseResponse, weeks: &[i32]) -> String {
+    let mut lines = Vec::new();
+    if let Some(teacher) = &course.teacher {
+        lines.push(format!("教师: {}", teacher));
+    }
+    if let Some(location) = &course.location {
+        lines.push(format!("地点: {}", location));
+    }
+    lines.push(format!("周次: {}", format_week_rule(weeks)));
+    lines.join("\n")
+}
+
+// 浮动时间（不带时区），由日历客户端按本地时区解释
+fn format_datetime(datetime: NaiveDateTime) -> String {
+    datetime.format("%Y%m%dT%H%M%S").to_string()
+}
+
+// RFC 5545 3.3.11 文本转义
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+// 导入结果：合并后的课程以及被跳过的事件说明
+#[derive(Debug)]
+pub struct IcsImport {
+    pub courses: Vec<CreateCourseRequest>,
+    pub skipped: Vec<String>,
+}
+
+// 课程的合并键：同名、同教师、同地点、同一星期几同一时段的事件视为同一门课程
+type CourseKey = (
+    String,
+    Option<String>,
+    Option<String>,
+    i32,
+    NaiveTime,
+    NaiveTime,
+);
+
+// 解析 iCalendar 文本，将每周重复的事件（或分散在各周的单次事件）折叠为课程
+// 日期按 semester 换算为周次，超出学期范围的周次会被丢弃
+pub fn import_calendar(text: &str, semester: &Semester) -> IcsImport {
+    let mut grouped: BTreeMap<CourseKey, BTreeSet<i32>> = BTreeMap::new();
+    let mut skipped = Vec::new();
+
+    for event in parse_events(text) {
+        let summary = event.text("SUMMARY").unwrap_or_default();
+        let label = if summary.is_empty() {
+            event
+                .text("UID")
+                .unwrap_or_else(|| "<未命名事件>".to_string())
+        } else {
+            summary.clone()
+        };
+
+        if event.get("RECURRENCE-ID").is_some() {
+            skipped.push(format!("{}: 单次修改的重复实例不支持导入", label));
+            continue;
+        }
+        if summary.is_empty() {
+            skipped.push(format!("{}: 缺少 SUMMARY", label));
+            continue;
+        }
+
+        let occurrences = match event.occurrences(semester) {
+            Ok(occurrences) => occurrences,
+            Err(reason) => {
+                skipped.push(format!("{}: {}", label, reason));
+                continue;
+            }
+        };
+
+        let location = event.text("LOCATION").filter(|l| !l.is_empty());
+        let teacher = event.text("DESCRIPTION").and_then(|d| parse_teacher(&d));
+
+        let mut out_of_range = 0;
+        for (start, end) in occurrences {
+            let week = semester.week_of(start.date());
+            if !semester.contains_week(week) {
+                out_of_range += 1;
+                continue;
+            }
+
+            let key = (
+                summary.clone(),
+                teacher.clone(),
+                location.clone(),
+                start.weekday().number_from_monday() as i32,
+                start.time(),
+                end.time(),
+            );
+            grouped.entry(key).or_default().insert(week);
+        }
+
+        if out_of_range > 0 {
+            debug!("事件 {} 有 {} 次出现在学期范围之外", label, out_of_range);
+        }
+    }
+
+    let courses = grouped
+        .into_iter()
+        .map(
+            |((name, teacher, location, weekday, start_time, end_time), weeks)| {
+                CreateCourseRequest {
+                    name,
+                    teacher,
+                    location,
+                    weekday,
+                    start_time: start_time.format("%H:%M:%S").to_string(),
+                    end_time: end_time.format("%H:%M:%S").to_string(),
+                    weeks: weeks.into_iter().collect(),
+                    week_rule: None,
+                    color: None,
+                    semester_id: Some(semester.id.clone()),
+                    campus: None,
+                    start_period: None,
+                    end_period: None,
+                    teacher_id: None,
+                    room_id: None,
+                }
+            },
+        )
+        .collect();
+
+    IcsImport { courses, skipped }
+}
+
+// 从导出时写入的描述中提取教师（"教师: xxx"）
+fn parse_teacher(description: &str) -> Option<String> {
+    description.lines().find_map(|line| {
+        let line = line.trim();
+        line.strip_prefix("教师:")
+            .or_else(|| line.strip_prefix("教师："))
+            .map(|teacher| teacher.trim().to_string())
+            .filter(|teacher| !teacher.is_empty())
+    })
+}
+
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+#[derive(Default)]
+struct Event {
+    properties: Vec<Property>,
+}
+
+impl Event {
+    fn get(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    fn text(&self, name: &str) -> Option<String> {
+        self.get(name).map(|p| unescape_text(&p.value))
+    }
+
+    // 展开事件的所有发生时间（开始、结束）
+    fn occurrences(
+        &self,
+        semester: &Semester,
+    ) -> Result<Vec<(NaiveDateTime, NaiveDateTime)>, String> {
+        let dtstart = self.get("DTSTART").ok_or("缺少 DTSTART")?;
+        let start = parse_datetime(dtstart).ok_or("DTSTART 不是日期时间（全天事件不支持）")?;
+        let end = match self.get("DTEND") {
+            Some(dtend) => parse_datetime(dtend).ok_or("DTEND 格式无效")?,
+            None => {
+                let duration = self
+                    .get("DURATION")
+                    .and_then(|p| parse_duration(&p.value))
+                    .ok_or("缺少 DTEND 或 DURATION")?;
+                start
+                    .checked_add_signed(duration)
+                    .ok_or("DURATION 超出范围")?
+            }
+        };
+        if end <= start || end.date() != start.date() {
+            return Err("不支持跨天或结束早于开始的事件".to_string());
+        }
+        let duration = end - start;
+
+        let excluded: BTreeSet<NaiveDate> = self
+            .properties
+            .iter()
+            .filter(|p| p.name == "EXDATE")
+            .flat_map(|p| {
+                p.value
+                    .split(',')
+                    .filter_map(|value| parse_date_value(value, &p.params))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let starts = match self.get("RRULE") {
+            Some(rrule) => expand_weekly_rule(&rrule.value, start, semester)?,
+            None => vec![start],
+        };
+
+        Ok(starts
+            .into_iter()
+            .filter(|s| !excluded.contains(&s.date()))
+            .map(|s| (s, s + duration))
+            .collect())
+    }
+}
+
+// 只支持 FREQ=WEEKLY，支持 INTERVAL、COUNT、UNTIL、BYDAY
+fn expand_weekly_rule(
+    rule: &str,
+    start: NaiveDateTime,
+    semester: &Semester,
+) -> Result<Vec<NaiveDateTime>, String> {
+    let parts: HashMap<String, String> = rule
+        .split(';')
+        .filter_map(|part| part.split_once('='))
+        .map(|(k, v)| (k.to_ascii_uppercase(), v.to_string()))
+        .collect();
+
+    match parts.get("FREQ").map(String::as_str) {
+        Some("WEEKLY") => {}
+        Some(freq) => return Err(format!("不支持的重复频率 {}", freq)),
+        None => return Err("RRULE 缺少 FREQ".to_string()),
+    }
+
+    let interval: i64 = match parts.get("INTERVAL") {
+        Some(value) => value
+            .parse()
+            .ok()
+            .filter(|i| (1..=MAX_INTERVAL).contains(i))
+            .ok_or(format!("INTERVAL 需在 1 到 {} 之间", MAX_INTERVAL))?,
+        None => 1,
+    };
+    let count: Option<usize> = match parts.get("COUNT") {
+        Some(value) => Some(
+            value
+                .parse()
+                .ok()
+                .filter(|c| (1..=MAX_OCCURRENCES).contains(c))
+                .ok_or(format!("COUNT 需在 1 到 {} 之间", MAX_OCCURRENCES))?,
+        ),
+        None => None,
+    };
+    // 没有 COUNT 和 UNTIL 时展开到学期结束
+    let until: NaiveDate = match parts.get("UNTIL") {
+        Some(value) => parse_date_value(value, &[]).ok_or("UNTIL 无效")?,
+        None => semester
+            .date_of(semester.total_weeks, 7)
+            .ok_or("学期结束日期超出范围")?,
+    };
+
+    let mut weekdays: Vec<u32> = match parts.get("BYDAY") {
+        Some(value) => value
+            .split(',')
+            .map(|day| parse_weekday(day).ok_or(format!("BYDAY 无效: {}", day)))
+            .collect::<Result<_, _>>()?,
+        None => vec![start.weekday().num_days_from_monday()],
+    };
+    weekdays.sort_unstable();
+    weekdays.dedup();
+
+    let week_monday = start.date() - Duration::days(start.weekday().num_days_from_monday() as i64);
+    let limit = count.unwrap_or(MAX_OCCURRENCES);
+    let mut starts = Vec::new();
+
+    // UNTIL 可能远在日期范围之外，日期计算溢出时视为规则无效
+    'weeks: for n in 0.. {
+        let date_of = |day: u32| {
+            let offset = Duration::try_weeks(n * interval)? + Duration::try_days(day as i64)?;
+            week_monday.checked_add_signed(offset)
+        };
+        for &day in &weekdays {
+            let date = date_of(day).ok_or("重复规则超出日期范围")?;
+            if date < start.date() {
+                continue;
+            }
+            if date > until || starts.len() >= limit {
+                break 'weeks;
+            }
+            starts.push(date.and_time(start.time()));
+        }
+    }
+
+    Ok(starts)
+}
+
+fn parse_weekday(value: &str) -> Option<u32> {
+    // 去掉可能存在的序数前缀（如 1MO），对每周重复没有意义
+    let day = value.trim_start_matches(|c: char| c == '+' || c == '-' || c.is_ascii_digit());
+    match day.to_ascii_uppercase().as_str() {
+        "MO" => Some(0),
+        "TU" => Some(1),
+        "WE" => Some(2),
+        "TH" => Some(3),
+        "FR" => Some(4),
+        "SA" => Some(5),
+        "SU" => Some(6),
+        _ => None,
+    }
+}
+
+// 解析日期时间属性；UTC 时间转换为本地时间，带 TZID 的时间按浮动时间处理
+fn parse_datetime(property: &Property) -> Option<NaiveDateTime> {
+    let is_date_only = property
+        .params
+        .iter()
+        .any(|(k, v)| k == "VALUE" && v.eq_ignore_ascii_case("DATE"));
+    if is_date_only {
+        return None;
+    }
+    parse_datetime_value(&property.value)
+}
+
+fn parse_datetime_value(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    match value.strip_suffix('Z') {
+        Some(utc) => {
+            let utc = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+            Some(Local.from_utc_datetime(&utc).naive_local())
+        }
+        None => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok(),
+    }
+}
+
+fn parse_date_value(value: &str, params: &[(String, String)]) -> Option<NaiveDate> {
+    let value = value.trim();
+    let is_date_only = params
+        .iter()
+        .any(|(k, v)| k == "VALUE" && v.eq_ignore_ascii_case("DATE"));
+    if is_date_only || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d").ok();
+    }
+    parse_datetime_value(value).map(|dt| dt.date())
+}
+
+// 解析形如 PT1H40M 的时长（只支持天、时、分、秒）
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim().strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for ch in value.chars() {
+        match ch {
+            '0'..='9' => number.push(ch),
+            'T' => {}
+            'D' | 'H' | 'M' | 'S' | 'W' => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                let part = match ch {
+                    'W' => Duration::try_weeks(n),
+                    'D' => Duration::try_days(n),
+                    'H' => Duration::try_hours(n),
+                    'M' => Duration::try_minutes(n),
+                    _ => Duration::try_seconds(n),
+                }?;
+                total = total.checked_add(&part)?;
+            }
+            _ => return None,
+        }
+    }
+    Some(total)
+}
+
+// 展开折行并拆分出所有 VEVENT
+fn parse_events(text: &str) -> Vec<Event> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (
+            raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<Event> = None;
+    // 嵌套组件（如 VALARM）中的属性不属于事件本身
+    let mut nested_depth = 0;
+
+    for line in lines.iter().filter(|l| !l.is_empty()) {
+        let Some(property) = parse_property(line) else {
+            continue;
+        };
+
+        match (
+            property.name.as_str(),
+            property.value.to_ascii_uppercase().as_str(),
+        ) {
+            ("BEGIN", "VEVENT") => current = Some(Event::default()),
+            ("END", "VEVENT") => {
+                if let Some(event) = current.take() {
+                    events.push(event);
+                }
+                nested_depth = 0;
+            }
+            ("BEGIN", _) if current.is_some() => nested_depth += 1,
+            ("END", _) if current.is_some() => nested_depth -= 1,
+            _ => {
+                if let (Some(event), 0) = (current.as_mut(), nested_depth) {
+                    event.properties.push(property);
+                }
+            }
+        }
+    }
+
+    events
+}
+
+fn parse_property(line: &str) -> Option<Property> {
+    // 找到第一个不在引号内的冒号
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(i),
+        _ => None,
+    })?;
+
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut segments = head.split(';');
+    let name = segments.next()?.trim().to_ascii_uppercase();
+    let params = segments
+        .filter_map(|param| param.split_once('='))
+        .map(|(k, v)| {
+            (
+                k.trim().to_ascii_uppercase(),
+                v.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[derive(Default)]
+struct IcsWriter {
+    buffer: String,
+}
+
+impl IcsWriter {
+    // 写入一行属性，超过 75 字节时按 RFC 5545 3.1 折行（不拆分 UTF-8 字符）
+    fn property(&mut self, name: &str, value: &str) {
+        let line = format!("{}:{}", name, value);
+        let mut line_len = 0;
+        for ch in line.chars() {
+            if line_len + ch.len_utf8() > 75 {
+                self.buffer.push_str("\r\n ");
+                line_len = 1;
+            }
+            self.buffer.push(ch);
+            line_len += ch.len_utf8();
+        }
+        self.buffer.push_str("\r\n");
+    }
+
+    fn finish(self) -> String {
+        self.buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::week_rule::parse_week_rule;
+
+    // 第一周从 2025-09-01（周一）开始，共 20 周
+    fn semester() -> Semester {
+        let now = NaiveDate::from_ymd_opt(2025, 8, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        Semester {
+            id: "s1".to_string(),
+            name: "2025 秋".to_string(),
+            start_date: NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(),
+            total_weeks: 20,
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn course(id: &str, weekday: i32, weeks: Vec<i32>) -> CourseResponse {
+        CourseResponse {
+            id: id.to_string(),
+            name: "高等数学".to_string(),
+            teacher: Some("张老师".to_string()),
+            location: Some("A101, 东区".to_string()),
+            weekday,
+            start_time: "08:00:00".to_string(),
+            end_time: "09:40:00".to_string(),
+            week_rule: format_week_rule(&weeks),
+            weeks,
+            color: None,
+            semester_id: Some("s1".to_string()),
+            campus: None,
+            start_period: None,
+            end_period: None,
+            teacher_id: None,
+            room_id: None,
+            owner_id: None,
+            timetable_id: None,
+        }
+    }
+
+    fn calendar(events: &[&str]) -> String {
+        let mut text = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n".to_string();
+        for event in events {
+            text.push_str("BEGIN:VEVENT\r\n");
+            text.push_str(event);
+            text.push_str("END:VEVENT\r\n");
+        }
+        text.push_str("END:VCALENDAR\r\n");
+        text
+    }
+
+    #[test]
+    fn round_trips_courses() {
+        let semester = semester();
+        let courses = vec![
+            course("c1", 1, parse_week_rule("1-17单").unwrap()),
+            course("c2", 3, parse_week_rule("1-8,10-16").unwrap()),
+        ];
+        let text = export_calendar(&courses, std::slice::from_ref(&semester), &[], "课表");
+        assert!(text.contains("RRULE:FREQ=WEEKLY;INTERVAL=2;COUNT=9"));
+        assert!(text.contains("EXDATE:20251029T080000"));
+
+        let import = import_calendar(&text, &semester);
+        assert!(import.skipped.is_empty(), "{:?}", import.skipped);
+        assert_eq!(import.courses.len(), 2);
+        for (imported, original) in import.courses.iter().zip(&courses) {
+            assert_eq!(imported.name, original.name);
+            assert_eq!(imported.teacher, original.teacher);
+            assert_eq!(imported.location, original.location);
+            assert_eq!(imported.weekday, original.weekday);
+            assert_eq!(imported.start_time, original.start_time);
+            assert_eq!(imported.end_time, original.end_time);
+            assert_eq!(imported.weeks, original.weeks);
+        }
+    }
+
+    #[test]
+    fn exports_cancelled_and_rescheduled_weeks() {
+        let semester = semester();
+        let now = semester.created_at;
+        let exception = |week: i32, action: ExceptionAction| CourseException {
+            id: format!("e{}", week),
+            course_id: "c1".to_string(),
+            week,
+            action: action.as_str().to_string(),
+            weekday: Some(5),
+            start_time: None,
+            end_time: None,
+            location: None,
+            note: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let text = export_calendar(
+            &[course("c1", 1, vec![1, 2, 3, 4])],
+            &[semester],
+            &[
+                exception(2, ExceptionAction::Cancel),
+                exception(3, ExceptionAction::Reschedule),
+            ],
+            "课表",
+        );
+        // 停课的第 2 周从重复规则中排除，第 3 周改到周五
+        assert!(text.contains("RRULE:FREQ=WEEKLY;COUNT=4"));
+        assert!(text.contains("EXDATE:20250908T080000"));
+        assert!(text.contains("RECURRENCE-ID:20250915T080000"));
+        assert!(text.contains("DTSTART:20250919T080000"));
+    }
+
+    #[test]
+    fn merges_single_events_into_one_course() {
+        let text = calendar(&[
+            "SUMMARY:线性代数\r\nDTSTART:20250902T100000\r\nDTEND:20250902T114000\r\n",
+            "SUMMARY:线性代数\r\nDTSTART:20250916T100000\r\nDURATION:PT1H40M\r\n",
+            // 学期之外的出现被丢弃
+            "SUMMARY:线性代数\r\nDTSTART:20260602T100000\r\nDTEND:20260602T114000\r\n",
+        ]);
+        let import = import_calendar(&text, &semester());
+        assert_eq!(import.courses.len(), 1);
+        assert_eq!(import.courses[0].weekday, 2);
+        assert_eq!(import.courses[0].end_time, "11:40:00");
+        assert_eq!(import.courses[0].weeks, vec![1, 3]);
+    }
+
+    #[test]
+    fn expands_byday_and_until() {
+        let text = calendar(&[concat!(
+            "SUMMARY:体育\r\nDTSTART:20250901T140000\r\nDTEND:20250901T153000\r\n",
+            "RRULE:FREQ=WEEKLY;BYDAY=MO,TH;UNTIL=20250915T235959Z\r\n",
+        )]);
+        let import = import_calendar(&text, &semester());
+        assert_eq!(import.courses.len(), 2);
+        assert_eq!(import.courses[0].weekday, 1);
+        assert_eq!(import.courses[0].weeks, vec![1, 2, 3]);
+        assert_eq!(import.courses[1].weekday, 4);
+        assert_eq!(import.courses[1].weeks, vec![1, 2]);
+    }
+
+    #[test]
+    fn skips_invalid_events_without_failing() {
+        let start = "DTSTART:20250901T080000\r\nDTEND:20250901T094000\r\n";
+        let events = [
+            format!(
+                "SUMMARY:间隔过大\r\n{}RRULE:FREQ=WEEKLY;INTERVAL=999999999999\r\n",
+                start
+            ),
+            format!(
+                "SUMMARY:次数过多\r\n{}RRULE:FREQ=WEEKLY;COUNT=99999999999\r\n",
+                start
+            ),
+            format!("SUMMARY:次数为零\r\n{}RRULE:FREQ=WEEKLY;COUNT=0\r\n", start),
+            format!("SUMMARY:按天重复\r\n{}RRULE:FREQ=DAILY\r\n", start),
+            "SUMMARY:时长过大\r\nDTSTART:20250901T080000\r\nDURATION:P999999999999W\r\n"
+                .to_string(),
+            "SUMMARY:全天\r\nDTSTART;VALUE=DATE:20250901\r\n".to_string(),
+            format!(
+                "SUMMARY:正常\r\n{}RRULE:FREQ=WEEKLY;UNTIL=99991231\r\n",
+                start
+            ),
+        ];
+        let events: Vec<&str> = events.iter().map(String::as_str).collect();
+        let import = import_calendar(&calendar(&events), &semester());
+
+        assert_eq!(import.skipped.len(), 6, "{:?}", import.skipped);
+        assert!(import.skipped[0].starts_with("间隔过大: INTERVAL"));
+        assert!(import.skipped[1].starts_with("次数过多: COUNT"));
+        assert_eq!(import.courses.len(), 1);
+        assert_eq!(import.courses[0].name, "正常");
+        assert_eq!(import.courses[0].weeks, (1..=20).collect::<Vec<_>>());
+    }
+}
@@ -1,15 +1,31 @@
 use actix_cors::Cors;
-use actix_web::{middleware::Logger, web, App, HttpServer};
+use actix_web::middleware::{from_fn, Logger};
+use actix_web::{web, App, HttpServer};
 use log::{error, info};
 
+mod auth;
+mod calendar;
+mod conflicts;
 mod database;
 mod db_storage;
+mod errors;
+mod events;
 mod handlers;
+mod ics;
 mod models;
+mod periods;
+mod reminders;
+mod rooms;
 mod schema;
-// mod storage; // 旧的内存存储，已被数据库存储替代
+mod storage;
+mod teachers;
+mod validation;
+mod week_rule;
 
 use handlers::*;
+use std::env;
+use std::sync::Arc;
+use storage::{MemoryRepository, ScheduleRepository};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -23,26 +39,140 @@ async fn main() -> std::io::Result<()> {
 
     info!("🚀 课程表后端服务启动中...");
 
-    // 初始化数据库
-    info!("📊 初始化数据库连接...");
-    let mut connection = database::establish_connection();
-    database::run_migrations(&mut connection);
-    info!("✅ 数据库初始化完成");
+    // 课程变更事件由存储后端发布，通过 GET /api/v1/events 推送给客户端
+    let events = events::EventBus::default();
 
-    let server = HttpServer::new(|| {
+    // 初始化存储后端，STORAGE_BACKEND=memory 时不使用数据库
+    let repository: Arc<dyn ScheduleRepository> = match env::var("STORAGE_BACKEND").as_deref() {
+        Ok("memory") => {
+            info!("💾 使用内存存储，数据不会持久化");
+            Arc::new(MemoryRepository::new(events.clone()))
+        }
+        _ => {
+            info!("📊 初始化数据库连接...");
+            let pool = database::create_pool();
+            {
+                let mut connection = pool.get().expect("Failed to get database connection");
+                database::run_migrations(&mut connection);
+            }
+            info!("✅ 数据库初始化完成");
+            Arc::new(db_storage::SqliteRepository::new(pool, events.clone()))
+        }
+    };
+    let repository = web::Data::from(repository);
+
+    let auth_config = auth::AuthConfig::from_env();
+    if auth_config.required {
+        info!("🔒 已启用强制登录，未携带令牌的请求将被拒绝");
+    }
+    if !auth_config.admins.is_empty() {
+        info!("🛡️ 管理员: {}", auth_config.admins.join(", "));
+    }
+    let auth_config = web::Data::new(auth_config);
+
+    // 课程提醒：通过事件流推送，配置了 REMINDER_WEBHOOK_URL 时同时推送到 webhook
+    let reminder_config = reminders::ReminderConfig::from_env();
+    if reminder_config.enabled {
+        let mut notifiers: Vec<Box<dyn reminders::Notifier>> = vec![Box::new(
+            reminders::EventStreamNotifier::new(events.clone()),
+        )];
+        if let Some(url) = &reminder_config.webhook_url {
+            notifiers.push(Box::new(reminders::WebhookNotifier::new(url.clone())));
+        }
+        reminders::ReminderScheduler::new(repository.clone(), reminder_config.clone(), notifiers)
+            .spawn();
+    } else {
+        info!("🔕 课程提醒已关闭");
+    }
+    let reminder_config = web::Data::new(reminder_config);
+    let events = web::Data::new(events);
+
+    let server = HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
             .allow_any_method()
             .allow_any_header();
 
-        App::new().wrap(cors).wrap(Logger::default()).service(
-            web::scope("/api/v1")
-                .service(get_schedule)
-                .service(create_course)
-                .service(update_course)
-                .service(delete_course)
-                .service(push_schedule),
-        )
+        App::new()
+            .app_data(repository.clone())
+            .app_data(auth_config.clone())
+            .app_data(events.clone())
+            .app_data(reminder_config.clone())
+            .app_data(web::JsonConfig::default().error_handler(errors::json_error_handler))
+            .app_data(web::QueryConfig::default().error_handler(errors::query_error_handler))
+            .app_data(web::PathConfig::default().error_handler(errors::path_error_handler))
+            .wrap(cors)
+            .wrap(Logger::default())
+            .service(
+                web::scope("/api/v1")
+                    .wrap(from_fn(auth::authenticate))
+                    .service(register)
+                    .service(login)
+                    .service(logout)
+                    .service(get_current_user)
+                    .service(get_schedule)
+                    .service(clear_schedule)
+                    .service(export_schedule_ics)
+                    .service(get_schedule_conflicts)
+                    .service(get_courses)
+                    .service(get_course)
+                    .service(create_course)
+                    .service(update_course)
+                    .service(delete_course)
+                    .service(get_course_reminder)
+                    .service(update_course_reminder)
+                    .service(delete_course_reminder)
+                    .service(push_schedule)
+                    .service(get_timetables)
+                    .service(get_timetable)
+                    .service(create_timetable)
+                    .service(update_timetable)
+                    .service(delete_timetable)
+                    .service(duplicate_timetable)
+                    .service(get_timetable_courses)
+                    .service(create_timetable_course)
+                    .service(push_timetable)
+                    .service(create_share_link)
+                    .service(get_share_links)
+                    .service(delete_share_link)
+                    .service(export_shared_schedule_ics)
+                    .service(get_shared_schedule)
+                    .service(import_schedule_ics)
+                    .service(get_current_week)
+                    .service(get_semesters)
+                    .service(get_semester)
+                    .service(create_semester)
+                    .service(update_semester)
+                    .service(delete_semester)
+                    .service(get_teachers)
+                    .service(get_teacher)
+                    .service(create_teacher)
+                    .service(update_teacher)
+                    .service(delete_teacher)
+                    .service(get_teacher_schedule)
+                    .service(get_rooms)
+                    .service(get_free_rooms)
+                    .service(get_room)
+                    .service(create_room)
+                    .service(update_room)
+                    .service(delete_room)
+                    .service(get_periods)
+                    .service(create_period)
+                    .service(update_period)
+                    .service(delete_period)
+                    .service(get_calendar_overrides)
+                    .service(create_calendar_override)
+                    .service(update_calendar_override)
+                    .service(delete_calendar_override)
+                    .service(get_course_exceptions)
+                    .service(create_course_exception)
+                    .service(update_course_exception)
+                    .service(delete_course_exception)
+                    .service(get_day_schedule)
+                    .service(get_free_slots)
+                    .service(get_common_free_slots)
+                    .service(get_events),
+            )
     })
     .bind("127.0.0.1:8080");
 
@@ -1,8 +1,11 @@
-use chrono::NaiveDateTime;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::schema::courses;
+use crate::schema::{
+    calendar_overrides, course_exceptions, course_reminders, course_weeks, courses, periods,
+    reminder_deliveries, rooms, semesters, share_links, teachers, timetables, user_sessions, users,
+};
 
 // 数据库模型 - 用于从数据库查询
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
@@ -16,10 +19,17 @@ pub struct Course {
     pub weekday: i32,       // 1=Monday, 2=Tuesday, ..., 7=Sunday
     pub start_time: String, // 存储为 "HH:MM:SS" 格式
     pub end_time: String,   // 存储为 "HH:MM:SS" 格式
-    pub weeks: String,      // JSON 字符串存储周次信息
     pub color: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub semester_id: Option<String>,
+    pub campus: Option<String>,
+    pub start_period: Option<i32>, // 按节次定义时的起止节次
+    pub end_period: Option<i32>,
+    pub teacher_id: Option<String>, // 关联教师时 teacher 为教师姓名
+    pub room_id: Option<String>,    // 关联教室时 location 为教室名称
+    pub owner_id: Option<String>,   // 所属用户，未登录时创建的课程为空
+    pub timetable_id: Option<String>,
 }
 
 // 插入模型 - 用于插入数据库
@@ -33,8 +43,23 @@ pub struct NewCourse {
     pub weekday: i32,
     pub start_time: String,
     pub end_time: String,
-    pub weeks: String,
     pub color: Option<String>,
+    pub semester_id: Option<String>,
+    pub campus: Option<String>,
+    pub start_period: Option<i32>,
+    pub end_period: Option<i32>,
+    pub teacher_id: Option<String>,
+    pub room_id: Option<String>,
+    pub owner_id: Option<String>,
+    pub timetable_id: Option<String>,
+}
+
+// 课程周次，每门课程每个上课周一行
+#[derive(Debug, Insertable)]
+#[diesel(table_name = course_weeks)]
+pub struct NewCourseWeek<'a> {
+    pub course_id: &'a str,
+    pub week: i32,
 }
 
 // 更新模型 - 用于更新数据库
@@ -47,13 +72,18 @@ pub struct UpdateCourse {
     pub weekday: Option<i32>,
     pub start_time: Option<String>,
     pub end_time: Option<String>,
-    pub weeks: Option<String>,
     pub color: Option<String>,
+    pub semester_id: Option<String>,
+    pub campus: Option<String>,
+    pub start_period: Option<Option<i32>>, // Some(None) 表示改为按时间定义
+    pub end_period: Option<Option<i32>>,
+    pub teacher_id: Option<Option<String>>, // 修改教师时一并替换，Some(None) 表示解除关联
+    pub room_id: Option<Option<String>>,    // 修改地点时一并替换
     pub updated_at: NaiveDateTime,
 }
 
 // API 响应模型 - 用于前端交互
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CourseResponse {
     pub id: String,
     pub name: String,
@@ -63,19 +93,45 @@ pub struct CourseResponse {
     pub start_time: String, // "HH:MM:SS" 格式
     pub end_time: String,   // "HH:MM:SS" 格式
     pub weeks: Vec<i32>,    // 解析后的周次数组
+    #[serde(default)]
+    pub week_rule: String, // 由 weeks 生成的周次规则，如 "1-17单"
     pub color: Option<String>,
+    pub semester_id: Option<String>,
+    pub campus: Option<String>,
+    pub start_period: Option<i32>,
+    pub end_period: Option<i32>,
+    #[serde(default)]
+    pub teacher_id: Option<String>,
+    #[serde(default)]
+    pub room_id: Option<String>,
+    #[serde(default)]
+    pub owner_id: Option<String>,
+    #[serde(default)]
+    pub timetable_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateCourseRequest {
     pub name: String,
     pub teacher: Option<String>,
     pub location: Option<String>,
-    pub weekday: i32,       // 1=Monday, 2=Tuesday, ..., 7=Sunday
-    pub start_time: String, // "HH:MM:SS" 格式
-    pub end_time: String,   // "HH:MM:SS" 格式
+    pub weekday: i32, // 1=Monday, 2=Tuesday, ..., 7=Sunday
+    #[serde(default)]
+    pub start_time: String, // "HH:MM:SS" 格式，指定 start_period 时可省略
+    #[serde(default)]
+    pub end_time: String, // "HH:MM:SS" 格式，指定 start_period 时可省略
+    #[serde(default)]
     pub weeks: Vec<i32>,
+    pub week_rule: Option<String>, // 如 "1-16"、"1-17单"、"1-8,10-16"，指定时代替 weeks
     pub color: Option<String>,
+    pub semester_id: Option<String>,
+    pub campus: Option<String>,    // 用于选择校区作息
+    pub start_period: Option<i32>, // 按节次定义时，时间由节次表推导
+    pub end_period: Option<i32>,   // 默认与 start_period 相同
+    #[serde(default)]
+    pub teacher_id: Option<String>, // 指定时 teacher 取教师姓名，否则按 teacher 匹配已有教师
+    #[serde(default)]
+    pub room_id: Option<String>, // 指定时 location 取教室名称，否则按 location 匹配已有教室
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,7 +143,14 @@ pub struct UpdateCourseRequest {
     pub start_time: Option<String>, // "HH:MM:SS" 格式
     pub end_time: Option<String>,   // "HH:MM:SS" 格式
     pub weeks: Option<Vec<i32>>,
+    pub week_rule: Option<String>, // 指定时代替 weeks
     pub color: Option<String>,
+    pub semester_id: Option<String>,
+    pub campus: Option<String>,
+    pub start_period: Option<i32>, // 只更新 start_time/end_time 时课程改为按时间定义
+    pub end_period: Option<i32>,
+    pub teacher_id: Option<String>, // 只更新 teacher 时按姓名重新匹配教师
+    pub room_id: Option<String>,    // 只更新 location 时按名称重新匹配教室
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -101,20 +164,891 @@ pub struct PushScheduleRequest {
     pub replace: bool, // 是否替换现有课表
 }
 
-// 工具函数：将数据库模型转换为 API 响应模型
-impl From<Course> for CourseResponse {
-    fn from(course: Course) -> Self {
-        let weeks: Vec<i32> = serde_json::from_str(&course.weeks).unwrap_or_default();
+// 两门课程的时间冲突：同一学期、同一天、时间段重叠且周次有交集
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CourseConflict {
+    pub course_id: String,
+    pub course_name: String,
+    pub other_course_id: String,
+    pub other_course_name: String,
+    pub weekday: i32,
+    pub start_time: String, // 重叠时段开始
+    pub end_time: String,   // 重叠时段结束
+    pub weeks: Vec<i32>,    // 同时上课的周次
+}
+
+// GET /courses 的查询参数，所有条件同时满足才返回
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct CourseQuery {
+    pub weekday: Option<i32>,
+    pub week: Option<i32>, // 包含该周次的课程
+    pub teacher: Option<String>,
+    pub teacher_id: Option<String>,
+    pub location: Option<String>,
+    pub room_id: Option<String>,
+    pub name: Option<String>,    // 名称包含该子串
+    pub from: Option<NaiveTime>, // 与 [from, to) 时间段有重叠的课程
+    pub to: Option<NaiveTime>,
+    #[serde(default)]
+    pub sort: CourseSortField,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseSortField {
+    #[default]
+    Weekday, // 按星期，再按开始时间
+    StartTime,
+    Name,
+    Teacher,
+    Location,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl CourseQuery {
+    pub fn matches(&self, course: &CourseResponse) -> bool {
+        self.weekday.is_none_or(|weekday| course.weekday == weekday)
+            && self.week.is_none_or(|week| course.weeks.contains(&week))
+            && self
+                .teacher
+                .as_ref()
+                .is_none_or(|teacher| course.teacher.as_ref() == Some(teacher))
+            && self
+                .teacher_id
+                .as_ref()
+                .is_none_or(|teacher_id| course.teacher_id.as_ref() == Some(teacher_id))
+            && self
+                .location
+                .as_ref()
+                .is_none_or(|location| course.location.as_ref() == Some(location))
+            && self
+                .room_id
+                .as_ref()
+                .is_none_or(|room_id| course.room_id.as_ref() == Some(room_id))
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|name| course.name.contains(name.as_str()))
+            && self
+                .from
+                .is_none_or(|from| course.end_time > from.format("%H:%M:%S").to_string())
+            && self
+                .to
+                .is_none_or(|to| course.start_time < to.format("%H:%M:%S").to_string())
+    }
+
+    pub fn sort(&self, courses: &mut [CourseResponse]) {
+        courses.sort_by(|a, b| {
+            let ordering = match self.sort {
+                CourseSortField::Weekday => a
+                    .weekday
+                    .cmp(&b.weekday)
+                    .then_with(|| a.start_time.cmp(&b.start_time)),
+                CourseSortField::StartTime => a.start_time.cmp(&b.start_time),
+                CourseSortField::Name => a.name.cmp(&b.name),
+                CourseSortField::Teacher => a.teacher.cmp(&b.teacher),
+                CourseSortField::Location => a.location.cmp(&b.location),
+            };
+            match self.order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        });
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConflictReport {
+    pub conflicts: Vec<CourseConflict>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportScheduleResponse {
+    pub dry_run: bool,
+    pub replace: bool,
+    pub courses: Vec<CreateCourseRequest>, // 从日历中解析出的课程
+    pub created: Vec<CourseResponse>,      // 实际写入的课程，试运行时为空
+    pub skipped: Vec<String>,              // 无法导入的事件及原因
+}
+
+// 学期数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = semesters)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Semester {
+    pub id: String,
+    pub name: String,
+    pub start_date: NaiveDate, // 第一周内的任意一天
+    pub total_weeks: i32,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = semesters)]
+pub struct NewSemester {
+    pub id: String,
+    pub name: String,
+    pub start_date: NaiveDate,
+    pub total_weeks: i32,
+    pub is_active: bool,
+}
+
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = semesters)]
+pub struct UpdateSemester {
+    pub name: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub total_weeks: Option<i32>,
+    pub is_active: Option<bool>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSemesterRequest {
+    pub name: String,
+    pub start_date: NaiveDate, // "YYYY-MM-DD" 格式
+    pub total_weeks: i32,
+    #[serde(default)]
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateSemesterRequest {
+    pub name: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub total_weeks: Option<i32>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CurrentWeekResponse {
+    pub semester_id: String,
+    pub semester_name: String,
+    pub date: NaiveDate,
+    pub week: i32, // 可能小于 1（未开学）或大于总周数（已结束）
+    pub total_weeks: i32,
+    pub in_semester: bool,
+}
+
+// 课程的访问范围：总是按 owner_id 过滤，timetable_id 为空时不限课表
+// 写入课程时 timetable_id 为目标课表
+#[derive(Debug, Clone, Default)]
+pub struct CourseScope {
+    pub owner_id: Option<String>,
+    pub timetable_id: Option<String>,
+}
+
+impl CourseScope {
+    pub fn matches(&self, course: &CourseResponse) -> bool {
+        course.owner_id == self.owner_id
+            && self
+                .timetable_id
+                .as_ref()
+                .is_none_or(|timetable_id| course.timetable_id.as_ref() == Some(timetable_id))
+    }
+}
+
+// 课表数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = timetables)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Timetable {
+    pub id: String,
+    pub owner_id: Option<String>,
+    pub name: String,
+    pub is_default: bool, // 未指定课表的接口（/schedule、/courses 等）使用默认课表
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = timetables)]
+pub struct NewTimetable {
+    pub id: String,
+    pub owner_id: Option<String>,
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = timetables)]
+pub struct UpdateTimetable {
+    pub name: Option<String>,
+    pub is_default: Option<bool>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTimetableRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTimetableRequest {
+    pub name: Option<String>,
+    pub is_default: Option<bool>, // 只能设为 true，原默认课表自动取消
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicateTimetableRequest {
+    pub name: Option<String>, // 默认为 "<原课表名称> 副本"
+}
+
+// 课表的只读分享链接，持有令牌即可查看课表，无需登录
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = share_links)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ShareLink {
+    pub id: String,
+    pub token: String,
+    pub owner_id: Option<String>,
+    pub timetable_id: String,
+    pub expires_at: Option<NaiveDateTime>, // 为空时永不过期
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = share_links)]
+pub struct NewShareLink {
+    pub id: String,
+    pub token: String,
+    pub owner_id: Option<String>,
+    pub timetable_id: String,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateShareLinkRequest {
+    pub timetable_id: Option<String>, // 默认为当前用户的默认课表
+    pub expires_in_days: Option<i64>, // 为空时永不过期
+}
+
+// 用户数据库模型，password_hash 不会出现在响应中
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = users)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct User {
+    pub id: String,
+    pub username: String,
+    #[serde(skip)]
+    pub password_hash: String, // argon2 PHC 字符串
+    pub display_name: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = users)]
+pub struct NewUser {
+    pub id: String,
+    pub username: String,
+    pub password_hash: String,
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = user_sessions)]
+pub struct NewUserSession {
+    pub token_hash: String,
+    pub user_id: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    pub username: String,
+    pub password: String,
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+// 登录成功后返回的令牌，之后的请求以 "Authorization: Bearer <token>" 携带
+#[derive(Debug, Serialize)]
+pub struct LoginResponse {
+    pub token: String,
+    pub expires_at: NaiveDateTime,
+    pub user: User,
+}
+
+// 节次数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = periods)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Period {
+    pub id: String,
+    pub semester_id: Option<String>, // 为空表示适用于所有学期
+    pub campus: Option<String>,      // 为空表示适用于所有校区
+    pub period_index: i32,           // 第几节，从 1 开始
+    pub start_time: String,          // "HH:MM:SS" 格式
+    pub end_time: String,            // "HH:MM:SS" 格式
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = periods)]
+pub struct NewPeriod {
+    pub id: String,
+    pub semester_id: Option<String>,
+    pub campus: Option<String>,
+    pub period_index: i32,
+    pub start_time: String,
+    pub end_time: String,
+}
+
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = periods)]
+pub struct UpdatePeriod {
+    pub period_index: Option<i32>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePeriodRequest {
+    pub semester_id: Option<String>,
+    pub campus: Option<String>,
+    pub period_index: i32,
+    pub start_time: String, // "HH:MM:SS" 格式
+    pub end_time: String,   // "HH:MM:SS" 格式
+}
+
+// 学期和校区决定节次属于哪套作息，创建后不可修改
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdatePeriodRequest {
+    pub period_index: Option<i32>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+}
+
+// 日历调整数据库模型：某一天停课，或按其他日子的课表上课
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = calendar_overrides)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CalendarOverride {
+    pub id: String,
+    pub date: NaiveDate,
+    pub kind: String,         // OverrideKind 的字符串形式
+    pub weekday: Option<i32>, // swap/custom: 按星期几的课表上课
+    pub week: Option<i32>,    // custom: 按第几周的课表上课
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverrideKind {
+    Cancelled, // 停课（节假日）
+    Swap,      // 调休：按本周星期 weekday 的课表上课
+    Custom,    // 按第 week 周星期 weekday 的课表上课
+}
+
+impl OverrideKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverrideKind::Cancelled => "cancelled",
+            OverrideKind::Swap => "swap",
+            OverrideKind::Custom => "custom",
+        }
+    }
+    pub fn from_db(kind: &str) -> Option<Self> {
+        match kind {
+            "cancelled" => Some(OverrideKind::Cancelled),
+            "swap" => Some(OverrideKind::Swap),
+            "custom" => Some(OverrideKind::Custom),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = calendar_overrides)]
+pub struct NewCalendarOverride {
+    pub id: String,
+    pub date: NaiveDate,
+    pub kind: String,
+    pub weekday: Option<i32>,
+    pub week: Option<i32>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = calendar_overrides)]
+pub struct UpdateCalendarOverride {
+    pub date: Option<NaiveDate>,
+    pub kind: Option<String>,
+    pub weekday: Option<Option<i32>>,
+    pub week: Option<Option<i32>>,
+    pub note: Option<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCalendarOverrideRequest {
+    pub date: NaiveDate, // "YYYY-MM-DD" 格式
+    pub kind: OverrideKind,
+    pub weekday: Option<i32>,
+    pub week: Option<i32>,
+    pub note: Option<String>,
+}
+
+// 修改 kind 时 weekday/week 按新的 kind 整体替换，未提供的视为清空
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCalendarOverrideRequest {
+    pub date: Option<NaiveDate>,
+    pub kind: Option<OverrideKind>,
+    pub weekday: Option<i32>,
+    pub week: Option<i32>,
+    pub note: Option<String>,
+}
+
+// GET /calendar/overrides 的查询参数
+#[derive(Debug, Deserialize)]
+pub struct CalendarOverrideQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+// 教师数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = teachers)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Teacher {
+    pub id: String,
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub office: Option<String>,
+    pub office_hours: Option<String>, // 如 "周二 14:00-16:00"
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = teachers)]
+pub struct NewTeacher {
+    pub id: String,
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub office: Option<String>,
+    pub office_hours: Option<String>,
+}
+
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = teachers)]
+pub struct UpdateTeacher {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub office: Option<String>,
+    pub office_hours: Option<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTeacherRequest {
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub office: Option<String>,
+    pub office_hours: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateTeacherRequest {
+    pub name: Option<String>, // 修改姓名时关联课程的 teacher 一并更新
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub office: Option<String>,
+    pub office_hours: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TeacherScheduleResponse {
+    pub teacher: Teacher,
+    pub courses: Vec<CourseResponse>,
+}
+
+// 教室数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = rooms)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Room {
+    pub id: String,
+    pub building: String,
+    pub room_number: String,
+    pub capacity: Option<i32>,
+    pub campus: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Room {
+    // 课程 location 中使用的教室名称，如 "教学楼A 101"
+    pub fn label(&self) -> String {
+        format!("{} {}", self.building, self.room_number)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = rooms)]
+pub struct NewRoom {
+    pub id: String,
+    pub building: String,
+    pub room_number: String,
+    pub capacity: Option<i32>,
+    pub campus: Option<String>,
+}
+
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = rooms)]
+pub struct UpdateRoom {
+    pub building: Option<String>,
+    pub room_number: Option<String>,
+    pub capacity: Option<i32>,
+    pub campus: Option<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRoomRequest {
+    pub building: String,
+    pub room_number: String,
+    pub capacity: Option<i32>,
+    pub campus: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRoomRequest {
+    pub building: Option<String>, // 修改楼栋或编号时关联课程的 location 一并更新
+    pub room_number: Option<String>,
+    pub capacity: Option<i32>,
+    pub campus: Option<String>,
+}
+
+// GET /rooms/free 的查询参数：第 week 周（为空时为所有周）星期 weekday 的 [start, end) 时段
+#[derive(Debug, Deserialize)]
+pub struct FreeRoomQuery {
+    pub weekday: i32,
+    pub week: Option<i32>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub campus: Option<String>,
+    pub min_capacity: Option<i32>,
+    pub semester_id: Option<String>, // 默认为当前学期
+}
+
+// 课程某一周的单次调整，不影响其余周次
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = course_exceptions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CourseException {
+    pub id: String,
+    pub course_id: String,
+    pub week: i32,
+    pub action: String,       // ExceptionAction 的字符串形式
+    pub weekday: Option<i32>, // reschedule: 改到同一周的星期几，为空时日期不变
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub location: Option<String>,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExceptionAction {
+    Cancel,     // 本周停课一次
+    Reschedule, // 本周改时间、改星期或换教室
+}
+
+impl ExceptionAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExceptionAction::Cancel => "cancel",
+            ExceptionAction::Reschedule => "reschedule",
+        }
+    }
+    pub fn from_db(action: &str) -> Option<Self> {
+        match action {
+            "cancel" => Some(ExceptionAction::Cancel),
+            "reschedule" => Some(ExceptionAction::Reschedule),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = course_exceptions)]
+pub struct NewCourseException {
+    pub id: String,
+    pub course_id: String,
+    pub week: i32,
+    pub action: String,
+    pub weekday: Option<i32>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub location: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = course_exceptions)]
+pub struct UpdateCourseException {
+    pub week: Option<i32>,
+    pub action: Option<String>,
+    pub weekday: Option<Option<i32>>,
+    pub start_time: Option<Option<String>>,
+    pub end_time: Option<Option<String>>,
+    pub location: Option<Option<String>>,
+    pub note: Option<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCourseExceptionRequest {
+    pub course_id: String,
+    pub week: i32,
+    pub action: ExceptionAction,
+    pub weekday: Option<i32>,
+    pub start_time: Option<String>, // "HH:MM:SS" 格式，为空时沿用课程时间
+    pub end_time: Option<String>,
+    pub location: Option<String>,
+    pub note: Option<String>,
+}
+
+// 修改 action 时 weekday、时间和地点按新的 action 整体替换，未提供的视为清空
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCourseExceptionRequest {
+    pub week: Option<i32>,
+    pub action: Option<ExceptionAction>,
+    pub weekday: Option<i32>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub location: Option<String>,
+    pub note: Option<String>,
+}
+
+// GET /course-exceptions 的查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct CourseExceptionQuery {
+    pub course_id: Option<String>,
+    pub week: Option<i32>,
+}
+
+// 某一天实际的课程安排
+#[derive(Debug, Serialize)]
+pub struct DayScheduleResponse {
+    pub date: NaiveDate,
+    pub semester_id: String,
+    pub week: i32,    // 日期所在的教学周
+    pub weekday: i32, // 日期本身是星期几
+    pub in_semester: bool,
+    #[serde(rename = "override")]
+    pub calendar_override: Option<CalendarOverride>,
+    pub schedule_week: Option<i32>, // 实际按哪一周、星期几的课表上课，停课时为空
+    pub schedule_weekday: Option<i32>,
+    pub courses: Vec<CourseResponse>, // 已应用单次调整后的时间和地点
+    pub exceptions: Vec<CourseException>, // 影响当天的单次调整（停课、调出、调入）
+}
+
+// GET /schedule/free-slots 的查询参数
+#[derive(Debug, Deserialize)]
+pub struct FreeSlotQuery {
+    pub week: Option<i32>,    // 默认为当前学期的本周
+    pub weekday: Option<i32>, // 为空时返回一周七天
+    #[serde(default)]
+    pub min_minutes: i64,
+    pub between: Option<String>,     // "HH:MM-HH:MM"，默认 08:00-22:00
+    pub semester_id: Option<String>, // 默认为当前学期
+}
+
+#[derive(Debug, Serialize)]
+pub struct FreeSlot {
+    pub start: String,
+    pub end: String,
+    pub minutes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DayFreeSlots {
+    pub weekday: i32,
+    pub date: Option<NaiveDate>, // 没有学期时无法确定日期
+    pub slots: Vec<FreeSlot>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FreeSlotsResponse {
+    pub semester_id: Option<String>,
+    pub week: i32,
+    pub start: String, // 统计的时间范围
+    pub end: String,
+    pub min_minutes: i64,
+    pub days: Vec<DayFreeSlots>,
+}
+
+// POST /schedule/common-free 的请求：自己的课表用 ID，其他人的课表用分享令牌
+#[derive(Debug, Deserialize)]
+pub struct CommonFreeRequest {
+    #[serde(default)]
+    pub timetable_ids: Vec<String>,
+    #[serde(default)]
+    pub share_tokens: Vec<String>,
+    pub from_week: i32,
+    pub to_week: i32,
+    #[serde(default)]
+    pub min_minutes: i64,
+    pub between: Option<String>, // "HH:MM-HH:MM"，默认 08:00-22:00
+}
+
+// 所有人都空闲的时段，weeks 为该时段空闲的周次
+#[derive(Debug, Serialize)]
+pub struct CommonFreeSlot {
+    pub weekday: i32,
+    pub start: String,
+    pub end: String,
+    pub minutes: i64,
+    pub free_weeks: usize,
+    pub weeks: Vec<i32>,
+    pub week_rule: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommonFreeResponse {
+    pub timetables: usize, // 参与计算的课表数
+    pub from_week: i32,
+    pub to_week: i32,
+    pub start: String,
+    pub end: String,
+    pub min_minutes: i64,
+    pub slots: Vec<CommonFreeSlot>, // 按空闲周数从多到少排列
+}
+
+// 单门课程的提醒设置，没有记录的课程按全局提前时间提醒
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = course_reminders)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CourseReminder {
+    pub course_id: String,
+    pub enabled: bool,
+    pub minutes_before: Option<i32>, // 为空时使用全局提前时间
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = course_reminders)]
+pub struct NewCourseReminder {
+    pub course_id: String,
+    pub enabled: bool,
+    pub minutes_before: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCourseReminderRequest {
+    pub enabled: Option<bool>,       // 默认开启
+    pub minutes_before: Option<i32>, // 为空时使用全局提前时间
+}
+
+// 课程实际生效的提醒设置
+#[derive(Debug, Serialize)]
+pub struct CourseReminderResponse {
+    pub course_id: String,
+    pub enabled: bool,
+    pub minutes_before: i32,
+    pub uses_default: bool, // 是否沿用全局提前时间
+}
+
+// 一次上课提醒，发给各个通知渠道
+#[derive(Debug, Clone, Serialize)]
+pub struct Reminder {
+    pub course: CourseResponse,
+    pub date: NaiveDate,
+    pub week: i32,
+    pub starts_at: NaiveDateTime,
+    pub minutes_before: i32,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = reminder_deliveries)]
+pub struct NewReminderDelivery<'a> {
+    pub course_id: &'a str,
+    pub starts_at: NaiveDateTime,
+}
+
+impl Semester {
+    // 第一周的周一
+    pub fn first_monday(&self) -> NaiveDate {
+        self.start_date - Duration::days(self.start_date.weekday().num_days_from_monday() as i64)
+    }
+
+    // 计算某个日期所在的教学周（从 1 开始）
+    pub fn week_of(&self, date: NaiveDate) -> i32 {
+        let days = (date - self.first_monday()).num_days();
+        (days.div_euclid(7) + 1) as i32
+    }
+
+    // 计算第 week 周星期 weekday 对应的日期，超出日期范围时返回 None
+    pub fn date_of(&self, week: i32, weekday: i32) -> Option<NaiveDate> {
+        let days = (week as i64 - 1) * 7 + (weekday as i64 - 1);
+        self.first_monday()
+            .checked_add_signed(Duration::try_days(days)?)
+    }
+
+    pub fn contains_week(&self, week: i32) -> bool {
+        week >= 1 && week <= self.total_weeks
+    }
+}
+
+// 工具函数：将数据库模型和 course_weeks 中的周次合并为 API 响应模型
+impl Course {
+    pub fn into_response(self, weeks: Vec<i32>) -> CourseResponse {
         CourseResponse {
-            id: course.id,
-            name: course.name,
-            teacher: course.teacher,
-            location: course.location,
-            weekday: course.weekday,
-            start_time: course.start_time,
-            end_time: course.end_time,
+            id: self.id,
+            name: self.name,
+            teacher: self.teacher,
+            location: self.location,
+            weekday: self.weekday,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            week_rule: crate::week_rule::format_week_rule(&weeks),
             weeks,
-            color: course.color,
+            color: self.color,
+            semester_id: self.semester_id,
+            campus: self.campus,
+            start_period: self.start_period,
+            end_period: self.end_period,
+            teacher_id: self.teacher_id,
+            room_id: self.room_id,
+            owner_id: self.owner_id,
+            timetable_id: self.timetable_id,
         }
     }
 }
+
+// 工具函数：解析 "HH:MM:SS"（兼容 "HH:MM"）格式的时间
+pub fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .ok()
+}
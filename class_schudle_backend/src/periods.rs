@@ -0,0 +1,49 @@
+use crate::models::{CourseResponse, Period};
+
+// 查找适用于课程的节次：学期、校区都匹配的作息优先，其次只匹配学期，再次只匹配校区，最后是通用作息
+pub fn find_period<'a>(
+    periods: &'a [Period],
+    semester_id: Option<&str>,
+    campus: Option<&str>,
+    period_index: i32,
+) -> Option<&'a Period> {
+    periods
+        .iter()
+        .filter(|p| p.period_index == period_index)
+        .filter(|p| p.semester_id.is_none() || p.semester_id.as_deref() == semester_id)
+        .filter(|p| p.campus.is_none() || p.campus.as_deref() == campus)
+        .max_by_key(|p| (p.semester_id.is_some(), p.campus.is_some()))
+}
+
+// 由起止节次推导上课时间，找不到节次时返回缺失的节次编号
+pub fn period_times(
+    periods: &[Period],
+    course: &CourseResponse,
+    start_period: i32,
+    end_period: i32,
+) -> Result<(String, String), i32> {
+    let semester_id = course.semester_id.as_deref();
+    let campus = course.campus.as_deref();
+    let start = find_period(periods, semester_id, campus, start_period).ok_or(start_period)?;
+    let end = find_period(periods, semester_id, campus, end_period).ok_or(end_period)?;
+    Ok((start.start_time.clone(), end.end_time.clone()))
+}
+
+// 按节次重新计算课程时间，返回时间是否有变化
+// 节次已被删除的课程保留原来的时间
+pub fn resync_course_times(periods: &[Period], course: &mut CourseResponse) -> bool {
+    let (Some(start_period), Some(end_period)) = (course.start_period, course.end_period) else {
+        return false;
+    };
+
+    match period_times(periods, course, start_period, end_period) {
+        Ok((start_time, end_time))
+            if start_time != course.start_time || end_time != course.end_time =>
+        {
+            course.start_time = start_time;
+            course.end_time = end_time;
+            true
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,479 @@
+use actix_web::web;
+use chrono::{Duration, Local, NaiveDateTime};
+use futures_util::future::BoxFuture;
+use log::{debug, info, warn};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::Mutex;
+use std::time::Duration as StdDuration;
+
+use crate::calendar;
+use crate::events::{EventBus, ScheduleEvent};
+use crate::models::{parse_time, CourseExceptionQuery, CourseReminder, Reminder};
+use crate::storage::{self, Repository, StorageError};
+
+// 单门课程可设置的最长提前时间（分钟），调度每次只需检查今天和明天的课
+pub const MAX_MINUTES_BEFORE: i32 = 24 * 60;
+
+// 提醒调度配置，全部来自环境变量
+#[derive(Debug, Clone)]
+pub struct ReminderConfig {
+    pub enabled: bool,               // REMINDERS_ENABLED，默认开启
+    pub minutes_before: i32,         // REMINDER_MINUTES_BEFORE，全局提前时间，默认 10 分钟
+    pub interval: StdDuration,       // REMINDER_CHECK_INTERVAL_SECS，检查间隔，默认 30 秒
+    pub webhook_url: Option<String>, // REMINDER_WEBHOOK_URL，设置后以 POST JSON 推送提醒
+}
+
+impl ReminderConfig {
+    pub fn from_env() -> Self {
+        let enabled = !matches!(
+            env::var("REMINDERS_ENABLED").as_deref(),
+            Ok("false") | Ok("0")
+        );
+        let minutes_before = env::var("REMINDER_MINUTES_BEFORE")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|minutes| (0..=MAX_MINUTES_BEFORE).contains(minutes))
+            .unwrap_or(10);
+        let interval = env::var("REMINDER_CHECK_INTERVAL_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(30);
+        let webhook_url = env::var("REMINDER_WEBHOOK_URL")
+            .ok()
+            .filter(|url| !url.is_empty());
+        Self {
+            enabled,
+            minutes_before,
+            interval: StdDuration::from_secs(interval),
+            webhook_url,
+        }
+    }
+
+    // 课程实际生效的提前时间，关闭提醒时返回 None
+    pub fn effective_minutes(&self, reminder: Option<&CourseReminder>) -> Option<i32> {
+        match reminder {
+            Some(reminder) if !reminder.enabled => None,
+            Some(reminder) => Some(reminder.minutes_before.unwrap_or(self.minutes_before)),
+            None => Some(self.minutes_before),
+        }
+    }
+}
+
+// 提醒的发送渠道
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn notify<'a>(&'a self, reminder: &'a Reminder) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+// 通过 GET /events 推送 course.reminder 事件给课程所属的用户
+pub struct EventStreamNotifier {
+    events: EventBus,
+}
+
+impl EventStreamNotifier {
+    pub fn new(events: EventBus) -> Self {
+        Self { events }
+    }
+}
+
+impl Notifier for EventStreamNotifier {
+    fn name(&self) -> &'static str {
+        "event-stream"
+    }
+
+    fn notify<'a>(&'a self, reminder: &'a Reminder) -> BoxFuture<'a, anyhow::Result<()>> {
+        self.events.publish(ScheduleEvent::reminder(reminder));
+        Box::pin(async { Ok(()) })
+    }
+}
+
+// 以 JSON 请求体 POST 到配置的地址
+pub struct WebhookNotifier {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(StdDuration::from_secs(10))
+            .build();
+        Self { url, agent }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn notify<'a>(&'a self, reminder: &'a Reminder) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let body = serde_json::to_value(reminder)?;
+            let agent = self.agent.clone();
+            let url = self.url.clone();
+            // ureq 是阻塞客户端，放到阻塞线程池执行
+            web::block(move || {
+                agent
+                    .post(&url)
+                    .send_json(body)
+                    .map(|_| ())
+                    .map_err(anyhow::Error::from)
+            })
+            .await?
+        })
+    }
+}
+
+// 定时检查即将开始的课程，在开课前 N 分钟通过各个渠道发送提醒
+pub struct ReminderScheduler {
+    repo: Repository,
+    config: ReminderConfig,
+    notifiers: Vec<Box<dyn Notifier>>,
+    // 部分渠道发送失败的上课，记录已发送成功的渠道，重试时只发送失败的渠道
+    partial: Mutex<HashMap<(String, NaiveDateTime), HashSet<&'static str>>>,
+}
+
+impl ReminderScheduler {
+    pub fn new(
+        repo: Repository,
+        config: ReminderConfig,
+        notifiers: Vec<Box<dyn Notifier>>,
+    ) -> Self {
+        Self {
+            repo,
+            config,
+            notifiers,
+            partial: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn spawn(self) {
+        let names: Vec<&str> = self.notifiers.iter().map(|n| n.name()).collect();
+        info!(
+            "⏰ 课程提醒已启动: 提前 {} 分钟, 每 {} 秒检查, 渠道: {}",
+            self.config.minutes_before,
+            self.config.interval.as_secs(),
+            names.join(", ")
+        );
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(self.config.interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.check(Local::now().naive_local()).await {
+                    warn!("⚠️ 检查课程提醒失败: {}", e);
+                }
+            }
+        });
+    }
+
+    // 找出提醒时间已到、尚未开课且未提醒过的课程，所有渠道都发送成功后才记录
+    // 发送失败的在下一次检查时重试，直到开课；服务停止期间错过的提醒在重启后补发
+    async fn check(&self, now: NaiveDateTime) -> Result<(), StorageError> {
+        self.partial
+            .lock()
+            .unwrap()
+            .retain(|(_, starts_at), _| *starts_at > now);
+
+        let Some(semester) = storage::run(&self.repo, |repo| repo.get_active_semester()).await?
+        else {
+            return Ok(());
+        };
+        let courses = storage::run(&self.repo, |repo| repo.list_reminder_courses()).await?;
+        if courses.is_empty() {
+            return Ok(());
+        }
+        let settings: HashMap<String, CourseReminder> =
+            storage::run(&self.repo, |repo| repo.list_course_reminders())
+                .await?
+                .into_iter()
+                .map(|reminder| (reminder.course_id.clone(), reminder))
+                .collect();
+        let exceptions = storage::run(&self.repo, |repo| {
+            repo.list_exceptions(&CourseExceptionQuery::default())
+        })
+        .await?;
+
+        // 提前时间最多一天，只需检查今天和明天
+        let today = now.date();
+        for date in [today, today + Duration::days(1)] {
+            let calendar_override =
+                storage::run(&self.repo, move |repo| repo.get_override_by_date(date)).await?;
+            let (day, _) = calendar::day_courses(
+                &semester,
+                date,
+                calendar_override.as_ref(),
+                &courses,
+                &exceptions,
+            );
+
+            for course in day {
+                let Some(minutes_before) = self.config.effective_minutes(settings.get(&course.id))
+                else {
+                    continue;
+                };
+                let Some(start_time) = parse_time(&course.start_time) else {
+                    continue;
+                };
+                let starts_at = date.and_time(start_time);
+                if now < starts_at - Duration::minutes(minutes_before as i64) || now >= starts_at {
+                    continue;
+                }
+
+                let course_id = course.id.clone();
+                let delivered = storage::run(&self.repo, move |repo| {
+                    repo.reminder_delivered(&course_id, starts_at)
+                })
+                .await?;
+                if delivered {
+                    continue;
+                }
+
+                let reminder = Reminder {
+                    week: semester.week_of(date),
+                    course,
+                    date,
+                    starts_at,
+                    minutes_before,
+                };
+                if !self.dispatch(&reminder).await {
+                    continue;
+                }
+                let course_id = reminder.course.id.clone();
+                storage::run(&self.repo, move |repo| {
+                    repo.record_reminder_delivery(&course_id, starts_at)
+                })
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    // 通过尚未发送成功的渠道发送提醒，全部渠道都已发送成功时返回 true
+    async fn dispatch(&self, reminder: &Reminder) -> bool {
+        let key = (reminder.course.id.clone(), reminder.starts_at);
+        let mut sent = self
+            .partial
+            .lock()
+            .unwrap()
+            .get(&key)
+            .cloned()
+            .unwrap_or_default();
+        info!(
+            "🔔 发送课程提醒: {} 于 {} 开始",
+            reminder.course.name, reminder.starts_at
+        );
+        for notifier in &self.notifiers {
+            if sent.contains(notifier.name()) {
+                continue;
+            }
+            match notifier.notify(reminder).await {
+                Ok(()) => {
+                    debug!("📨 提醒已通过 {} 发送", notifier.name());
+                    sent.insert(notifier.name());
+                }
+                Err(e) => warn!("⚠️ 通过 {} 发送提醒失败，稍后重试: {}", notifier.name(), e),
+            }
+        }
+
+        let complete = self.notifiers.iter().all(|n| sent.contains(n.name()));
+        let mut partial = self.partial.lock().unwrap();
+        if complete {
+            partial.remove(&key);
+        } else {
+            partial.insert(key, sent);
+        }
+        complete
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database;
+    use crate::db_storage::SqliteRepository;
+    use crate::models::{CourseScope, CreateCourseRequest, CreateSemesterRequest};
+    use crate::storage::{MemoryRepository, ScheduleRepository};
+    use chrono::NaiveDate;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    // 记录发送过的课程名称，failing 为 true 时发送失败
+    #[derive(Clone, Default)]
+    struct FakeNotifier {
+        name: &'static str,
+        sent: Arc<Mutex<Vec<String>>>,
+        failing: Arc<AtomicBool>,
+    }
+
+    impl FakeNotifier {
+        fn new(name: &'static str) -> Self {
+            Self {
+                name,
+                ..Default::default()
+            }
+        }
+
+        fn sent(&self) -> Vec<String> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    impl Notifier for FakeNotifier {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn notify<'a>(&'a self, reminder: &'a Reminder) -> BoxFuture<'a, anyhow::Result<()>> {
+            let result = if self.failing.load(Ordering::SeqCst) {
+                Err(anyhow::anyhow!("{} unavailable", self.name))
+            } else {
+                self.sent.lock().unwrap().push(reminder.course.name.clone());
+                Ok(())
+            };
+            Box::pin(async move { result })
+        }
+    }
+
+    fn config() -> ReminderConfig {
+        ReminderConfig {
+            enabled: true,
+            minutes_before: 10,
+            interval: StdDuration::from_secs(30),
+            webhook_url: None,
+        }
+    }
+
+    // 当前学期从 2026-09-07（周一）开始，默认课表中有一门周一 10:00 开始的课
+    fn repositories() -> Vec<(&'static str, Repository)> {
+        let repos: Vec<(&'static str, Arc<dyn ScheduleRepository>)> = vec![
+            (
+                "memory",
+                Arc::new(MemoryRepository::new(EventBus::default())),
+            ),
+            (
+                "sqlite",
+                Arc::new(SqliteRepository::new(
+                    database::create_memory_pool(),
+                    EventBus::default(),
+                )),
+            ),
+        ];
+        repos
+            .into_iter()
+            .map(|(backend, repo)| {
+                repo.insert_semester(&CreateSemesterRequest {
+                    name: "秋季学期".to_string(),
+                    start_date: NaiveDate::from_ymd_opt(2026, 9, 7).unwrap(),
+                    total_weeks: 20,
+                    is_active: true,
+                })
+                .unwrap();
+                let scope = CourseScope {
+                    owner_id: None,
+                    timetable_id: Some(repo.default_timetable(None).unwrap().id),
+                };
+                let course: CreateCourseRequest = serde_json::from_value(json!({
+                    "name": "高等数学",
+                    "weekday": 1,
+                    "start_time": "10:00:00",
+                    "end_time": "11:40:00",
+                    "weeks": [1, 2, 3],
+                }))
+                .unwrap();
+                repo.insert_course(&scope, &course).unwrap();
+                (backend, web::Data::from(repo))
+            })
+            .collect()
+    }
+
+    fn at(time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("2026-09-07 {}", time), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn scheduler(repo: &Repository, notifiers: &[&FakeNotifier]) -> ReminderScheduler {
+        let notifiers = notifiers
+            .iter()
+            .map(|notifier| Box::new((*notifier).clone()) as Box<dyn Notifier>)
+            .collect();
+        ReminderScheduler::new(repo.clone(), config(), notifiers)
+    }
+
+    #[actix_web::test]
+    async fn reminds_once_within_the_window() {
+        for (backend, repo) in repositories() {
+            let notifier = FakeNotifier::new("fake");
+            let scheduler = scheduler(&repo, &[&notifier]);
+
+            scheduler.check(at("09:49")).await.unwrap();
+            assert!(notifier.sent().is_empty(), "{}", backend);
+
+            for time in ["09:50", "09:55", "09:59"] {
+                scheduler.check(at(time)).await.unwrap();
+            }
+            assert_eq!(notifier.sent(), vec!["高等数学"], "{}", backend);
+
+            scheduler.check(at("10:00")).await.unwrap();
+            assert_eq!(notifier.sent().len(), 1, "{}", backend);
+        }
+    }
+
+    #[actix_web::test]
+    async fn failed_channels_are_retried_until_delivered() {
+        for (backend, repo) in repositories() {
+            let events = FakeNotifier::new("events");
+            let webhook = FakeNotifier::new("webhook");
+            webhook.failing.store(true, Ordering::SeqCst);
+            let scheduler = scheduler(&repo, &[&events, &webhook]);
+
+            scheduler.check(at("09:51")).await.unwrap();
+            scheduler.check(at("09:52")).await.unwrap();
+            assert_eq!(events.sent().len(), 1, "{}", backend);
+            assert!(webhook.sent().is_empty(), "{}", backend);
+
+            // 恢复后只重试失败的渠道，之后不再发送
+            webhook.failing.store(false, Ordering::SeqCst);
+            scheduler.check(at("09:53")).await.unwrap();
+            scheduler.check(at("09:54")).await.unwrap();
+            assert_eq!(events.sent().len(), 1, "{}", backend);
+            assert_eq!(webhook.sent().len(), 1, "{}", backend);
+        }
+    }
+
+    #[actix_web::test]
+    async fn deliveries_are_persisted_across_restarts() {
+        for (backend, repo) in repositories() {
+            let notifier = FakeNotifier::new("fake");
+            scheduler(&repo, &[&notifier])
+                .check(at("09:52"))
+                .await
+                .unwrap();
+
+            // 重启后的调度器不会重复提醒
+            let restarted = FakeNotifier::new("fake");
+            scheduler(&repo, &[&restarted])
+                .check(at("09:53"))
+                .await
+                .unwrap();
+            assert_eq!(notifier.sent().len(), 1, "{}", backend);
+            assert!(restarted.sent().is_empty(), "{}", backend);
+
+            // 全部渠道都失败时不记录，重启后补发
+            let failing = FakeNotifier::new("fake");
+            failing.failing.store(true, Ordering::SeqCst);
+            let next_week = at("09:52") + Duration::weeks(1);
+            scheduler(&repo, &[&failing])
+                .check(next_week)
+                .await
+                .unwrap();
+            scheduler(&repo, &[&restarted])
+                .check(next_week + Duration::minutes(1))
+                .await
+                .unwrap();
+            assert_eq!(restarted.sent().len(), 1, "{}", backend);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use crate::calendar::{apply_exception, in_semester};
+use crate::models::{
+    CourseException, CourseResponse, ExceptionAction, FreeRoomQuery, Room, Semester,
+};
+use crate::teachers::name_key;
+
+// 按名称查找教室（忽略空白和大小写），不同校区有同名教室时取课程所在校区的
+pub fn find_by_location<'a>(
+    rooms: &'a [Room],
+    location: &str,
+    campus: Option<&str>,
+) -> Option<&'a Room> {
+    let key = name_key(location);
+    if key.is_empty() {
+        return None;
+    }
+
+    let matches: Vec<&Room> = rooms
+        .iter()
+        .filter(|r| name_key(&r.label()) == key)
+        .collect();
+    match matches.as_slice() {
+        [room] => Some(room),
+        _ => {
+            let mut same_campus = matches
+                .into_iter()
+                .filter(|r| campus.is_some() && r.campus.as_deref() == campus);
+            match (same_campus.next(), same_campus.next()) {
+                (Some(room), None) => Some(room),
+                _ => None,
+            }
+        }
+    }
+}
+
+// 关联课程的教室：指定 room_id 时 location 取教室名称，否则按 location 匹配已有教室
+// room_id 指向的教室不存在时返回该 ID
+pub fn link_room(rooms: &[Room], course: &mut CourseResponse) -> Result<(), String> {
+    if let Some(id) = &course.room_id {
+        let linked = rooms.iter().find(|r| &r.id == id).ok_or(id.clone())?;
+        course.location = Some(linked.label());
+        return Ok(());
+    }
+
+    let linked = course
+        .location
+        .as_deref()
+        .and_then(|location| find_by_location(rooms, location, course.campus.as_deref()));
+    if let Some(linked) = linked {
+        course.location = Some(linked.label());
+        course.room_id = Some(linked.id.clone());
+    }
+    Ok(())
+}
+
+// 课程所在的教室：已关联的教室，否则按 location 匹配
+fn course_room<'a>(rooms: &'a [Room], course: &CourseResponse) -> Option<&'a Room> {
+    match &course.room_id {
+        Some(room_id) => rooms.iter().find(|r| &r.id == room_id),
+        None => course
+            .location
+            .as_deref()
+            .and_then(|location| find_by_location(rooms, location, course.campus.as_deref())),
+    }
+}
+
+// 查询时段内被课程占用的教室 ID，未指定周次时任意一周有课即视为占用
+// 指定周次时应用该周的单次停课和调课；semester 为空时考虑所有课程
+pub fn occupied_rooms(
+    rooms: &[Room],
+    courses: &[CourseResponse],
+    exceptions: &[CourseException],
+    semester: Option<&Semester>,
+    query: &FreeRoomQuery,
+) -> HashSet<String> {
+    let start = query.start.format("%H:%M:%S").to_string();
+    let end = query.end.format("%H:%M:%S").to_string();
+    let mut occupied = HashSet::new();
+
+    for course in courses {
+        if semester.is_some_and(|semester| !in_semester(course, semester)) {
+            continue;
+        }
+
+        let course = match query.week {
+            Some(week) => {
+                if !course.weeks.contains(&week) {
+                    continue;
+                }
+                let exception = exceptions
+                    .iter()
+                    .find(|e| e.course_id == course.id && e.week == week);
+                match exception {
+                    Some(exception)
+                        if ExceptionAction::from_db(&exception.action)
+                            == Some(ExceptionAction::Cancel) =>
+                    {
+                        continue
+                    }
+                    Some(exception) => {
+                        let mut rescheduled = apply_exception(course, exception);
+                        // 调课换了教室时按新地点匹配
+                        if exception.location.is_some() {
+                            rescheduled.room_id = None;
+                        }
+                        rescheduled
+                    }
+                    None => course.clone(),
+                }
+            }
+            None => course.clone(),
+        };
+
+        let overlaps =
+            course.weekday == query.weekday && course.start_time < end && course.end_time > start;
+        if !overlaps {
+            continue;
+        }
+        if let Some(room) = course_room(rooms, &course) {
+            occupied.insert(room.id.clone());
+        }
+    }
+
+    occupied
+}
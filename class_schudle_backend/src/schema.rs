@@ -1,5 +1,58 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    calendar_overrides (id) {
+        id -> Text,
+        date -> Date,
+        kind -> Text,
+        weekday -> Nullable<Integer>,
+        week -> Nullable<Integer>,
+        note -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    course_exceptions (id) {
+        id -> Text,
+        course_id -> Text,
+        week -> Integer,
+        action -> Text,
+        weekday -> Nullable<Integer>,
+        start_time -> Nullable<Text>,
+        end_time -> Nullable<Text>,
+        location -> Nullable<Text>,
+        note -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    course_reminders (course_id) {
+        course_id -> Text,
+        enabled -> Bool,
+        minutes_before -> Nullable<Integer>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    course_weeks (course_id, week) {
+        course_id -> Text,
+        week -> Integer,
+    }
+}
+
+diesel::table! {
+    course_weeks_legacy (course_id) {
+        course_id -> Text,
+        weeks -> Text,
+    }
+}
+
 diesel::table! {
     courses (id) {
         id -> Text,
@@ -9,9 +62,149 @@ diesel::table! {
         weekday -> Integer,
         start_time -> Text,
         end_time -> Text,
-        weeks -> Text,
         color -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        semester_id -> Nullable<Text>,
+        campus -> Nullable<Text>,
+        start_period -> Nullable<Integer>,
+        end_period -> Nullable<Integer>,
+        teacher_id -> Nullable<Text>,
+        room_id -> Nullable<Text>,
+        owner_id -> Nullable<Text>,
+        timetable_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    periods (id) {
+        id -> Text,
+        semester_id -> Nullable<Text>,
+        campus -> Nullable<Text>,
+        period_index -> Integer,
+        start_time -> Text,
+        end_time -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    reminder_deliveries (course_id, starts_at) {
+        course_id -> Text,
+        starts_at -> Timestamp,
+        notified_at -> Timestamp,
     }
 }
+
+diesel::table! {
+    rooms (id) {
+        id -> Text,
+        building -> Text,
+        room_number -> Text,
+        capacity -> Nullable<Integer>,
+        campus -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    semesters (id) {
+        id -> Text,
+        name -> Text,
+        start_date -> Date,
+        total_weeks -> Integer,
+        is_active -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    share_links (id) {
+        id -> Text,
+        token -> Text,
+        owner_id -> Nullable<Text>,
+        timetable_id -> Text,
+        expires_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    teachers (id) {
+        id -> Text,
+        name -> Text,
+        email -> Nullable<Text>,
+        phone -> Nullable<Text>,
+        office -> Nullable<Text>,
+        office_hours -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    timetables (id) {
+        id -> Text,
+        owner_id -> Nullable<Text>,
+        name -> Text,
+        is_default -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    user_sessions (token_hash) {
+        token_hash -> Text,
+        user_id -> Text,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    users (id) {
+        id -> Text,
+        username -> Text,
+        password_hash -> Text,
+        display_name -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(course_exceptions -> courses (course_id));
+diesel::joinable!(course_reminders -> courses (course_id));
+diesel::joinable!(course_weeks -> courses (course_id));
+diesel::joinable!(courses -> rooms (room_id));
+diesel::joinable!(courses -> semesters (semester_id));
+diesel::joinable!(courses -> teachers (teacher_id));
+diesel::joinable!(courses -> timetables (timetable_id));
+diesel::joinable!(courses -> users (owner_id));
+diesel::joinable!(periods -> semesters (semester_id));
+diesel::joinable!(reminder_deliveries -> courses (course_id));
+diesel::joinable!(share_links -> timetables (timetable_id));
+diesel::joinable!(share_links -> users (owner_id));
+diesel::joinable!(timetables -> users (owner_id));
+diesel::joinable!(user_sessions -> users (user_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    calendar_overrides,
+    course_exceptions,
+    course_reminders,
+    course_weeks,
+    course_weeks_legacy,
+    courses,
+    periods,
+    reminder_deliveries,
+    rooms,
+    semesters,
+    share_links,
+    teachers,
+    timetables,
+    user_sessions,
+    users,
+);
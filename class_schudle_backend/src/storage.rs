@@ -1,53 +1,1768 @@
-use crate::models::Course;
+use actix_web::error::BlockingError;
+use actix_web::web;
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use diesel::r2d2::PoolError;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
 use log::{debug, info};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
 use uuid::Uuid;
 
-pub type Storage = Arc<Mutex<HashMap<Uuid, Course>>>;
+use crate::events::{EventBus, EventKind, ScheduleEvent};
+use crate::models::{
+    CalendarOverride, CourseException, CourseExceptionQuery, CourseQuery, CourseReminder,
+    CourseResponse, CourseScope, CreateCalendarOverrideRequest, CreateCourseExceptionRequest,
+    CreateCourseRequest, CreatePeriodRequest, CreateRoomRequest, CreateSemesterRequest,
+    CreateTeacherRequest, CreateTimetableRequest, NewCourseReminder, NewShareLink, NewUserSession,
+    Period, RegisterRequest, Room, Semester, ShareLink, Teacher, Timetable,
+    UpdateCalendarOverrideRequest, UpdateCourseExceptionRequest, UpdateCourseRequest,
+    UpdatePeriodRequest, UpdateRoomRequest, UpdateSemesterRequest, UpdateTeacherRequest,
+    UpdateTimetableRequest, User,
+};
 
-pub fn create_storage() -> Storage {
-    info!("💾 初始化内存存储");
-    Arc::new(Mutex::new(HashMap::new()))
+// 课程表存储接口，由 SQLite（db_storage::SqliteRepository）和内存（MemoryRepository）两种后端实现
+// 所有方法都是同步阻塞的，在 handler 中通过 storage::run 放到阻塞线程池执行
+pub trait ScheduleRepository: Send + Sync {
+    // 课程按 scope 隔离：登录用户只能访问自己的课程，owner_id 为空时访问不属于任何用户的课程
+    fn list_courses(&self, scope: &CourseScope) -> Result<Vec<CourseResponse>, StorageError>;
+    // 不分用户和课表的全部课程，只用于教室占用这类全局查询
+    fn list_all_courses(&self) -> Result<Vec<CourseResponse>, StorageError>;
+    fn find_courses(
+        &self,
+        scope: &CourseScope,
+        query: &CourseQuery,
+    ) -> Result<Vec<CourseResponse>, StorageError>;
+    fn get_course(
+        &self,
+        scope: &CourseScope,
+        course_id: &str,
+    ) -> Result<Option<CourseResponse>, StorageError>;
+    fn insert_course(
+        &self,
+        scope: &CourseScope,
+        course_req: &CreateCourseRequest,
+    ) -> Result<CourseResponse, StorageError>;
+    fn update_course(
+        &self,
+        scope: &CourseScope,
+        course_id: &str,
+        update_req: &UpdateCourseRequest,
+    ) -> Result<Option<CourseResponse>, StorageError>;
+    fn delete_course(&self, scope: &CourseScope, course_id: &str) -> Result<bool, StorageError>;
+    fn delete_all_courses(&self, scope: &CourseScope) -> Result<usize, StorageError>;
+    // 批量写入课程：replace 为 true 时先清空现有课程，两步在同一事务中完成
+    fn insert_courses(
+        &self,
+        scope: &CourseScope,
+        course_requests: &[CreateCourseRequest],
+        replace: bool,
+    ) -> Result<Vec<CourseResponse>, StorageError>;
+
+    // 课表按 owner_id 隔离，删除课表时其课程一并删除
+    fn list_timetables(&self, owner_id: Option<&str>) -> Result<Vec<Timetable>, StorageError>;
+    fn get_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+    ) -> Result<Option<Timetable>, StorageError>;
+    // 取用户的默认课表，不存在时创建
+    fn default_timetable(&self, owner_id: Option<&str>) -> Result<Timetable, StorageError>;
+    fn insert_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_req: &CreateTimetableRequest,
+    ) -> Result<Timetable, StorageError>;
+    // is_default 设为 true 时原默认课表在同一事务中取消默认
+    fn update_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+        update_req: &UpdateTimetableRequest,
+    ) -> Result<Option<Timetable>, StorageError>;
+    fn delete_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+    ) -> Result<bool, StorageError>;
+    // 复制课表及其课程和课程单次调整，新课表不是默认课表
+    fn duplicate_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+        name: &str,
+    ) -> Result<Option<Timetable>, StorageError>;
+
+    // 分享链接按 owner_id 隔离，按令牌查询时只返回未过期的链接
+    fn insert_share_link(&self, share_link: &NewShareLink) -> Result<ShareLink, StorageError>;
+    fn list_share_links(&self, owner_id: Option<&str>) -> Result<Vec<ShareLink>, StorageError>;
+    fn get_share_link(&self, token: &str) -> Result<Option<ShareLink>, StorageError>;
+    fn delete_share_link(
+        &self,
+        owner_id: Option<&str>,
+        share_link_id: &str,
+    ) -> Result<bool, StorageError>;
+
+    // 用户名不区分大小写，登录令牌只保存摘要
+    fn insert_user(
+        &self,
+        register_req: &RegisterRequest,
+        password_hash: &str,
+    ) -> Result<User, StorageError>;
+    fn get_user_by_username(&self, username: &str) -> Result<Option<User>, StorageError>;
+    fn insert_session(&self, session: &NewUserSession) -> Result<(), StorageError>;
+    // 返回未过期会话对应的用户
+    fn get_session_user(&self, token_hash: &str) -> Result<Option<User>, StorageError>;
+    fn delete_session(&self, token_hash: &str) -> Result<bool, StorageError>;
+
+    // 学期、教师、教室和节次由所有用户共用，写入时只改动 scope（操作者自己）的课程，
+    // 仍被其他用户的课程引用时不能删除，按违反外键约束处理
+    fn list_semesters(&self) -> Result<Vec<Semester>, StorageError>;
+    fn get_semester(&self, semester_id: &str) -> Result<Option<Semester>, StorageError>;
+    fn get_active_semester(&self) -> Result<Option<Semester>, StorageError>;
+    fn insert_semester(
+        &self,
+        semester_req: &CreateSemesterRequest,
+    ) -> Result<Semester, StorageError>;
+    fn update_semester(
+        &self,
+        semester_id: &str,
+        update_req: &UpdateSemesterRequest,
+    ) -> Result<Option<Semester>, StorageError>;
+    fn delete_semester(&self, scope: &CourseScope, semester_id: &str)
+        -> Result<bool, StorageError>;
+
+    // 修改教师姓名时 scope 内关联课程的 teacher 在同一事务中更新，删除教师时课程解除关联
+    fn list_teachers(&self) -> Result<Vec<Teacher>, StorageError>;
+    fn get_teacher(&self, teacher_id: &str) -> Result<Option<Teacher>, StorageError>;
+    fn insert_teacher(&self, teacher_req: &CreateTeacherRequest) -> Result<Teacher, StorageError>;
+    fn update_teacher(
+        &self,
+        scope: &CourseScope,
+        teacher_id: &str,
+        update_req: &UpdateTeacherRequest,
+    ) -> Result<Option<Teacher>, StorageError>;
+    fn delete_teacher(&self, scope: &CourseScope, teacher_id: &str) -> Result<bool, StorageError>;
+
+    // 修改教室楼栋或编号时 scope 内关联课程的 location 在同一事务中更新，删除教室时课程解除关联
+    fn list_rooms(&self) -> Result<Vec<Room>, StorageError>;
+    fn get_room(&self, room_id: &str) -> Result<Option<Room>, StorageError>;
+    fn insert_room(&self, room_req: &CreateRoomRequest) -> Result<Room, StorageError>;
+    fn update_room(
+        &self,
+        scope: &CourseScope,
+        room_id: &str,
+        update_req: &UpdateRoomRequest,
+    ) -> Result<Option<Room>, StorageError>;
+    fn delete_room(&self, scope: &CourseScope, room_id: &str) -> Result<bool, StorageError>;
+
+    // 日历调整（停课、调休），每个日期最多一条
+    fn list_overrides(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<CalendarOverride>, StorageError>;
+    fn get_override(&self, override_id: &str) -> Result<Option<CalendarOverride>, StorageError>;
+    fn get_override_by_date(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Option<CalendarOverride>, StorageError>;
+    fn insert_override(
+        &self,
+        override_req: &CreateCalendarOverrideRequest,
+    ) -> Result<CalendarOverride, StorageError>;
+    fn update_override(
+        &self,
+        override_id: &str,
+        update_req: &UpdateCalendarOverrideRequest,
+    ) -> Result<Option<CalendarOverride>, StorageError>;
+    fn delete_override(&self, override_id: &str) -> Result<bool, StorageError>;
+
+    // 课程单次调整，每门课程每周最多一条，随课程一并删除
+    fn list_exceptions(
+        &self,
+        query: &CourseExceptionQuery,
+    ) -> Result<Vec<CourseException>, StorageError>;
+    fn get_exception(&self, exception_id: &str) -> Result<Option<CourseException>, StorageError>;
+    fn insert_exception(
+        &self,
+        exception_req: &CreateCourseExceptionRequest,
+    ) -> Result<CourseException, StorageError>;
+    fn update_exception(
+        &self,
+        exception_id: &str,
+        update_req: &UpdateCourseExceptionRequest,
+    ) -> Result<Option<CourseException>, StorageError>;
+    fn delete_exception(&self, exception_id: &str) -> Result<bool, StorageError>;
+
+    // 节次的增删改会在同一事务中重新计算 scope 内按节次定义的课程时间
+    fn list_periods(&self) -> Result<Vec<Period>, StorageError>;
+    fn insert_period(
+        &self,
+        scope: &CourseScope,
+        period_req: &CreatePeriodRequest,
+    ) -> Result<Period, StorageError>;
+    fn update_period(
+        &self,
+        scope: &CourseScope,
+        period_id: &str,
+        update_req: &UpdatePeriodRequest,
+    ) -> Result<Option<Period>, StorageError>;
+    fn delete_period(&self, scope: &CourseScope, period_id: &str) -> Result<bool, StorageError>;
+
+    // 课程提醒：只提醒各用户默认课表中的课程，提醒设置和提醒记录随课程一并删除
+    fn list_reminder_courses(&self) -> Result<Vec<CourseResponse>, StorageError>;
+    fn list_course_reminders(&self) -> Result<Vec<CourseReminder>, StorageError>;
+    fn get_course_reminder(&self, course_id: &str) -> Result<Option<CourseReminder>, StorageError>;
+    fn upsert_course_reminder(
+        &self,
+        reminder: &NewCourseReminder,
+    ) -> Result<CourseReminder, StorageError>;
+    fn delete_course_reminder(&self, course_id: &str) -> Result<bool, StorageError>;
+    // 某次上课是否已经提醒过
+    fn reminder_delivered(
+        &self,
+        course_id: &str,
+        starts_at: NaiveDateTime,
+    ) -> Result<bool, StorageError>;
+    // 记录某次上课已提醒，已经记录过时返回 false
+    fn record_reminder_delivery(
+        &self,
+        course_id: &str,
+        starts_at: NaiveDateTime,
+    ) -> Result<bool, StorageError>;
+}
+
+pub type Repository = web::Data<dyn ScheduleRepository>;
+
+// 自动创建的默认课表名称
+pub const DEFAULT_TIMETABLE_NAME: &str = "默认课表";
+
+// 存储访问错误：获取连接失败、查询失败或后台线程执行失败
+#[derive(Debug)]
+pub enum StorageError {
+    Pool(PoolError),
+    Query(DieselError),
+    Blocking(BlockingError),
 }
 
-pub fn get_all_courses(storage: &Storage) -> Vec<Course> {
-    let courses = storage.lock().unwrap();
-    let course_list: Vec<Course> = courses.values().cloned().collect();
-    debug!("📖 获取所有课程: {} 门", course_list.len());
-    course_list
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Pool(e) => write!(f, "database pool error: {}", e),
+            StorageError::Query(e) => write!(f, "database query error: {}", e),
+            StorageError::Blocking(e) => write!(f, "blocking task error: {}", e),
+        }
+    }
 }
 
-pub fn get_course_by_id(storage: &Storage, id: &Uuid) -> Option<Course> {
-    let courses = storage.lock().unwrap();
-    courses.get(id).cloned()
+impl std::error::Error for StorageError {}
+
+impl From<PoolError> for StorageError {
+    fn from(e: PoolError) -> Self {
+        StorageError::Pool(e)
+    }
 }
 
-pub fn insert_course(storage: &Storage, course: Course) -> Course {
-    let mut courses = storage.lock().unwrap();
-    courses.insert(course.id, course.clone());
-    debug!("💾 课程已存储: {} (ID: {})", course.name, course.id);
-    course
+impl From<DieselError> for StorageError {
+    fn from(e: DieselError) -> Self {
+        StorageError::Query(e)
+    }
 }
 
-pub fn update_course(storage: &Storage, id: &Uuid, updated_course: Course) -> Option<Course> {
-    let mut courses = storage.lock().unwrap();
-    if courses.contains_key(id) {
-        courses.insert(*id, updated_course.clone());
-        Some(updated_course)
-    } else {
-        None
+impl From<BlockingError> for StorageError {
+    fn from(e: BlockingError) -> Self {
+        StorageError::Blocking(e)
     }
 }
 
-pub fn delete_course(storage: &Storage, id: &Uuid) -> Option<Course> {
-    let mut courses = storage.lock().unwrap();
-    courses.remove(id)
+// 在阻塞线程池中执行存储操作，避免阻塞 actix 工作线程
+pub async fn run<T, F>(repo: &Repository, f: F) -> Result<T, StorageError>
+where
+    F: FnOnce(&dyn ScheduleRepository) -> Result<T, StorageError> + Send + 'static,
+    T: Send + 'static,
+{
+    let repo = repo.clone();
+    web::block(move || f(repo.get_ref())).await?
+}
+
+// 内存存储：不依赖数据库文件，进程退出后数据丢失，适用于测试和嵌入式使用
+#[derive(Default)]
+pub struct MemoryRepository {
+    state: Mutex<MemoryState>,
+    events: EventBus,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    courses: Vec<CourseResponse>, // 保持插入顺序，与 SQLite 的默认返回顺序一致
+    semesters: Vec<Semester>,
+    teachers: Vec<Teacher>,
+    rooms: Vec<Room>,
+    periods: Vec<Period>,
+    overrides: Vec<CalendarOverride>,
+    exceptions: Vec<CourseException>,
+    timetables: Vec<Timetable>,
+    share_links: Vec<ShareLink>,
+    users: Vec<User>,
+    sessions: Vec<NewUserSession>,
+    course_reminders: Vec<CourseReminder>,
+    reminder_deliveries: HashSet<(String, NaiveDateTime)>,
 }
 
-pub fn clear_all_courses(storage: &Storage) {
-    let mut courses = storage.lock().unwrap();
-    let count = courses.len();
-    courses.clear();
-    info!("🗑️ 清空所有课程: {} 门课程已删除", count);
+impl MemoryRepository {
+    pub fn new(events: EventBus) -> Self {
+        info!("💾 初始化内存存储");
+        Self {
+            state: Mutex::default(),
+            events,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        // 持锁线程 panic 后数据仍然可用，直接取回
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl MemoryState {
+    // 模拟 SQLite 的外键约束
+    fn check_semester(&self, semester_id: &Option<String>) -> Result<(), StorageError> {
+        match semester_id {
+            Some(id) if !self.semesters.iter().any(|s| &s.id == id) => Err(foreign_key_violation(
+                "courses.semester_id references a missing semester",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_teacher(&self, teacher_id: &Option<String>) -> Result<(), StorageError> {
+        match teacher_id {
+            Some(id) if !self.teachers.iter().any(|t| &t.id == id) => Err(foreign_key_violation(
+                "courses.teacher_id references a missing teacher",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_timetable(&self, timetable_id: &Option<String>) -> Result<(), StorageError> {
+        match timetable_id {
+            Some(id) if !self.timetables.iter().any(|t| &t.id == id) => Err(foreign_key_violation(
+                "courses.timetable_id references a missing timetable",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn new_timetable(&self, owner_id: Option<&str>, name: &str, is_default: bool) -> Timetable {
+        let now = Utc::now().naive_utc();
+        Timetable {
+            id: Uuid::new_v4().to_string(),
+            owner_id: owner_id.map(str::to_string),
+            name: name.to_string(),
+            is_default,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn check_room(&self, room_id: &Option<String>) -> Result<(), StorageError> {
+        match room_id {
+            Some(id) if !self.rooms.iter().any(|r| &r.id == id) => Err(foreign_key_violation(
+                "courses.room_id references a missing room",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // 与 idx_rooms_identity 唯一索引一致
+    fn check_room_unique(
+        &self,
+        room_id: &str,
+        campus: &Option<String>,
+        building: &str,
+        room_number: &str,
+    ) -> Result<(), StorageError> {
+        let duplicate = self.rooms.iter().any(|r| {
+            r.id != room_id
+                && &r.campus == campus
+                && r.building == building
+                && r.room_number == room_number
+        });
+        if duplicate {
+            return Err(StorageError::Query(DieselError::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                Box::new("room already exists in this building and campus".to_string()),
+            )));
+        }
+        Ok(())
+    }
+
+    fn new_course(
+        &self,
+        scope: &CourseScope,
+        course_req: &CreateCourseRequest,
+    ) -> Result<CourseResponse, StorageError> {
+        self.check_semester(&course_req.semester_id)?;
+        self.check_timetable(&scope.timetable_id)?;
+        self.check_teacher(&course_req.teacher_id)?;
+        self.check_room(&course_req.room_id)?;
+        Ok(CourseResponse {
+            id: Uuid::new_v4().to_string(),
+            name: course_req.name.clone(),
+            teacher: course_req.teacher.clone(),
+            location: course_req.location.clone(),
+            weekday: course_req.weekday,
+            start_time: course_req.start_time.clone(),
+            end_time: course_req.end_time.clone(),
+            weeks: course_req.weeks.clone(),
+            week_rule: crate::week_rule::format_week_rule(&course_req.weeks),
+            color: course_req.color.clone(),
+            semester_id: course_req.semester_id.clone(),
+            campus: course_req.campus.clone(),
+            start_period: course_req.start_period,
+            end_period: course_req.end_period,
+            teacher_id: course_req.teacher_id.clone(),
+            room_id: course_req.room_id.clone(),
+            owner_id: scope.owner_id.clone(),
+            timetable_id: scope.timetable_id.clone(),
+        })
+    }
+
+    // 与 idx_periods_variant 唯一索引一致
+    fn check_period_unique(
+        &self,
+        period_id: &str,
+        semester_id: &Option<String>,
+        campus: &Option<String>,
+        period_index: i32,
+    ) -> Result<(), StorageError> {
+        let duplicate = self.periods.iter().any(|p| {
+            p.id != period_id
+                && &p.semester_id == semester_id
+                && &p.campus == campus
+                && p.period_index == period_index
+        });
+        if duplicate {
+            return Err(StorageError::Query(DieselError::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                Box::new("period already defined for this semester and campus".to_string()),
+            )));
+        }
+        Ok(())
+    }
+
+    // 与 calendar_overrides.date 唯一约束一致
+    fn check_override_date(&self, override_id: &str, date: NaiveDate) -> Result<(), StorageError> {
+        if self
+            .overrides
+            .iter()
+            .any(|o| o.id != override_id && o.date == date)
+        {
+            return Err(StorageError::Query(DieselError::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                Box::new("calendar override already exists for this date".to_string()),
+            )));
+        }
+        Ok(())
+    }
+
+    // 与 course_exceptions 的外键和 (course_id, week) 唯一约束一致
+    fn check_exception(
+        &self,
+        exception_id: &str,
+        course_id: &str,
+        week: i32,
+    ) -> Result<(), StorageError> {
+        if !self.courses.iter().any(|c| c.id == course_id) {
+            return Err(foreign_key_violation(
+                "course_exceptions.course_id references a missing course",
+            ));
+        }
+        if self
+            .exceptions
+            .iter()
+            .any(|e| e.id != exception_id && e.course_id == course_id && e.week == week)
+        {
+            return Err(StorageError::Query(DieselError::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                Box::new("course already has an exception for this week".to_string()),
+            )));
+        }
+        Ok(())
+    }
+
+    // 与 ON DELETE CASCADE 一致：删除已不存在课程的单次调整、提醒设置和提醒记录
+    fn drop_orphans(&mut self) {
+        let courses = &self.courses;
+        self.exceptions
+            .retain(|e| courses.iter().any(|c| c.id == e.course_id));
+        self.course_reminders
+            .retain(|r| courses.iter().any(|c| c.id == r.course_id));
+        self.reminder_deliveries
+            .retain(|(course_id, _)| courses.iter().any(|c| &c.id == course_id));
+    }
+
+    fn resync_period_times(&mut self, scope: &CourseScope) {
+        let periods = &self.periods;
+        for course in self.courses.iter_mut().filter(|c| scope.matches(c)) {
+            crate::periods::resync_course_times(periods, course);
+        }
+    }
+
+    // 节次变化后重新计算 scope 内的课程时间，时间变化的课程逐门发布更新事件
+    fn resync_period_times_and_publish(&mut self, scope: &CourseScope, events: &EventBus) {
+        let before = self.courses.clone();
+        self.resync_period_times(scope);
+        events.publish_changed(&before, &self.courses);
+    }
+
+    // 与 SQLite 后端一致：共用数据被 scope 之外的课程引用时拒绝删除
+    fn check_unused_outside(
+        &self,
+        scope: &CourseScope,
+        references: impl Fn(&CourseResponse) -> bool,
+        message: &str,
+    ) -> Result<(), StorageError> {
+        if self
+            .courses
+            .iter()
+            .any(|c| references(c) && !scope.matches(c))
+        {
+            return Err(foreign_key_violation(message));
+        }
+        Ok(())
+    }
+
+    // 单次调整改变了课程某一周的安排，发布课程的更新事件，订阅者据此重新拉取日程
+    fn publish_exception_course(&self, events: &EventBus, course_id: &str) {
+        if let Some(course) = self.courses.iter().find(|c| c.id == course_id) {
+            events.publish(ScheduleEvent::course(EventKind::CourseUpdated, course));
+        }
+    }
+}
+
+fn foreign_key_violation(message: &str) -> StorageError {
+    StorageError::Query(DieselError::DatabaseError(
+        DatabaseErrorKind::ForeignKeyViolation,
+        Box::new(message.to_string()),
+    ))
+}
+
+impl ScheduleRepository for MemoryRepository {
+    fn list_courses(&self, scope: &CourseScope) -> Result<Vec<CourseResponse>, StorageError> {
+        let courses: Vec<CourseResponse> = self
+            .lock()
+            .courses
+            .iter()
+            .filter(|c| scope.matches(c))
+            .cloned()
+            .collect();
+        debug!("📖 获取所有课程: {} 门", courses.len());
+        Ok(courses)
+    }
+
+    fn list_all_courses(&self) -> Result<Vec<CourseResponse>, StorageError> {
+        Ok(self.lock().courses.clone())
+    }
+
+    fn find_courses(
+        &self,
+        scope: &CourseScope,
+        query: &CourseQuery,
+    ) -> Result<Vec<CourseResponse>, StorageError> {
+        let mut courses: Vec<CourseResponse> = self
+            .lock()
+            .courses
+            .iter()
+            .filter(|c| scope.matches(c) && query.matches(c))
+            .cloned()
+            .collect();
+        query.sort(&mut courses);
+        Ok(courses)
+    }
+
+    fn get_course(
+        &self,
+        scope: &CourseScope,
+        course_id: &str,
+    ) -> Result<Option<CourseResponse>, StorageError> {
+        Ok(self
+            .lock()
+            .courses
+            .iter()
+            .find(|c| c.id == course_id && scope.matches(c))
+            .cloned())
+    }
+
+    fn insert_course(
+        &self,
+        scope: &CourseScope,
+        course_req: &CreateCourseRequest,
+    ) -> Result<CourseResponse, StorageError> {
+        let mut state = self.lock();
+        let course = state.new_course(scope, course_req)?;
+        state.courses.push(course.clone());
+        debug!("💾 课程已存储: {} (ID: {})", course.name, course.id);
+        self.events
+            .publish(ScheduleEvent::course(EventKind::CourseCreated, &course));
+        Ok(course)
+    }
+
+    fn update_course(
+        &self,
+        scope: &CourseScope,
+        course_id: &str,
+        update_req: &UpdateCourseRequest,
+    ) -> Result<Option<CourseResponse>, StorageError> {
+        let mut state = self.lock();
+        if update_req.semester_id.is_some() {
+            state.check_semester(&update_req.semester_id)?;
+        }
+        state.check_teacher(&update_req.teacher_id)?;
+        state.check_room(&update_req.room_id)?;
+
+        let Some(course) = state
+            .courses
+            .iter_mut()
+            .find(|c| c.id == course_id && scope.matches(c))
+        else {
+            return Ok(None);
+        };
+
+        if let Some(name) = &update_req.name {
+            course.name = name.clone();
+        }
+        if let Some(teacher) = &update_req.teacher {
+            course.teacher = Some(teacher.clone());
+        }
+        // 与 SQLite 后端一致：修改教师或地点时 teacher_id/room_id 一并替换
+        if update_req.teacher.is_some() || update_req.teacher_id.is_some() {
+            course.teacher_id = update_req.teacher_id.clone();
+        }
+        if update_req.location.is_some() || update_req.room_id.is_some() {
+            course.room_id = update_req.room_id.clone();
+        }
+        if let Some(location) = &update_req.location {
+            course.location = Some(location.clone());
+        }
+        if let Some(weekday) = update_req.weekday {
+            course.weekday = weekday;
+        }
+        if let Some(start_time) = &update_req.start_time {
+            course.start_time = start_time.clone();
+        }
+        if let Some(end_time) = &update_req.end_time {
+            course.end_time = end_time.clone();
+        }
+        if let Some(weeks) = &update_req.weeks {
+            course.weeks = weeks.clone();
+            course.week_rule = crate::week_rule::format_week_rule(weeks);
+        }
+        if let Some(color) = &update_req.color {
+            course.color = Some(color.clone());
+        }
+        if let Some(semester_id) = &update_req.semester_id {
+            course.semester_id = Some(semester_id.clone());
+        }
+        if let Some(campus) = &update_req.campus {
+            course.campus = Some(campus.clone());
+        }
+        // 与 SQLite 后端一致：只更新时间而不指定节次时，课程改为按时间定义
+        if update_req.start_period.is_none()
+            && (update_req.start_time.is_some() || update_req.end_time.is_some())
+        {
+            course.start_period = None;
+            course.end_period = None;
+        }
+        if let Some(start_period) = update_req.start_period {
+            course.start_period = Some(start_period);
+        }
+        if let Some(end_period) = update_req.end_period {
+            course.end_period = Some(end_period);
+        }
+
+        debug!("🔄 课程已更新: {} (ID: {})", course.name, course.id);
+        let course = course.clone();
+        self.events
+            .publish(ScheduleEvent::course(EventKind::CourseUpdated, &course));
+        Ok(Some(course))
+    }
+
+    fn delete_course(&self, scope: &CourseScope, course_id: &str) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let Some(index) = state
+            .courses
+            .iter()
+            .position(|c| c.id == course_id && scope.matches(c))
+        else {
+            return Ok(false);
+        };
+        let course = state.courses.remove(index);
+        state.drop_orphans();
+        self.events
+            .publish(ScheduleEvent::course(EventKind::CourseDeleted, &course));
+        Ok(true)
+    }
+
+    fn delete_all_courses(&self, scope: &CourseScope) -> Result<usize, StorageError> {
+        let mut state = self.lock();
+        let count = state.courses.len();
+        state.courses.retain(|c| !scope.matches(c));
+        let count = count - state.courses.len();
+        state.drop_orphans();
+        info!("🗑️ 清空所有课程: {} 门课程已删除", count);
+        if count > 0 {
+            self.events.publish(ScheduleEvent::replaced(
+                scope.owner_id.clone(),
+                scope.timetable_id.clone(),
+            ));
+        }
+        Ok(count)
+    }
+
+    fn insert_courses(
+        &self,
+        scope: &CourseScope,
+        course_requests: &[CreateCourseRequest],
+        replace: bool,
+    ) -> Result<Vec<CourseResponse>, StorageError> {
+        let mut state = self.lock();
+        // 先全部构造成功再写入，保证与事务相同的原子性
+        let created = course_requests
+            .iter()
+            .map(|course_req| state.new_course(scope, course_req))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if replace {
+            state.courses.retain(|c| !scope.matches(c));
+            state.drop_orphans();
+        }
+        state.courses.extend(created.iter().cloned());
+        debug!("💾 批量创建了 {} 门课程", created.len());
+        self.events.publish_inserted(scope, &created, replace);
+        Ok(created)
+    }
+
+    fn list_timetables(&self, owner_id: Option<&str>) -> Result<Vec<Timetable>, StorageError> {
+        let mut timetables: Vec<Timetable> = self
+            .lock()
+            .timetables
+            .iter()
+            .filter(|t| t.owner_id.as_deref() == owner_id)
+            .cloned()
+            .collect();
+        // 与 SQLite 后端一致：默认课表在前，其余按创建顺序
+        timetables.sort_by_key(|t| !t.is_default);
+        Ok(timetables)
+    }
+
+    fn get_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+    ) -> Result<Option<Timetable>, StorageError> {
+        Ok(self
+            .lock()
+            .timetables
+            .iter()
+            .find(|t| t.id == timetable_id && t.owner_id.as_deref() == owner_id)
+            .cloned())
+    }
+
+    // 查找和创建在同一把锁内完成，并发的首次请求也只会创建一张默认课表
+    fn default_timetable(&self, owner_id: Option<&str>) -> Result<Timetable, StorageError> {
+        let mut state = self.lock();
+        if let Some(timetable) = state
+            .timetables
+            .iter()
+            .find(|t| t.is_default && t.owner_id.as_deref() == owner_id)
+        {
+            return Ok(timetable.clone());
+        }
+
+        let timetable = state.new_timetable(owner_id, DEFAULT_TIMETABLE_NAME, true);
+        state.timetables.push(timetable.clone());
+        info!("📒 已创建默认课表 (ID: {})", timetable.id);
+        Ok(timetable)
+    }
+
+    fn insert_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_req: &CreateTimetableRequest,
+    ) -> Result<Timetable, StorageError> {
+        let mut state = self.lock();
+        let timetable = state.new_timetable(owner_id, &timetable_req.name, false);
+        state.timetables.push(timetable.clone());
+        debug!("💾 课表已存储: {} (ID: {})", timetable.name, timetable.id);
+        Ok(timetable)
+    }
+
+    fn update_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+        update_req: &UpdateTimetableRequest,
+    ) -> Result<Option<Timetable>, StorageError> {
+        let mut state = self.lock();
+        if !state
+            .timetables
+            .iter()
+            .any(|t| t.id == timetable_id && t.owner_id.as_deref() == owner_id)
+        {
+            return Ok(None);
+        }
+
+        let now = Utc::now().naive_utc();
+        if update_req.is_default == Some(true) {
+            for other in state
+                .timetables
+                .iter_mut()
+                .filter(|t| t.is_default && t.owner_id.as_deref() == owner_id)
+            {
+                other.is_default = false;
+                other.updated_at = now;
+            }
+        }
+
+        let Some(timetable) = state.timetables.iter_mut().find(|t| t.id == timetable_id) else {
+            return Ok(None);
+        };
+        if let Some(name) = &update_req.name {
+            timetable.name = name.clone();
+        }
+        if let Some(is_default) = update_req.is_default {
+            timetable.is_default = is_default;
+        }
+        timetable.updated_at = now;
+        debug!("🔄 课表已更新: {} (ID: {})", timetable.name, timetable.id);
+        Ok(Some(timetable.clone()))
+    }
+
+    fn delete_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+    ) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let count = state.timetables.len();
+        state
+            .timetables
+            .retain(|t| t.id != timetable_id || t.owner_id.as_deref() != owner_id);
+        if state.timetables.len() == count {
+            return Ok(false);
+        }
+
+        // 与 ON DELETE CASCADE 一致：课表的课程及其单次调整、分享链接一并删除
+        state
+            .courses
+            .retain(|c| c.timetable_id.as_deref() != Some(timetable_id));
+        state.drop_orphans();
+        state.share_links.retain(|l| l.timetable_id != timetable_id);
+        self.events.publish(ScheduleEvent::replaced(
+            owner_id.map(str::to_string),
+            Some(timetable_id.to_string()),
+        ));
+        Ok(true)
+    }
+
+    fn duplicate_timetable(
+        &self,
+        owner_id: Option<&str>,
+        timetable_id: &str,
+        name: &str,
+    ) -> Result<Option<Timetable>, StorageError> {
+        let mut state = self.lock();
+        if !state
+            .timetables
+            .iter()
+            .any(|t| t.id == timetable_id && t.owner_id.as_deref() == owner_id)
+        {
+            return Ok(None);
+        }
+
+        let timetable = state.new_timetable(owner_id, name, false);
+        let mut courses = Vec::new();
+        let mut exceptions = Vec::new();
+        for course in state
+            .courses
+            .iter()
+            .filter(|c| c.timetable_id.as_deref() == Some(timetable_id))
+        {
+            let mut copy = course.clone();
+            copy.id = Uuid::new_v4().to_string();
+            copy.timetable_id = Some(timetable.id.clone());
+            for exception in state.exceptions.iter().filter(|e| e.course_id == course.id) {
+                let mut exception = exception.clone();
+                exception.id = Uuid::new_v4().to_string();
+                exception.course_id = copy.id.clone();
+                exceptions.push(exception);
+            }
+            courses.push(copy);
+        }
+
+        debug!(
+            "📋 课表已复制: {} -> {} ({} 门课程)",
+            timetable_id,
+            timetable.id,
+            courses.len()
+        );
+        state.timetables.push(timetable.clone());
+        state.courses.extend(courses);
+        state.exceptions.extend(exceptions);
+        self.events.publish(ScheduleEvent::replaced(
+            timetable.owner_id.clone(),
+            Some(timetable.id.clone()),
+        ));
+        Ok(Some(timetable))
+    }
+
+    fn insert_share_link(&self, share_link: &NewShareLink) -> Result<ShareLink, StorageError> {
+        let mut state = self.lock();
+        state.check_timetable(&Some(share_link.timetable_id.clone()))?;
+
+        let share_link = ShareLink {
+            id: share_link.id.clone(),
+            token: share_link.token.clone(),
+            owner_id: share_link.owner_id.clone(),
+            timetable_id: share_link.timetable_id.clone(),
+            expires_at: share_link.expires_at,
+            created_at: Utc::now().naive_utc(),
+        };
+        state.share_links.push(share_link.clone());
+        debug!(
+            "💾 分享链接已存储: 课表 {} (ID: {})",
+            share_link.timetable_id, share_link.id
+        );
+        Ok(share_link)
+    }
+
+    fn list_share_links(&self, owner_id: Option<&str>) -> Result<Vec<ShareLink>, StorageError> {
+        Ok(self
+            .lock()
+            .share_links
+            .iter()
+            .filter(|l| l.owner_id.as_deref() == owner_id)
+            .cloned()
+            .collect())
+    }
+
+    fn get_share_link(&self, token: &str) -> Result<Option<ShareLink>, StorageError> {
+        let now = Utc::now().naive_utc();
+        Ok(self
+            .lock()
+            .share_links
+            .iter()
+            .find(|l| l.token == token && l.expires_at.is_none_or(|expires_at| expires_at > now))
+            .cloned())
+    }
+
+    fn delete_share_link(
+        &self,
+        owner_id: Option<&str>,
+        share_link_id: &str,
+    ) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let count = state.share_links.len();
+        state
+            .share_links
+            .retain(|l| l.id != share_link_id || l.owner_id.as_deref() != owner_id);
+        Ok(state.share_links.len() < count)
+    }
+
+    fn insert_user(
+        &self,
+        register_req: &RegisterRequest,
+        password_hash: &str,
+    ) -> Result<User, StorageError> {
+        let mut state = self.lock();
+        // 与 users.username 的 UNIQUE COLLATE NOCASE 约束一致
+        if state
+            .users
+            .iter()
+            .any(|u| u.username.eq_ignore_ascii_case(&register_req.username))
+        {
+            return Err(StorageError::Query(DieselError::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                Box::new("username already exists".to_string()),
+            )));
+        }
+
+        let now = Utc::now().naive_utc();
+        let user = User {
+            id: Uuid::new_v4().to_string(),
+            username: register_req.username.clone(),
+            password_hash: password_hash.to_string(),
+            display_name: register_req.display_name.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        state.users.push(user.clone());
+        debug!("💾 用户已注册: {} (ID: {})", user.username, user.id);
+        Ok(user)
+    }
+
+    fn get_user_by_username(&self, username: &str) -> Result<Option<User>, StorageError> {
+        Ok(self
+            .lock()
+            .users
+            .iter()
+            .find(|u| u.username.eq_ignore_ascii_case(username))
+            .cloned())
+    }
+
+    fn insert_session(&self, session: &NewUserSession) -> Result<(), StorageError> {
+        let mut state = self.lock();
+        if !state.users.iter().any(|u| u.id == session.user_id) {
+            return Err(foreign_key_violation(
+                "user_sessions.user_id references a missing user",
+            ));
+        }
+        state.sessions.push(session.clone());
+        Ok(())
+    }
+
+    fn get_session_user(&self, token_hash: &str) -> Result<Option<User>, StorageError> {
+        let now = Utc::now().naive_utc();
+        let state = self.lock();
+        let Some(session) = state
+            .sessions
+            .iter()
+            .find(|s| s.token_hash == token_hash && s.expires_at > now)
+        else {
+            return Ok(None);
+        };
+        Ok(state
+            .users
+            .iter()
+            .find(|u| u.id == session.user_id)
+            .cloned())
+    }
+
+    fn delete_session(&self, token_hash: &str) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let count = state.sessions.len();
+        state.sessions.retain(|s| s.token_hash != token_hash);
+        Ok(state.sessions.len() < count)
+    }
+
+    fn list_semesters(&self) -> Result<Vec<Semester>, StorageError> {
+        let mut semesters = self.lock().semesters.clone();
+        semesters.sort_by_key(|s| std::cmp::Reverse(s.start_date));
+        Ok(semesters)
+    }
+
+    fn get_semester(&self, semester_id: &str) -> Result<Option<Semester>, StorageError> {
+        Ok(self
+            .lock()
+            .semesters
+            .iter()
+            .find(|s| s.id == semester_id)
+            .cloned())
+    }
+
+    fn get_active_semester(&self) -> Result<Option<Semester>, StorageError> {
+        Ok(self.lock().semesters.iter().find(|s| s.is_active).cloned())
+    }
+
+    fn insert_semester(
+        &self,
+        semester_req: &CreateSemesterRequest,
+    ) -> Result<Semester, StorageError> {
+        let mut state = self.lock();
+        if semester_req.is_active {
+            state.semesters.iter_mut().for_each(|s| s.is_active = false);
+        }
+
+        let now = Utc::now().naive_utc();
+        let semester = Semester {
+            id: Uuid::new_v4().to_string(),
+            name: semester_req.name.clone(),
+            start_date: semester_req.start_date,
+            total_weeks: semester_req.total_weeks,
+            is_active: semester_req.is_active,
+            created_at: now,
+            updated_at: now,
+        };
+        state.semesters.push(semester.clone());
+        Ok(semester)
+    }
+
+    fn update_semester(
+        &self,
+        semester_id: &str,
+        update_req: &UpdateSemesterRequest,
+    ) -> Result<Option<Semester>, StorageError> {
+        let mut state = self.lock();
+        if !state.semesters.iter().any(|s| s.id == semester_id) {
+            return Ok(None);
+        }
+
+        for semester in state.semesters.iter_mut() {
+            if semester.id != semester_id {
+                if update_req.is_active == Some(true) {
+                    semester.is_active = false;
+                }
+                continue;
+            }
+
+            if let Some(name) = &update_req.name {
+                semester.name = name.clone();
+            }
+            if let Some(start_date) = update_req.start_date {
+                semester.start_date = start_date;
+            }
+            if let Some(total_weeks) = update_req.total_weeks {
+                semester.total_weeks = total_weeks;
+            }
+            if let Some(is_active) = update_req.is_active {
+                semester.is_active = is_active;
+            }
+            semester.updated_at = Utc::now().naive_utc();
+        }
+
+        Ok(state
+            .semesters
+            .iter()
+            .find(|s| s.id == semester_id)
+            .cloned())
+    }
+
+    fn delete_semester(
+        &self,
+        scope: &CourseScope,
+        semester_id: &str,
+    ) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        state.check_unused_outside(
+            scope,
+            |c| c.semester_id.as_deref() == Some(semester_id),
+            "Semester is still used by other users' courses",
+        )?;
+        let count = state.semesters.len();
+        state.semesters.retain(|s| s.id != semester_id);
+        if state.semesters.len() == count {
+            return Ok(false);
+        }
+
+        // 与 ON DELETE SET NULL 一致：保留课程，解除关联
+        let before = state.courses.clone();
+        for course in state.courses.iter_mut() {
+            if course.semester_id.as_deref() == Some(semester_id) {
+                course.semester_id = None;
+            }
+        }
+        // 与 ON DELETE CASCADE 一致：删除该学期的节次
+        state
+            .periods
+            .retain(|p| p.semester_id.as_deref() != Some(semester_id));
+        state.resync_period_times(scope);
+        self.events.publish_changed(&before, &state.courses);
+        Ok(true)
+    }
+
+    fn list_teachers(&self) -> Result<Vec<Teacher>, StorageError> {
+        let mut teachers = self.lock().teachers.clone();
+        teachers.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(teachers)
+    }
+
+    fn get_teacher(&self, teacher_id: &str) -> Result<Option<Teacher>, StorageError> {
+        Ok(self
+            .lock()
+            .teachers
+            .iter()
+            .find(|t| t.id == teacher_id)
+            .cloned())
+    }
+
+    fn insert_teacher(&self, teacher_req: &CreateTeacherRequest) -> Result<Teacher, StorageError> {
+        let now = Utc::now().naive_utc();
+        let teacher = Teacher {
+            id: Uuid::new_v4().to_string(),
+            name: teacher_req.name.clone(),
+            email: teacher_req.email.clone(),
+            phone: teacher_req.phone.clone(),
+            office: teacher_req.office.clone(),
+            office_hours: teacher_req.office_hours.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        self.lock().teachers.push(teacher.clone());
+        Ok(teacher)
+    }
+
+    fn update_teacher(
+        &self,
+        scope: &CourseScope,
+        teacher_id: &str,
+        update_req: &UpdateTeacherRequest,
+    ) -> Result<Option<Teacher>, StorageError> {
+        let mut state = self.lock();
+        let Some(teacher) = state.teachers.iter_mut().find(|t| t.id == teacher_id) else {
+            return Ok(None);
+        };
+
+        if let Some(name) = &update_req.name {
+            teacher.name = name.clone();
+        }
+        if let Some(email) = &update_req.email {
+            teacher.email = Some(email.clone());
+        }
+        if let Some(phone) = &update_req.phone {
+            teacher.phone = Some(phone.clone());
+        }
+        if let Some(office) = &update_req.office {
+            teacher.office = Some(office.clone());
+        }
+        if let Some(office_hours) = &update_req.office_hours {
+            teacher.office_hours = Some(office_hours.clone());
+        }
+        teacher.updated_at = Utc::now().naive_utc();
+        let teacher = teacher.clone();
+
+        if let Some(name) = &update_req.name {
+            let before = state.courses.clone();
+            for course in state.courses.iter_mut().filter(|c| scope.matches(c)) {
+                if course.teacher_id.as_deref() == Some(teacher_id) {
+                    course.teacher = Some(name.clone());
+                }
+            }
+            self.events.publish_changed(&before, &state.courses);
+        }
+        Ok(Some(teacher))
+    }
+
+    fn delete_teacher(&self, scope: &CourseScope, teacher_id: &str) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        state.check_unused_outside(
+            scope,
+            |c| c.teacher_id.as_deref() == Some(teacher_id),
+            "Teacher is still used by other users' courses",
+        )?;
+        let count = state.teachers.len();
+        state.teachers.retain(|t| t.id != teacher_id);
+        if state.teachers.len() == count {
+            return Ok(false);
+        }
+
+        // 与 ON DELETE SET NULL 一致：保留课程和教师姓名，解除关联
+        let before = state.courses.clone();
+        for course in state.courses.iter_mut() {
+            if course.teacher_id.as_deref() == Some(teacher_id) {
+                course.teacher_id = None;
+            }
+        }
+        self.events.publish_changed(&before, &state.courses);
+        Ok(true)
+    }
+
+    fn list_rooms(&self) -> Result<Vec<Room>, StorageError> {
+        let mut rooms = self.lock().rooms.clone();
+        rooms.sort_by(|a, b| (&a.building, &a.room_number).cmp(&(&b.building, &b.room_number)));
+        Ok(rooms)
+    }
+
+    fn get_room(&self, room_id: &str) -> Result<Option<Room>, StorageError> {
+        Ok(self.lock().rooms.iter().find(|r| r.id == room_id).cloned())
+    }
+
+    fn insert_room(&self, room_req: &CreateRoomRequest) -> Result<Room, StorageError> {
+        let mut state = self.lock();
+        state.check_room_unique(
+            "",
+            &room_req.campus,
+            &room_req.building,
+            &room_req.room_number,
+        )?;
+
+        let now = Utc::now().naive_utc();
+        let room = Room {
+            id: Uuid::new_v4().to_string(),
+            building: room_req.building.clone(),
+            room_number: room_req.room_number.clone(),
+            capacity: room_req.capacity,
+            campus: room_req.campus.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        state.rooms.push(room.clone());
+        Ok(room)
+    }
+
+    fn update_room(
+        &self,
+        scope: &CourseScope,
+        room_id: &str,
+        update_req: &UpdateRoomRequest,
+    ) -> Result<Option<Room>, StorageError> {
+        let mut state = self.lock();
+        let Some(existing) = state.rooms.iter().find(|r| r.id == room_id) else {
+            return Ok(None);
+        };
+        let campus = update_req.campus.clone().or(existing.campus.clone());
+        let building = update_req
+            .building
+            .clone()
+            .unwrap_or(existing.building.clone());
+        let room_number = update_req
+            .room_number
+            .clone()
+            .unwrap_or(existing.room_number.clone());
+        state.check_room_unique(room_id, &campus, &building, &room_number)?;
+
+        let Some(room) = state.rooms.iter_mut().find(|r| r.id == room_id) else {
+            return Ok(None);
+        };
+        room.building = building;
+        room.room_number = room_number;
+        room.campus = campus;
+        if let Some(capacity) = update_req.capacity {
+            room.capacity = Some(capacity);
+        }
+        room.updated_at = Utc::now().naive_utc();
+        let room = room.clone();
+
+        if update_req.building.is_some() || update_req.room_number.is_some() {
+            let before = state.courses.clone();
+            for course in state.courses.iter_mut().filter(|c| scope.matches(c)) {
+                if course.room_id.as_deref() == Some(room_id) {
+                    course.location = Some(room.label());
+                }
+            }
+            self.events.publish_changed(&before, &state.courses);
+        }
+        Ok(Some(room))
+    }
+
+    fn delete_room(&self, scope: &CourseScope, room_id: &str) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        state.check_unused_outside(
+            scope,
+            |c| c.room_id.as_deref() == Some(room_id),
+            "Room is still used by other users' courses",
+        )?;
+        let count = state.rooms.len();
+        state.rooms.retain(|r| r.id != room_id);
+        if state.rooms.len() == count {
+            return Ok(false);
+        }
+
+        // 与 ON DELETE SET NULL 一致：保留课程和教室名称，解除关联
+        let before = state.courses.clone();
+        for course in state.courses.iter_mut() {
+            if course.room_id.as_deref() == Some(room_id) {
+                course.room_id = None;
+            }
+        }
+        self.events.publish_changed(&before, &state.courses);
+        Ok(true)
+    }
+
+    fn list_overrides(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<CalendarOverride>, StorageError> {
+        let mut overrides: Vec<CalendarOverride> = self
+            .lock()
+            .overrides
+            .iter()
+            .filter(|o| from.is_none_or(|from| o.date >= from))
+            .filter(|o| to.is_none_or(|to| o.date <= to))
+            .cloned()
+            .collect();
+        overrides.sort_by_key(|o| o.date);
+        Ok(overrides)
+    }
+
+    fn get_override(&self, override_id: &str) -> Result<Option<CalendarOverride>, StorageError> {
+        Ok(self
+            .lock()
+            .overrides
+            .iter()
+            .find(|o| o.id == override_id)
+            .cloned())
+    }
+
+    fn get_override_by_date(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Option<CalendarOverride>, StorageError> {
+        Ok(self
+            .lock()
+            .overrides
+            .iter()
+            .find(|o| o.date == date)
+            .cloned())
+    }
+
+    fn insert_override(
+        &self,
+        override_req: &CreateCalendarOverrideRequest,
+    ) -> Result<CalendarOverride, StorageError> {
+        let mut state = self.lock();
+        state.check_override_date("", override_req.date)?;
+
+        let now = Utc::now().naive_utc();
+        let calendar_override = CalendarOverride {
+            id: Uuid::new_v4().to_string(),
+            date: override_req.date,
+            kind: override_req.kind.as_str().to_string(),
+            weekday: override_req.weekday,
+            week: override_req.week,
+            note: override_req.note.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        state.overrides.push(calendar_override.clone());
+        Ok(calendar_override)
+    }
+
+    fn update_override(
+        &self,
+        override_id: &str,
+        update_req: &UpdateCalendarOverrideRequest,
+    ) -> Result<Option<CalendarOverride>, StorageError> {
+        let mut state = self.lock();
+        if let Some(date) = update_req.date {
+            state.check_override_date(override_id, date)?;
+        }
+
+        let Some(calendar_override) = state.overrides.iter_mut().find(|o| o.id == override_id)
+        else {
+            return Ok(None);
+        };
+        if let Some(date) = update_req.date {
+            calendar_override.date = date;
+        }
+        // 与 SQLite 后端一致：修改 kind 时 weekday/week 整体替换
+        if let Some(kind) = update_req.kind {
+            calendar_override.kind = kind.as_str().to_string();
+            calendar_override.weekday = update_req.weekday;
+            calendar_override.week = update_req.week;
+        } else {
+            if let Some(weekday) = update_req.weekday {
+                calendar_override.weekday = Some(weekday);
+            }
+            if let Some(week) = update_req.week {
+                calendar_override.week = Some(week);
+            }
+        }
+        if let Some(note) = &update_req.note {
+            calendar_override.note = Some(note.clone());
+        }
+        calendar_override.updated_at = Utc::now().naive_utc();
+        Ok(Some(calendar_override.clone()))
+    }
+
+    fn delete_override(&self, override_id: &str) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let count = state.overrides.len();
+        state.overrides.retain(|o| o.id != override_id);
+        Ok(state.overrides.len() < count)
+    }
+
+    fn list_exceptions(
+        &self,
+        query: &CourseExceptionQuery,
+    ) -> Result<Vec<CourseException>, StorageError> {
+        let mut exceptions: Vec<CourseException> = self
+            .lock()
+            .exceptions
+            .iter()
+            .filter(|e| query.course_id.as_ref().is_none_or(|id| &e.course_id == id))
+            .filter(|e| query.week.is_none_or(|week| e.week == week))
+            .cloned()
+            .collect();
+        exceptions.sort_by_key(|e| e.week);
+        Ok(exceptions)
+    }
+
+    fn get_exception(&self, exception_id: &str) -> Result<Option<CourseException>, StorageError> {
+        Ok(self
+            .lock()
+            .exceptions
+            .iter()
+            .find(|e| e.id == exception_id)
+            .cloned())
+    }
+
+    fn insert_exception(
+        &self,
+        exception_req: &CreateCourseExceptionRequest,
+    ) -> Result<CourseException, StorageError> {
+        let mut state = self.lock();
+        state.check_exception("", &exception_req.course_id, exception_req.week)?;
+
+        let now = Utc::now().naive_utc();
+        let exception = CourseException {
+            id: Uuid::new_v4().to_string(),
+            course_id: exception_req.course_id.clone(),
+            week: exception_req.week,
+            action: exception_req.action.as_str().to_string(),
+            weekday: exception_req.weekday,
+            start_time: exception_req.start_time.clone(),
+            end_time: exception_req.end_time.clone(),
+            location: exception_req.location.clone(),
+            note: exception_req.note.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        state.exceptions.push(exception.clone());
+        state.publish_exception_course(&self.events, &exception.course_id);
+        Ok(exception)
+    }
+
+    fn update_exception(
+        &self,
+        exception_id: &str,
+        update_req: &UpdateCourseExceptionRequest,
+    ) -> Result<Option<CourseException>, StorageError> {
+        let mut state = self.lock();
+        let Some(existing) = state.exceptions.iter().find(|e| e.id == exception_id) else {
+            return Ok(None);
+        };
+        if let Some(week) = update_req.week {
+            let course_id = existing.course_id.clone();
+            state.check_exception(exception_id, &course_id, week)?;
+        }
+
+        let Some(exception) = state.exceptions.iter_mut().find(|e| e.id == exception_id) else {
+            return Ok(None);
+        };
+        if let Some(week) = update_req.week {
+            exception.week = week;
+        }
+        // 与 SQLite 后端一致：修改 action 时调整内容整体替换
+        if let Some(action) = update_req.action {
+            exception.action = action.as_str().to_string();
+            exception.weekday = update_req.weekday;
+            exception.start_time = update_req.start_time.clone();
+            exception.end_time = update_req.end_time.clone();
+            exception.location = update_req.location.clone();
+        } else {
+            if let Some(weekday) = update_req.weekday {
+                exception.weekday = Some(weekday);
+            }
+            if let Some(start_time) = &update_req.start_time {
+                exception.start_time = Some(start_time.clone());
+            }
+            if let Some(end_time) = &update_req.end_time {
+                exception.end_time = Some(end_time.clone());
+            }
+            if let Some(location) = &update_req.location {
+                exception.location = Some(location.clone());
+            }
+        }
+        if let Some(note) = &update_req.note {
+            exception.note = Some(note.clone());
+        }
+        exception.updated_at = Utc::now().naive_utc();
+        let exception = exception.clone();
+        state.publish_exception_course(&self.events, &exception.course_id);
+        Ok(Some(exception))
+    }
+
+    fn delete_exception(&self, exception_id: &str) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let Some(index) = state.exceptions.iter().position(|e| e.id == exception_id) else {
+            return Ok(false);
+        };
+        let exception = state.exceptions.remove(index);
+        state.publish_exception_course(&self.events, &exception.course_id);
+        Ok(true)
+    }
+
+    fn list_periods(&self) -> Result<Vec<Period>, StorageError> {
+        let mut periods = self.lock().periods.clone();
+        periods.sort_by(|a, b| {
+            (&a.semester_id, &a.campus, a.period_index).cmp(&(
+                &b.semester_id,
+                &b.campus,
+                b.period_index,
+            ))
+        });
+        Ok(periods)
+    }
+
+    fn insert_period(
+        &self,
+        scope: &CourseScope,
+        period_req: &CreatePeriodRequest,
+    ) -> Result<Period, StorageError> {
+        let mut state = self.lock();
+        state.check_semester(&period_req.semester_id)?;
+        state.check_period_unique(
+            "",
+            &period_req.semester_id,
+            &period_req.campus,
+            period_req.period_index,
+        )?;
+
+        let now = Utc::now().naive_utc();
+        let period = Period {
+            id: Uuid::new_v4().to_string(),
+            semester_id: period_req.semester_id.clone(),
+            campus: period_req.campus.clone(),
+            period_index: period_req.period_index,
+            start_time: period_req.start_time.clone(),
+            end_time: period_req.end_time.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        state.periods.push(period.clone());
+        state.resync_period_times_and_publish(scope, &self.events);
+        Ok(period)
+    }
+
+    fn update_period(
+        &self,
+        scope: &CourseScope,
+        period_id: &str,
+        update_req: &UpdatePeriodRequest,
+    ) -> Result<Option<Period>, StorageError> {
+        let mut state = self.lock();
+        let Some(existing) = state.periods.iter().find(|p| p.id == period_id).cloned() else {
+            return Ok(None);
+        };
+        if let Some(period_index) = update_req.period_index {
+            state.check_period_unique(
+                period_id,
+                &existing.semester_id,
+                &existing.campus,
+                period_index,
+            )?;
+        }
+
+        let Some(period) = state.periods.iter_mut().find(|p| p.id == period_id) else {
+            return Ok(None);
+        };
+        if let Some(period_index) = update_req.period_index {
+            period.period_index = period_index;
+        }
+        if let Some(start_time) = &update_req.start_time {
+            period.start_time = start_time.clone();
+        }
+        if let Some(end_time) = &update_req.end_time {
+            period.end_time = end_time.clone();
+        }
+        period.updated_at = Utc::now().naive_utc();
+
+        let updated = period.clone();
+        state.resync_period_times_and_publish(scope, &self.events);
+        Ok(Some(updated))
+    }
+
+    fn delete_period(&self, scope: &CourseScope, period_id: &str) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let count = state.periods.len();
+        state.periods.retain(|p| p.id != period_id);
+        if state.periods.len() == count {
+            return Ok(false);
+        }
+        state.resync_period_times_and_publish(scope, &self.events);
+        Ok(true)
+    }
+
+    fn list_reminder_courses(&self) -> Result<Vec<CourseResponse>, StorageError> {
+        let state = self.lock();
+        Ok(state
+            .courses
+            .iter()
+            .filter(|c| {
+                state
+                    .timetables
+                    .iter()
+                    .any(|t| t.is_default && c.timetable_id.as_deref() == Some(t.id.as_str()))
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn list_course_reminders(&self) -> Result<Vec<CourseReminder>, StorageError> {
+        Ok(self.lock().course_reminders.clone())
+    }
+
+    fn get_course_reminder(&self, course_id: &str) -> Result<Option<CourseReminder>, StorageError> {
+        Ok(self
+            .lock()
+            .course_reminders
+            .iter()
+            .find(|r| r.course_id == course_id)
+            .cloned())
+    }
+
+    fn upsert_course_reminder(
+        &self,
+        reminder: &NewCourseReminder,
+    ) -> Result<CourseReminder, StorageError> {
+        let mut state = self.lock();
+        if !state.courses.iter().any(|c| c.id == reminder.course_id) {
+            return Err(foreign_key_violation("course does not exist"));
+        }
+
+        let now = Utc::now().naive_utc();
+        let existing = state
+            .course_reminders
+            .iter_mut()
+            .find(|r| r.course_id == reminder.course_id);
+        let course_reminder = match existing {
+            Some(existing) => {
+                existing.enabled = reminder.enabled;
+                existing.minutes_before = reminder.minutes_before;
+                existing.updated_at = now;
+                existing.clone()
+            }
+            None => {
+                let course_reminder = CourseReminder {
+                    course_id: reminder.course_id.clone(),
+                    enabled: reminder.enabled,
+                    minutes_before: reminder.minutes_before,
+                    created_at: now,
+                    updated_at: now,
+                };
+                state.course_reminders.push(course_reminder.clone());
+                course_reminder
+            }
+        };
+        debug!(
+            "⏰ 课程提醒设置已保存 (课程ID: {})",
+            course_reminder.course_id
+        );
+        Ok(course_reminder)
+    }
+
+    fn delete_course_reminder(&self, course_id: &str) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let count = state.course_reminders.len();
+        state.course_reminders.retain(|r| r.course_id != course_id);
+        Ok(state.course_reminders.len() < count)
+    }
+
+    fn reminder_delivered(
+        &self,
+        course_id: &str,
+        starts_at: NaiveDateTime,
+    ) -> Result<bool, StorageError> {
+        Ok(self
+            .lock()
+            .reminder_deliveries
+            .contains(&(course_id.to_string(), starts_at)))
+    }
+
+    fn record_reminder_delivery(
+        &self,
+        course_id: &str,
+        starts_at: NaiveDateTime,
+    ) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        if !state.courses.iter().any(|c| c.id == course_id) {
+            return Err(foreign_key_violation("course does not exist"));
+        }
+        Ok(state
+            .reminder_deliveries
+            .insert((course_id.to_string(), starts_at)))
+    }
 }
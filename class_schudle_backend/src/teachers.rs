@@ -0,0 +1,46 @@
+use crate::models::Teacher;
+
+// 忽略空白和大小写后的姓名，用于识别同一位教师（"张老师"、"张 老师"）
+pub fn name_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// 按姓名查找教师，同名教师不止一位时无法确定，返回 None
+pub fn find_by_name<'a>(teachers: &'a [Teacher], name: &str) -> Option<&'a Teacher> {
+    let key = name_key(name);
+    if key.is_empty() {
+        return None;
+    }
+
+    let mut matches = teachers.iter().filter(|t| name_key(&t.name) == key);
+    match (matches.next(), matches.next()) {
+        (Some(teacher), None) => Some(teacher),
+        _ => None,
+    }
+}
+
+// 关联课程的教师：指定 teacher_id 时 teacher 取教师姓名，否则按 teacher 匹配已有教师
+// teacher_id 指向的教师不存在时返回该 ID
+pub fn link_teacher(
+    teachers: &[Teacher],
+    teacher: &mut Option<String>,
+    teacher_id: &mut Option<String>,
+) -> Result<(), String> {
+    if let Some(id) = teacher_id {
+        let linked = teachers.iter().find(|t| &t.id == id).ok_or(id.clone())?;
+        *teacher = Some(linked.name.clone());
+        return Ok(());
+    }
+
+    if let Some(linked) = teacher
+        .as_deref()
+        .and_then(|name| find_by_name(teachers, name))
+    {
+        *teacher = Some(linked.name.clone());
+        *teacher_id = Some(linked.id.clone());
+    }
+    Ok(())
+}
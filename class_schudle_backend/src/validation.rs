@@ -0,0 +1,662 @@
+use chrono::NaiveTime;
+use serde::Serialize;
+
+use crate::models::{
+    parse_time, CommonFreeRequest, CourseResponse, ExceptionAction, FreeSlotQuery, OverrideKind,
+    Period, Room, Semester, Teacher,
+};
+use crate::periods;
+use crate::reminders::MAX_MINUTES_BEFORE;
+use crate::rooms;
+use crate::teachers;
+use crate::week_rule::parse_week_rule;
+
+// 单个字段的校验错误，field 为请求体中的字段路径（如 "courses[2].weekday"）
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+// 收集字段错误，prefix 用于批量请求中标识是第几门课程
+struct FieldErrors<'a> {
+    prefix: &'a str,
+    errors: Vec<FieldError>,
+}
+
+impl FieldErrors<'_> {
+    fn push(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: format!("{}{}", self.prefix, field),
+            message: message.into(),
+        });
+    }
+}
+
+// 指定了周次规则时由规则生成周次，随后去重并升序排列
+pub fn resolve_weeks(
+    week_rule: Option<&str>,
+    weeks: &mut Vec<i32>,
+    prefix: &str,
+) -> Result<(), FieldError> {
+    if let Some(week_rule) = week_rule {
+        *weeks = parse_week_rule(week_rule).map_err(|message| FieldError {
+            field: format!("{}week_rule", prefix),
+            message,
+        })?;
+    }
+    weeks.sort_unstable();
+    weeks.dedup();
+    Ok(())
+}
+
+// 严格解析 "HH:MM:SS"，不接受省略秒或不补零的写法
+fn parse_strict_time(value: &str) -> Option<NaiveTime> {
+    if value.len() != 8 {
+        return None;
+    }
+    NaiveTime::parse_from_str(value, "%H:%M:%S").ok()
+}
+
+fn is_hex_color(value: &str) -> bool {
+    value.len() == 7 && value.starts_with('#') && value[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// 校验一门课程（新建请求或合并了更新内容后的课程）
+// semester 为课程所属学期，用于检查周次上限；为 None 时只检查周次为正数
+pub fn validate_course(
+    course: &CourseResponse,
+    semester: Option<&Semester>,
+    prefix: &str,
+) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix,
+        errors: Vec::new(),
+    };
+
+    if course.name.trim().is_empty() {
+        errors.push("name", "must not be empty");
+    }
+
+    if !(1..=7).contains(&course.weekday) {
+        errors.push("weekday", "must be between 1 (Monday) and 7 (Sunday)");
+    }
+
+    // 按节次定义的课程时间由节次表推导，节次缺失时已由 apply_period_times 报告
+    if course.start_period.is_none() {
+        let start = parse_strict_time(&course.start_time);
+        if start.is_none() {
+            errors.push("start_time", "must be in HH:MM:SS format");
+        }
+        let end = parse_strict_time(&course.end_time);
+        if end.is_none() {
+            errors.push("end_time", "must be in HH:MM:SS format");
+        }
+        if let (Some(start), Some(end)) = (start, end) {
+            if start >= end {
+                errors.push("end_time", "must be later than start_time");
+            }
+        }
+    }
+
+    match (course.start_period, course.end_period) {
+        (Some(start_period), _) if start_period < 1 => {
+            errors.push("start_period", "must be at least 1")
+        }
+        (Some(start_period), Some(end_period)) if end_period < start_period => {
+            errors.push("end_period", "must not be earlier than start_period")
+        }
+        (None, Some(_)) => errors.push("end_period", "requires start_period"),
+        _ => {}
+    }
+
+    if course.weeks.is_empty() {
+        errors.push("weeks", "must contain at least one week");
+    }
+    for week in &course.weeks {
+        match semester {
+            Some(semester) if !semester.contains_week(*week) => errors.push(
+                "weeks",
+                format!(
+                    "week {} is outside semester {} (1-{})",
+                    week, semester.name, semester.total_weeks
+                ),
+            ),
+            None if *week < 1 => errors.push("weeks", format!("week {} must be positive", week)),
+            _ => {}
+        }
+    }
+
+    if let Some(color) = &course.color {
+        if !is_hex_color(color) {
+            errors.push("color", "must be a #RRGGBB hex colour");
+        }
+    }
+
+    errors.errors
+}
+
+// 课程所属学期：显式指定的学期，否则为当前学期
+// 指定的学期不存在时记录字段错误
+pub fn course_semester<'a>(
+    course: &CourseResponse,
+    semesters: &'a [Semester],
+    prefix: &str,
+    errors: &mut Vec<FieldError>,
+) -> Option<&'a Semester> {
+    match &course.semester_id {
+        Some(semester_id) => {
+            let semester = semesters.iter().find(|s| &s.id == semester_id);
+            if semester.is_none() {
+                errors.push(FieldError {
+                    field: format!("{}semester_id", prefix),
+                    message: format!("semester {} does not exist", semester_id),
+                });
+            }
+            semester
+        }
+        None => semesters.iter().find(|s| s.is_active),
+    }
+}
+
+// 按节次定义的课程：补全 end_period 并由节次表推导上课时间
+pub fn apply_period_times(
+    all_periods: &[Period],
+    course: &mut CourseResponse,
+    prefix: &str,
+) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix,
+        errors: Vec::new(),
+    };
+
+    let Some(start_period) = course.start_period else {
+        return errors.errors;
+    };
+    let end_period = *course.end_period.get_or_insert(start_period);
+    if start_period < 1 || end_period < start_period {
+        // 范围错误由 validate_course 报告
+        return errors.errors;
+    }
+
+    match periods::period_times(all_periods, course, start_period, end_period) {
+        Ok((start_time, end_time)) => {
+            course.start_time = start_time;
+            course.end_time = end_time;
+        }
+        Err(missing) => {
+            let field = if missing == start_period {
+                "start_period"
+            } else {
+                "end_period"
+            };
+            errors.push(field, format!("period {} is not defined", missing));
+        }
+    }
+    errors.errors
+}
+
+// 关联课程的教师，teacher_id 指向的教师不存在时报告字段错误
+pub fn link_course_teacher(
+    all_teachers: &[Teacher],
+    course: &mut CourseResponse,
+    prefix: &str,
+) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix,
+        errors: Vec::new(),
+    };
+
+    if let Err(missing) =
+        teachers::link_teacher(all_teachers, &mut course.teacher, &mut course.teacher_id)
+    {
+        errors.push("teacher_id", format!("teacher {} does not exist", missing));
+    }
+    errors.errors
+}
+
+// 关联课程的教室，room_id 指向的教室不存在时报告字段错误
+pub fn link_course_room(
+    all_rooms: &[Room],
+    course: &mut CourseResponse,
+    prefix: &str,
+) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix,
+        errors: Vec::new(),
+    };
+
+    if let Err(missing) = rooms::link_room(all_rooms, course) {
+        errors.push("room_id", format!("room {} does not exist", missing));
+    }
+    errors.errors
+}
+
+pub fn validate_room(
+    building: Option<&str>,
+    room_number: Option<&str>,
+    capacity: Option<i32>,
+) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    if matches!(building, Some(building) if building.trim().is_empty()) {
+        errors.push("building", "must not be empty");
+    }
+    if matches!(room_number, Some(room_number) if room_number.trim().is_empty()) {
+        errors.push("room_number", "must not be empty");
+    }
+    if matches!(capacity, Some(capacity) if capacity < 1) {
+        errors.push("capacity", "must be at least 1");
+    }
+
+    errors.errors
+}
+
+// 按星期、周次和时间段查询时的条件
+pub fn validate_slot(
+    weekday: i32,
+    week: Option<i32>,
+    start: NaiveTime,
+    end: NaiveTime,
+) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    if !(1..=7).contains(&weekday) {
+        errors.push("weekday", "must be between 1 (Monday) and 7 (Sunday)");
+    }
+    if matches!(week, Some(week) if week < 1) {
+        errors.push("week", "must be at least 1");
+    }
+    if start >= end {
+        errors.push("end", "must be later than start");
+    }
+
+    errors.errors
+}
+
+// 校验空闲时段查询，返回统计的时间范围 between（默认 08:00-22:00）
+// 有学期时周次不能超过学期总周数
+pub fn validate_free_slots(
+    query: &FreeSlotQuery,
+    semester: Option<&Semester>,
+) -> Result<(NaiveTime, NaiveTime), Vec<FieldError>> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    match (query.week, semester) {
+        (Some(week), _) if week < 1 => errors.push("week", "must be at least 1"),
+        (Some(week), Some(semester)) if week > semester.total_weeks => errors.push(
+            "week",
+            format!(
+                "must not exceed the semester's {} weeks",
+                semester.total_weeks
+            ),
+        ),
+        _ => {}
+    }
+    if matches!(query.weekday, Some(weekday) if !(1..=7).contains(&weekday)) {
+        errors.push("weekday", "must be between 1 (Monday) and 7 (Sunday)");
+    }
+    if query.min_minutes < 0 {
+        errors.push("min_minutes", "must not be negative");
+    }
+
+    match parse_between(query.between.as_deref(), &mut errors) {
+        Some(range) if errors.errors.is_empty() => Ok(range),
+        _ => Err(errors.errors),
+    }
+}
+
+// 校验共同空闲时段请求：至少一个课表或分享令牌，周次范围不超过 53 周
+pub fn validate_common_free(
+    request: &CommonFreeRequest,
+) -> Result<(NaiveTime, NaiveTime), Vec<FieldError>> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    if request.timetable_ids.is_empty() && request.share_tokens.is_empty() {
+        errors.push(
+            "timetable_ids",
+            "at least one timetable id or share token is required",
+        );
+    }
+    if request.from_week < 1 {
+        errors.push("from_week", "must be at least 1");
+    }
+    if request.to_week < request.from_week {
+        errors.push("to_week", "must not be earlier than from_week");
+    } else if i64::from(request.to_week) - i64::from(request.from_week) >= 53 {
+        errors.push("to_week", "week range must not exceed 53 weeks");
+    }
+    if request.min_minutes < 0 {
+        errors.push("min_minutes", "must not be negative");
+    }
+
+    match parse_between(request.between.as_deref(), &mut errors) {
+        Some(range) if errors.errors.is_empty() => Ok(range),
+        _ => Err(errors.errors),
+    }
+}
+
+// 解析统计的时间范围 "HH:MM-HH:MM"，默认 08:00-22:00
+fn parse_between(
+    between: Option<&str>,
+    errors: &mut FieldErrors,
+) -> Option<(NaiveTime, NaiveTime)> {
+    let range = between
+        .unwrap_or("08:00-22:00")
+        .split_once('-')
+        .and_then(|(start, end)| Some((parse_time(start.trim())?, parse_time(end.trim())?)));
+    match range {
+        Some((start, end)) if start < end => Some((start, end)),
+        Some(_) => {
+            errors.push("between", "end must be later than start");
+            None
+        }
+        None => {
+            errors.push("between", "must be in HH:MM-HH:MM format");
+            None
+        }
+    }
+}
+
+// 用户名 3~32 位，只能包含字母、数字和 "_.-"；密码至少 8 位
+pub fn validate_registration(username: &str, password: &str) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    let username_len = username.chars().count();
+    if !(3..=32).contains(&username_len) {
+        errors.push("username", "must be between 3 and 32 characters");
+    } else if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+    {
+        errors.push(
+            "username",
+            "may only contain letters, digits, '_', '.' and '-'",
+        );
+    }
+    if password.chars().count() < 8 {
+        errors.push("password", "must be at least 8 characters");
+    }
+
+    errors.errors
+}
+
+pub fn validate_semester(name: Option<&str>, total_weeks: Option<i32>) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    if matches!(name, Some(name) if name.trim().is_empty()) {
+        errors.push("name", "must not be empty");
+    }
+    if total_weeks.is_some_and(|total_weeks| total_weeks < 1) {
+        errors.push("total_weeks", "must be at least 1");
+    }
+
+    errors.errors
+}
+
+pub fn validate_teacher(name: Option<&str>, email: Option<&str>) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    if matches!(name, Some(name) if name.trim().is_empty()) {
+        errors.push("name", "must not be empty");
+    }
+    if let Some(email) = email {
+        let valid = email
+            .split_once('@')
+            .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'));
+        if !valid {
+            errors.push("email", "must be a valid email address");
+        }
+    }
+
+    errors.errors
+}
+
+// is_default 只能设为 true：默认课表由设置另一课表为默认来更换，保证每个用户总有一个默认课表
+pub fn validate_timetable(name: Option<&str>, is_default: Option<bool>) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    if matches!(name, Some(name) if name.trim().is_empty()) {
+        errors.push("name", "must not be empty");
+    }
+    if is_default == Some(false) {
+        errors.push(
+            "is_default",
+            "can only be set to true; make another timetable the default instead",
+        );
+    }
+
+    errors.errors
+}
+
+// 有效期上限约十年，避免计算过期时间时溢出
+pub fn validate_share_link(expires_in_days: Option<i64>) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    if expires_in_days.is_some_and(|days| !(1..=3650).contains(&days)) {
+        errors.push("expires_in_days", "must be between 1 and 3650");
+    }
+
+    errors.errors
+}
+
+pub fn validate_course_reminder(minutes_before: Option<i32>) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    if minutes_before.is_some_and(|minutes| !(0..=MAX_MINUTES_BEFORE).contains(&minutes)) {
+        errors.push(
+            "minutes_before",
+            format!("must be between 0 and {}", MAX_MINUTES_BEFORE),
+        );
+    }
+
+    errors.errors
+}
+
+pub fn validate_period(
+    period_index: Option<i32>,
+    start_time: Option<&str>,
+    end_time: Option<&str>,
+) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    if matches!(period_index, Some(period_index) if period_index < 1) {
+        errors.push("period_index", "must be at least 1");
+    }
+
+    let start = start_time.map(parse_strict_time);
+    if matches!(start, Some(None)) {
+        errors.push("start_time", "must be in HH:MM:SS format");
+    }
+    let end = end_time.map(parse_strict_time);
+    if matches!(end, Some(None)) {
+        errors.push("end_time", "must be in HH:MM:SS format");
+    }
+    if let (Some(Some(start)), Some(Some(end))) = (start, end) {
+        if start >= end {
+            errors.push("end_time", "must be later than start_time");
+        }
+    }
+
+    errors.errors
+}
+
+// 调休需指定星期几，自定义调整需同时指定周次与星期几，停课不带两者
+pub fn validate_override(
+    kind: OverrideKind,
+    weekday: Option<i32>,
+    week: Option<i32>,
+) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    match (kind, weekday, week) {
+        (OverrideKind::Cancelled, Some(_), _) => {
+            errors.push("weekday", "must not be set for a cancelled day")
+        }
+        (OverrideKind::Swap | OverrideKind::Custom, None, _) => {
+            errors.push("weekday", format!("is required for kind {}", kind.as_str()))
+        }
+        _ => {}
+    }
+    match (kind, week) {
+        (OverrideKind::Cancelled | OverrideKind::Swap, Some(_)) => errors.push(
+            "week",
+            format!("must not be set for kind {}", kind.as_str()),
+        ),
+        (OverrideKind::Custom, None) => errors.push("week", "is required for kind custom"),
+        _ => {}
+    }
+
+    if matches!(weekday, Some(weekday) if !(1..=7).contains(&weekday)) {
+        errors.push("weekday", "must be between 1 (Monday) and 7 (Sunday)");
+    }
+    if matches!(week, Some(week) if week < 1) {
+        errors.push("week", "must be at least 1");
+    }
+
+    errors.errors
+}
+
+// 单次调整的内容，校验时与已有调整合并
+pub struct ExceptionFields<'a> {
+    pub week: i32,
+    pub action: ExceptionAction,
+    pub weekday: Option<i32>,
+    pub start_time: Option<&'a str>,
+    pub end_time: Option<&'a str>,
+    pub location: Option<&'a str>,
+}
+
+// 调整的周次须是课程上课的周次；停课不带调整内容，调课至少修改一项
+// 只改开始或结束时间之一时，与课程原来的另一端比较先后
+pub fn validate_exception(course: &CourseResponse, fields: &ExceptionFields) -> Vec<FieldError> {
+    let mut errors = FieldErrors {
+        prefix: "",
+        errors: Vec::new(),
+    };
+
+    if !course.weeks.contains(&fields.week) {
+        errors.push(
+            "week",
+            format!(
+                "course {} has no class in week {}",
+                course.name, fields.week
+            ),
+        );
+    }
+
+    let has_details = fields.weekday.is_some()
+        || fields.start_time.is_some()
+        || fields.end_time.is_some()
+        || fields.location.is_some();
+    match fields.action {
+        ExceptionAction::Cancel if has_details => errors.push(
+            "action",
+            "cancel must not set weekday, start_time, end_time or location",
+        ),
+        ExceptionAction::Reschedule if !has_details => errors.push(
+            "action",
+            "reschedule requires at least one of weekday, start_time, end_time or location",
+        ),
+        _ => {}
+    }
+
+    if matches!(fields.weekday, Some(weekday) if !(1..=7).contains(&weekday)) {
+        errors.push("weekday", "must be between 1 (Monday) and 7 (Sunday)");
+    }
+
+    let start = fields.start_time.map(parse_strict_time);
+    if matches!(start, Some(None)) {
+        errors.push("start_time", "must be in HH:MM:SS format");
+    }
+    let end = fields.end_time.map(parse_strict_time);
+    if matches!(end, Some(None)) {
+        errors.push("end_time", "must be in HH:MM:SS format");
+    }
+    let start = start.unwrap_or_else(|| parse_strict_time(&course.start_time));
+    let end = end.unwrap_or_else(|| parse_strict_time(&course.end_time));
+    if let (Some(start), Some(end)) = (start, end) {
+        if start >= end {
+            errors.push("end_time", "must be later than start_time");
+        }
+    }
+
+    if matches!(fields.location, Some(location) if location.trim().is_empty()) {
+        errors.push("location", "must not be empty");
+    }
+
+    errors.errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn common_free(from_week: i32, to_week: i32) -> CommonFreeRequest {
+        CommonFreeRequest {
+            timetable_ids: vec!["t1".to_string()],
+            share_tokens: Vec::new(),
+            from_week,
+            to_week,
+            min_minutes: 0,
+            between: None,
+        }
+    }
+
+    fn fields(errors: Vec<FieldError>) -> Vec<String> {
+        errors.into_iter().map(|e| e.field).collect()
+    }
+
+    #[test]
+    fn common_free_accepts_up_to_53_weeks() {
+        assert!(validate_common_free(&common_free(1, 53)).is_ok());
+        let errors = validate_common_free(&common_free(1, 54)).unwrap_err();
+        assert_eq!(fields(errors), vec!["to_week"]);
+    }
+
+    #[test]
+    fn common_free_rejects_extreme_week_ranges() {
+        // 差值超出 i32 范围时不能溢出
+        let errors = validate_common_free(&common_free(i32::MIN, i32::MAX)).unwrap_err();
+        assert_eq!(fields(errors), vec!["from_week", "to_week"]);
+
+        let errors = validate_common_free(&common_free(i32::MAX, i32::MIN)).unwrap_err();
+        assert_eq!(fields(errors), vec!["to_week"]);
+    }
+}
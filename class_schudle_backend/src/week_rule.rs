@@ -0,0 +1,200 @@
+// 周次规则：用 "1-16"、"1-17单"、"2-16双"、"1-8,10-16" 这样的字符串描述上课周次
+
+// 规则中允许的最大周次，一年最多 53 周
+pub const MAX_WEEK: i32 = 53;
+
+// 解析周次规则，返回去重并升序排列的周次
+// 各段以逗号分隔，每段为单个周次或 "起-止" 范围，范围后可跟 "单"/"双" 只取奇数/偶数周，可带 "周" 字
+pub fn parse_week_rule(rule: &str) -> Result<Vec<i32>, String> {
+    let mut weeks = Vec::new();
+
+    for segment in rule.split([',', '，']) {
+        let segment = segment.trim();
+        if segment.is_empty() {
+            continue;
+        }
+
+        let mut body = segment.trim_end_matches('周');
+        let parity = if let Some(rest) = body.strip_suffix('单') {
+            body = rest;
+            Some(1)
+        } else if let Some(rest) = body.strip_suffix('双') {
+            body = rest;
+            Some(0)
+        } else {
+            None
+        };
+        let body = body.trim().trim_end_matches('周');
+
+        let (start, end) = match body.split_once(['-', '~']) {
+            Some((start, end)) => (parse_week(start, segment)?, parse_week(end, segment)?),
+            None => {
+                let week = parse_week(body, segment)?;
+                (week, week)
+            }
+        };
+        if start > end {
+            return Err(format!(
+                "invalid week range '{}': start is after end",
+                segment
+            ));
+        }
+
+        weeks.extend((start..=end).filter(|week| parity.is_none_or(|parity| week % 2 == parity)));
+    }
+
+    if weeks.is_empty() {
+        return Err(format!("week rule '{}' contains no weeks", rule));
+    }
+
+    weeks.sort_unstable();
+    weeks.dedup();
+    Ok(weeks)
+}
+
+fn parse_week(value: &str, segment: &str) -> Result<i32, String> {
+    match value.trim().trim_start_matches('第').parse::<i32>() {
+        Ok(week) if week > MAX_WEEK => Err(format!(
+            "week {} in '{}' exceeds the maximum of {}",
+            week, segment, MAX_WEEK
+        )),
+        Ok(week) if week >= 1 => Ok(week),
+        _ => Err(format!("invalid week '{}' in '{}'", value.trim(), segment)),
+    }
+}
+
+// 将周次格式化为最简规则字符串，parse_week_rule 可还原出相同的周次
+// 连续周次合并为 "起-止"，三个及以上间隔一周的周次合并为 "起-止单"/"起-止双"
+pub fn format_week_rule(weeks: &[i32]) -> String {
+    let mut sorted = weeks.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let run_length = |start: usize, step: i32| {
+        let mut end = start;
+        while end + 1 < sorted.len() && sorted[end + 1] == sorted[end] + step {
+            end += 1;
+        }
+        end - start + 1
+    };
+
+    let mut segments = Vec::new();
+    let mut i = 0;
+    while i < sorted.len() {
+        let consecutive = run_length(i, 1);
+        let alternate = run_length(i, 2);
+        let start = sorted[i];
+
+        if alternate >= 3 && alternate > consecutive {
+            let end = sorted[i + alternate - 1];
+            let parity = if start % 2 == 1 { "单" } else { "双" };
+            segments.push(format!("{}-{}{}", start, end, parity));
+            i += alternate;
+        } else if consecutive >= 2 {
+            segments.push(format!("{}-{}", start, sorted[i + consecutive - 1]));
+            i += consecutive;
+        } else {
+            segments.push(start.to_string());
+            i += 1;
+        }
+    }
+
+    segments.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_common_rules() {
+        assert_eq!(
+            parse_week_rule("1-16").unwrap(),
+            (1..=16).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            parse_week_rule("1-17单").unwrap(),
+            vec![1, 3, 5, 7, 9, 11, 13, 15, 17]
+        );
+        assert_eq!(
+            parse_week_rule("2-16双").unwrap(),
+            vec![2, 4, 6, 8, 10, 12, 14, 16]
+        );
+        assert_eq!(
+            parse_week_rule("1-8,10-16").unwrap(),
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 10, 11, 12, 13, 14, 15, 16]
+        );
+    }
+
+    #[test]
+    fn parses_loose_variants() {
+        assert_eq!(parse_week_rule("第1-4周").unwrap(), vec![1, 2, 3, 4]);
+        assert_eq!(parse_week_rule("1-7单周").unwrap(), vec![1, 3, 5, 7]);
+        assert_eq!(parse_week_rule(" 3 ， 1-2 ,3").unwrap(), vec![1, 2, 3]);
+        assert_eq!(parse_week_rule("2-7单").unwrap(), vec![3, 5, 7]);
+        assert_eq!(parse_week_rule("5").unwrap(), vec![5]);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert!(parse_week_rule("").is_err());
+        assert!(parse_week_rule("0-4").is_err());
+        assert!(parse_week_rule("8-4").is_err());
+        assert!(parse_week_rule("a-b").is_err());
+        assert!(parse_week_rule("2-2单").is_err());
+    }
+
+    #[test]
+    fn rejects_weeks_above_maximum() {
+        assert_eq!(parse_week_rule("1-53").unwrap().len(), 53);
+        assert!(parse_week_rule("54").is_err());
+        assert!(parse_week_rule("1-2000000000").is_err());
+        assert!(parse_week_rule("1-99999999999").is_err());
+    }
+
+    #[test]
+    fn formats_canonical_rules() {
+        assert_eq!(format_week_rule(&(1..=16).collect::<Vec<_>>()), "1-16");
+        assert_eq!(format_week_rule(&[1, 3, 5, 7, 9, 11, 13, 15, 17]), "1-17单");
+        assert_eq!(format_week_rule(&[2, 4, 6, 8, 10, 12, 14, 16]), "2-16双");
+        assert_eq!(
+            format_week_rule(&[1, 2, 3, 4, 5, 6, 7, 8, 10, 11, 12, 13, 14, 15, 16]),
+            "1-8,10-16"
+        );
+        assert_eq!(format_week_rule(&[1, 3]), "1,3");
+        assert_eq!(format_week_rule(&[5, 1, 5, 2]), "1-2,5");
+        assert_eq!(format_week_rule(&[]), "");
+    }
+
+    #[test]
+    fn round_trips_rules() {
+        for rule in [
+            "1-16",
+            "1-17单",
+            "2-16双",
+            "1-8,10-16",
+            "1-3,5-9单",
+            "4",
+            "1,3,6-7",
+        ] {
+            let weeks = parse_week_rule(rule).unwrap();
+            assert_eq!(format_week_rule(&weeks), rule);
+        }
+    }
+
+    #[test]
+    fn round_trips_week_lists() {
+        let cases: Vec<Vec<i32>> = vec![
+            vec![1],
+            vec![1, 2],
+            vec![1, 3, 5, 6, 7, 8],
+            vec![2, 4, 6, 7, 9, 11, 13],
+            vec![1, 4, 7, 10],
+            (1..=20).filter(|w| w % 3 != 0).collect(),
+        ];
+        for weeks in cases {
+            let rule = format_week_rule(&weeks);
+            assert_eq!(parse_week_rule(&rule).unwrap(), weeks, "rule {}", rule);
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use log::{debug, error, info, warn};
 use std::fs;
 use std::path::PathBuf;
-use tauri::{Manager, menu::{MenuBuilder, MenuItem, PredefinedMenuItem}, tray::{TrayIconBuilder, TrayIconEvent}};
+use std::sync::Mutex;
+use tauri::{Manager, tray::{TrayIconBuilder, TrayIconEvent}};
 
 #[cfg(target_os = "windows")]
 use windows::Win32::{
@@ -13,6 +14,10 @@ use windows::Win32::{
     },
 };
 
+mod reminders;
+mod schedule;
+mod tray;
+
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -240,6 +245,7 @@ fn quit_app(_app: tauri::AppHandle) -> Result<(), String> {
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
         .invoke_handler(tauri::generate_handler![
             greet,
             show_main_app,
@@ -250,7 +256,10 @@ pub fn run() {
             show_widget,
             hide_widget,
             toggle_widget,
-            quit_app
+            quit_app,
+            reminders::get_reminder_settings,
+            reminders::save_reminder_settings,
+            reminders::snooze_reminder
         ])
         .setup(|app| {
             // 初始化日志，设置默认级别为 info
@@ -259,18 +268,16 @@ pub fn run() {
                 .init();
             info!("应用启动，初始化日志系统");
 
-            // 创建托盘菜单
-            let show_main = MenuItem::with_id(app, "show_main", "显示主窗口", true, None::<&str>)?;
-            let toggle_widget_menu = MenuItem::with_id(app, "toggle_widget", "显示/隐藏小组件", true, None::<&str>)?;
-            let separator = PredefinedMenuItem::separator(app)?;
-            let quit = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
+            // 加载提醒设置并启动后台提醒
+            let reminder_settings = reminders::load_settings(app.handle());
+            app.manage(Mutex::new(reminders::ReminderState::new(reminder_settings)));
+            reminders::spawn(app.handle().clone());
 
-            let menu = MenuBuilder::new(app)
-                .items(&[&show_main, &toggle_widget_menu, &separator, &quit])
-                .build()?;
+            // 创建托盘菜单，课程信息由 tray::spawn 定时刷新
+            let menu = tray::build_menu(app.handle(), &tray::TrayStatus::default())?;
 
             // 创建托盘图标
-            let _tray = TrayIconBuilder::with_id("main")
+            let _tray = TrayIconBuilder::with_id(tray::TRAY_ID)
                 .icon(app.default_window_icon().unwrap().clone())
                 .menu(&menu)
                 .tooltip("课程表管理系统")
@@ -284,11 +291,19 @@ pub fn run() {
                             info!("托盘菜单：切换小组件");
                             let _ = toggle_widget(app.clone());
                         }
+                        "snooze_reminder" => {
+                            info!("托盘菜单：稍后提醒");
+                            let _ = reminders::snooze_reminder(app.clone(), None, None, None);
+                        }
                         "quit" => {
                             info!("托盘菜单：退出应用");
                             std::process::exit(0);
                         }
-                        _ => {}
+                        id => {
+                            if let Some(course_id) = id.strip_prefix(tray::COURSE_ITEM_PREFIX) {
+                                tray::open_course(app, course_id);
+                            }
+                        }
                     }
                 })
                 .on_tray_icon_event(|tray, event| {
@@ -307,6 +322,7 @@ pub fn run() {
                     }
                 })
                 .build(app)?;
+            tray::spawn(app.handle().clone());
 
             // 启动时恢复小组件位置并设置桌面模式
             let app_handle = app.handle().clone();
@@ -0,0 +1,418 @@
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::schedule::{self, Course};
+
+// 检查提醒的间隔（秒）
+const CHECK_INTERVAL_SECS: u64 = 30;
+// 课程数据的刷新间隔（分钟），日期变化或修改设置时立即刷新
+const REFRESH_INTERVAL_MINUTES: i64 = 5;
+// 发送给前端窗口的提醒事件，主窗口和桌面小组件据此显示可点击的提醒
+pub const REMINDER_EVENT: &str = "course-reminder";
+
+// 提醒设置，保存在应用数据目录的 reminder_settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReminderSettings {
+    pub enabled: bool,
+    pub backend_url: String,
+    pub api_token: Option<String>, // 后端开启 REQUIRE_AUTH 时使用的登录令牌
+    pub minutes_before: i64, // 提前多少分钟提醒
+    pub snooze_minutes: i64, // 稍后提醒的间隔
+    pub dnd_enabled: bool,   // 免打扰时段内不弹出提醒
+    pub dnd_start: String,   // "HH:MM" 格式，可以跨越午夜
+    pub dnd_end: String,
+}
+
+impl Default for ReminderSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            backend_url: "http://localhost:8080".to_string(),
+            api_token: None,
+            minutes_before: 10,
+            snooze_minutes: 5,
+            dnd_enabled: false,
+            dnd_start: "22:00".to_string(),
+            dnd_end: "07:00".to_string(),
+        }
+    }
+}
+
+impl ReminderSettings {
+    fn validate(&self) -> Result<(), String> {
+        if !(0..=24 * 60).contains(&self.minutes_before) {
+            return Err("提前提醒时间需在 0 到 1440 分钟之间".to_string());
+        }
+        if !(1..=120).contains(&self.snooze_minutes) {
+            return Err("稍后提醒间隔需在 1 到 120 分钟之间".to_string());
+        }
+        if schedule::parse_time(&self.dnd_start).is_none()
+            || schedule::parse_time(&self.dnd_end).is_none()
+        {
+            return Err("免打扰时段格式应为 HH:MM".to_string());
+        }
+        Ok(())
+    }
+
+    // 免打扰时段为左闭右开区间，开始时间晚于结束时间时表示跨越午夜，例如 22:00-07:00
+    fn in_dnd(&self, time: NaiveTime) -> bool {
+        if !self.dnd_enabled {
+            return false;
+        }
+        let (Some(start), Some(end)) = (
+            schedule::parse_time(&self.dnd_start),
+            schedule::parse_time(&self.dnd_end),
+        ) else {
+            return false;
+        };
+        if start <= end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+// 某门课程的一次上课
+#[derive(Debug, Clone)]
+pub struct Occurrence {
+    pub course: Course,
+    pub starts_at: NaiveDateTime,
+}
+
+// 提醒事件的内容，starts_at 为 "YYYY-MM-DDTHH:MM:SS" 格式，稍后提醒时原样传回
+#[derive(Debug, Clone, Serialize)]
+pub struct ReminderEvent {
+    pub course_id: String,
+    pub name: String,
+    pub starts_at: String,
+    pub location: Option<String>,
+}
+
+// 提醒状态，由 app.manage 托管，后台循环、托盘和命令共同使用
+#[derive(Default)]
+pub struct ReminderState {
+    settings: ReminderSettings,
+    days: Vec<(NaiveDate, Vec<Course>)>, // 今天和明天的课程
+    loaded_at: Option<NaiveDateTime>,
+    notified: HashSet<(String, NaiveDateTime)>, // 已提醒过的 (课程 ID, 上课时间)
+    reminded: Vec<Occurrence>,                  // 已提醒且尚未开始的课，稍后提醒从中选择
+    snoozed: Vec<(NaiveDateTime, Occurrence)>,  // (再次提醒的时间, 上课)
+}
+
+impl ReminderState {
+    pub fn new(settings: ReminderSettings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    // 今天和明天的课程，各自按开始时间排序
+    pub fn days(&self) -> &[(NaiveDate, Vec<Course>)] {
+        &self.days
+    }
+
+    fn needs_refresh(&self, now: NaiveDateTime) -> bool {
+        match self.loaded_at {
+            Some(loaded_at) => {
+                loaded_at.date() != now.date()
+                    || now - loaded_at >= Duration::minutes(REFRESH_INTERVAL_MINUTES)
+            }
+            None => true,
+        }
+    }
+
+    // 取出到了提醒时间的上课：开课前 minutes_before 分钟内且未提醒过的课，以及到时间的稍后提醒
+    // 免打扰时段内到期的提醒直接丢弃，不会在时段结束后补发
+    fn take_due(&mut self, now: NaiveDateTime) -> Vec<Occurrence> {
+        if !self.settings.enabled {
+            self.snoozed.clear();
+            return Vec::new();
+        }
+
+        let mut due = Vec::new();
+        for (date, courses) in &self.days {
+            for course in courses {
+                let Some(start) = course.start() else {
+                    continue;
+                };
+                let starts_at = date.and_time(start);
+                let remind_at = starts_at - Duration::minutes(self.settings.minutes_before);
+                if remind_at <= now
+                    && now < starts_at
+                    && self.notified.insert((course.id.clone(), starts_at))
+                {
+                    due.push(Occurrence {
+                        course: course.clone(),
+                        starts_at,
+                    });
+                }
+            }
+        }
+
+        let (snoozed_due, snoozed): (Vec<_>, Vec<_>) = self
+            .snoozed
+            .drain(..)
+            .partition(|(fire_at, _)| *fire_at <= now);
+        self.snoozed = snoozed;
+        due.extend(snoozed_due.into_iter().map(|(_, occurrence)| occurrence));
+
+        if !due.is_empty() && self.settings.in_dnd(now.time()) {
+            info!("免打扰时段内，跳过 {} 条课程提醒", due.len());
+            return Vec::new();
+        }
+        // 稍后提醒再次到期的课程移到末尾，成为最近一次提醒
+        self.reminded.retain(|reminded| {
+            reminded.starts_at > now
+                && !due.iter().any(|occurrence| {
+                    occurrence.course.id == reminded.course.id
+                        && occurrence.starts_at == reminded.starts_at
+                })
+        });
+        self.reminded.extend(due.iter().cloned());
+        due
+    }
+
+    // 稍后再次提醒指定的课程，未指定时为最近一次提醒的课程，最晚在上课时提醒
+    fn snooze(
+        &mut self,
+        now: NaiveDateTime,
+        target: Option<(&str, NaiveDateTime)>,
+        minutes: Option<i64>,
+    ) -> Result<NaiveDateTime, String> {
+        let occurrence = self
+            .reminded
+            .iter()
+            .rev()
+            .find(|occurrence| match target {
+                Some((course_id, starts_at)) => {
+                    occurrence.course.id == course_id && occurrence.starts_at == starts_at
+                }
+                None => true,
+            })
+            .cloned()
+            .ok_or_else(|| "没有可以稍后提醒的课程".to_string())?;
+        if occurrence.starts_at <= now {
+            return Err(format!("{} 已经开始", occurrence.course.name));
+        }
+        let minutes = minutes.unwrap_or(self.settings.snooze_minutes).max(1);
+        let fire_at = (now + Duration::minutes(minutes)).min(occurrence.starts_at);
+        self.snoozed.retain(|(_, snoozed)| {
+            snoozed.course.id != occurrence.course.id || snoozed.starts_at != occurrence.starts_at
+        });
+        self.snoozed.push((fire_at, occurrence));
+        Ok(fire_at)
+    }
+}
+
+// 获取提醒设置文件路径
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    fs::create_dir_all(&app_data_dir).map_err(|e| e.to_string())?;
+    Ok(app_data_dir.join("reminder_settings.json"))
+}
+
+// 读取提醒设置，文件不存在或无法解析时使用默认设置
+pub fn load_settings(app: &AppHandle) -> ReminderSettings {
+    let Ok(settings_path) = get_settings_path(app) else {
+        return ReminderSettings::default();
+    };
+    if !settings_path.exists() {
+        info!("提醒设置文件不存在，使用默认设置");
+        return ReminderSettings::default();
+    }
+
+    fs::read_to_string(&settings_path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            warn!("读取提醒设置失败，使用默认设置: {}", e);
+            ReminderSettings::default()
+        })
+}
+
+// 获取提醒设置
+#[tauri::command]
+pub fn get_reminder_settings(state: tauri::State<'_, Mutex<ReminderState>>) -> ReminderSettings {
+    state.lock().unwrap().settings.clone()
+}
+
+// 保存提醒设置，并在下一次检查时重新加载课程
+#[tauri::command]
+pub fn save_reminder_settings(
+    app: AppHandle,
+    state: tauri::State<'_, Mutex<ReminderState>>,
+    settings: ReminderSettings,
+) -> Result<(), String> {
+    // 设置中包含登录令牌，不记录完整内容
+    info!(
+        "保存提醒设置: 启用={}, 后端={}, 提前 {} 分钟",
+        settings.enabled, settings.backend_url, settings.minutes_before
+    );
+    settings.validate()?;
+
+    let settings_path = get_settings_path(&app)?;
+    let content = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    fs::write(&settings_path, content).map_err(|e| {
+        error!("保存提醒设置失败: {}", e);
+        e.to_string()
+    })?;
+
+    let mut state = state.lock().unwrap();
+    state.settings = settings;
+    state.loaded_at = None;
+    debug!("提醒设置已保存到: {:?}", settings_path);
+    Ok(())
+}
+
+// 稍后提醒，course_id 和 starts_at 来自提醒事件，为空时针对最近一次提醒的课程
+// minutes 为空时使用设置中的间隔
+#[tauri::command]
+pub fn snooze_reminder(
+    app: AppHandle,
+    course_id: Option<String>,
+    starts_at: Option<String>,
+    minutes: Option<i64>,
+) -> Result<String, String> {
+    let target = match (&course_id, &starts_at) {
+        (Some(course_id), Some(starts_at)) => {
+            let starts_at = NaiveDateTime::parse_from_str(starts_at, "%Y-%m-%dT%H:%M:%S")
+                .map_err(|e| format!("上课时间格式错误: {}", e))?;
+            Some((course_id.as_str(), starts_at))
+        }
+        _ => None,
+    };
+    let state = app.state::<Mutex<ReminderState>>();
+    let fire_at = state
+        .lock()
+        .unwrap()
+        .snooze(Local::now().naive_local(), target, minutes)
+        .map_err(|e| {
+            warn!("稍后提醒失败: {}", e);
+            e
+        })?;
+    info!("将在 {} 再次提醒", fire_at.format("%H:%M"));
+    Ok(fire_at.format("%H:%M").to_string())
+}
+
+// 启动后台提醒循环，不依赖前端页面，主窗口隐藏时同样会提醒
+pub fn spawn(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let now = Local::now().naive_local();
+            refresh(&app, now).await;
+
+            let due = {
+                let state = app.state::<Mutex<ReminderState>>();
+                let mut state = state.lock().unwrap();
+                state.take_due(now)
+            };
+            for occurrence in due {
+                show_notification(&app, &occurrence, now);
+            }
+        }
+    });
+    info!("课程提醒循环已启动");
+}
+
+// 按需从后端重新加载今天和明天的课程，加载失败时保留上一次的数据
+// 托盘也使用这些课程，因此关闭提醒时同样加载
+async fn refresh(app: &AppHandle, now: NaiveDateTime) {
+    let (backend_url, token) = {
+        let state = app.state::<Mutex<ReminderState>>();
+        let state = state.lock().unwrap();
+        if !state.needs_refresh(now) {
+            return;
+        }
+        let token = state
+            .settings
+            .api_token
+            .clone()
+            .filter(|token| !token.is_empty());
+        (state.settings.backend_url.clone(), token)
+    };
+
+    let today = now.date();
+    let dates = [today, today + Duration::days(1)];
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        dates
+            .iter()
+            .map(|date| {
+                schedule::fetch_day_courses(&backend_url, token.as_deref(), *date)
+                    .map(|courses| (*date, courses))
+            })
+            .collect::<Result<Vec<_>, String>>()
+    })
+    .await;
+
+    let state = app.state::<Mutex<ReminderState>>();
+    let mut state = state.lock().unwrap();
+    match result {
+        Ok(Ok(days)) => {
+            debug!("课程提醒数据已刷新");
+            state.days = days;
+            state
+                .notified
+                .retain(|(_, starts_at)| starts_at.date() >= today);
+        }
+        Ok(Err(e)) => warn!("加载课程失败: {}", e),
+        Err(e) => error!("加载课程任务失败: {}", e),
+    }
+    // 失败时同样等到下一个刷新间隔再重试，避免后端不可用时频繁请求
+    state.loaded_at = Some(now);
+}
+
+// 弹出系统通知：课程名称、开始时间、教室和教师
+fn show_notification(app: &AppHandle, occurrence: &Occurrence, now: NaiveDateTime) {
+    let course = &occurrence.course;
+    let minutes = (occurrence.starts_at - now).num_minutes().max(0);
+    let mut lines = vec![format!(
+        "{} 开始（{} 分钟后）",
+        occurrence.starts_at.format("%H:%M"),
+        minutes
+    )];
+    if let Some(location) = &course.location {
+        lines.push(format!("教室：{}", location));
+    }
+    if let Some(teacher) = &course.teacher {
+        lines.push(format!("教师：{}", teacher));
+    }
+    lines.push("可在桌面小组件、主窗口或托盘菜单中选择“稍后提醒”".to_string());
+
+    info!(
+        "发送课程提醒: {} 于 {} 开始",
+        course.name, occurrence.starts_at
+    );
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title(format!("课程提醒：{}", course.name))
+        .body(lines.join("\n"))
+        .show()
+    {
+        error!("发送系统通知失败: {}", e);
+    }
+
+    // 系统通知在桌面端不支持按钮和点击回调，由前端窗口显示可点击的提醒
+    let event = ReminderEvent {
+        course_id: course.id.clone(),
+        name: course.name.clone(),
+        starts_at: occurrence.starts_at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        location: course.location.clone(),
+    };
+    if let Err(e) = app.emit(REMINDER_EVENT, &event) {
+        error!("通知前端窗口课程提醒失败: {}", e);
+    }
+}
@@ -0,0 +1,101 @@
+use chrono::{Datelike, NaiveDate, NaiveTime};
+use log::debug;
+use serde::Deserialize;
+use std::time::Duration;
+
+// 与后端课程响应对应，只取桌面端需要的字段
+#[derive(Debug, Clone, Deserialize)]
+pub struct Course {
+    pub id: String,
+    pub name: String,
+    pub teacher: Option<String>,
+    pub location: Option<String>,
+    pub weekday: u32,       // 1=周一, ..., 7=周日
+    pub start_time: String, // "HH:MM:SS" 格式
+    pub end_time: String,
+}
+
+impl Course {
+    pub fn start(&self) -> Option<NaiveTime> {
+        parse_time(&self.start_time)
+    }
+
+    pub fn end(&self) -> Option<NaiveTime> {
+        parse_time(&self.end_time)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CourseList {
+    courses: Vec<Course>,
+}
+
+// 后端统一的错误响应体，只取 code
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    code: String,
+}
+
+// 兼容 "HH:MM:SS" 和 "HH:MM"
+pub fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .ok()
+}
+
+// 获取某一天实际上的课，按开始时间排序
+// 优先使用后端按学期周次、调休和调课计算好的日程，后端未设置当前学期时退回按星期筛选全部课程
+// token 为后端要求登录时使用的令牌
+// 使用阻塞请求，需在 spawn_blocking 中调用
+pub fn fetch_day_courses(
+    backend_url: &str,
+    token: Option<&str>,
+    date: NaiveDate,
+) -> Result<Vec<Course>, String> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .build();
+    let base_url = backend_url.trim_end_matches('/');
+    let get = |url: &str| {
+        let request = agent.get(url);
+        match token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    };
+
+    let day_url = format!("{}/api/v1/schedule/date/{}", base_url, date);
+    let mut courses = match get(&day_url).call() {
+        Ok(response) => {
+            let day: CourseList = response.into_json().map_err(|e| e.to_string())?;
+            day.courses
+        }
+        Err(ureq::Error::Status(404, response)) => {
+            // 只有未设置当前学期时才退回，其他 404（如接口不存在）直接报错
+            let no_active_semester = response
+                .into_json::<ErrorBody>()
+                .map(|body| body.code == "NO_ACTIVE_SEMESTER")
+                .unwrap_or(false);
+            if !no_active_semester {
+                return Err(format!("{}: 404 Not Found", day_url));
+            }
+            debug!("后端未设置当前学期，按星期筛选课程");
+            let schedule: CourseList = get(&format!("{}/api/v1/schedule", base_url))
+                .call()
+                .map_err(|e| e.to_string())?
+                .into_json()
+                .map_err(|e| e.to_string())?;
+            let weekday = date.weekday().number_from_monday();
+            schedule
+                .courses
+                .into_iter()
+                .filter(|course| course.weekday == weekday)
+                .collect()
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+
+    courses.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+    debug!("已获取 {} 的 {} 门课程", date, courses.len());
+    Ok(courses)
+}
@@ -0,0 +1,179 @@
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use log::{debug, error, info};
+use std::sync::Mutex;
+use tauri::menu::{Menu, MenuBuilder, MenuItem, PredefinedMenuItem};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::reminders::ReminderState;
+use crate::schedule::Course;
+
+pub const TRAY_ID: &str = "main";
+// 课程菜单项的 ID 前缀，后接课程 ID
+pub const COURSE_ITEM_PREFIX: &str = "course:";
+// 托盘提示和菜单的刷新间隔（秒）
+const REFRESH_INTERVAL_SECS: u64 = 30;
+const APP_TITLE: &str = "课程表管理系统";
+
+// 托盘菜单中的一门课程
+#[derive(Debug, Clone, PartialEq)]
+struct CourseEntry {
+    course_id: String,
+    label: String,
+}
+
+// 当前正在上的课和下一节课
+#[derive(Debug, Default, PartialEq)]
+pub struct TrayStatus {
+    current: Option<CourseEntry>,
+    next: Option<CourseEntry>,
+}
+
+impl TrayStatus {
+    // 根据今天和明天的课程计算，课程按开始时间排序
+    fn from_days(days: &[(NaiveDate, Vec<Course>)], now: NaiveDateTime) -> Self {
+        let mut status = Self::default();
+        for (date, courses) in days {
+            for course in courses {
+                let (Some(start), Some(end)) = (course.start(), course.end()) else {
+                    continue;
+                };
+                let starts_at = date.and_time(start);
+                let ends_at = date.and_time(end);
+
+                if status.current.is_none() && starts_at <= now && now < ends_at {
+                    status.current = Some(CourseEntry {
+                        course_id: course.id.clone(),
+                        label: format!(
+                            "正在上：{}，{} 下课（还有 {}）",
+                            course.name,
+                            ends_at.format("%H:%M"),
+                            format_countdown(ends_at - now)
+                        ),
+                    });
+                }
+                if status.next.is_none() && starts_at > now {
+                    let day = if *date == now.date() { "" } else { "明天 " };
+                    let mut label = format!(
+                        "下一节：{}，{}{} 开始（还有 {}）",
+                        course.name,
+                        day,
+                        starts_at.format("%H:%M"),
+                        format_countdown(starts_at - now)
+                    );
+                    if let Some(location) = &course.location {
+                        label.push_str(&format!("，教室 {}", location));
+                    }
+                    status.next = Some(CourseEntry {
+                        course_id: course.id.clone(),
+                        label,
+                    });
+                }
+            }
+        }
+        status
+    }
+
+    fn tooltip(&self) -> String {
+        let mut lines = vec![APP_TITLE.to_string()];
+        lines.extend(self.current.iter().map(|entry| entry.label.clone()));
+        match &self.next {
+            Some(entry) => lines.push(entry.label.clone()),
+            None => lines.push("今明两天没有更多课程".to_string()),
+        }
+        lines.join("\n")
+    }
+}
+
+// 不足一小时显示分钟，否则显示小时和分钟
+fn format_countdown(duration: chrono::Duration) -> String {
+    let minutes = duration.num_minutes().max(1);
+    if minutes < 60 {
+        format!("{} 分钟", minutes)
+    } else if minutes % 60 == 0 {
+        format!("{} 小时", minutes / 60)
+    } else {
+        format!("{} 小时 {} 分钟", minutes / 60, minutes % 60)
+    }
+}
+
+// 创建托盘菜单：顶部为当前和下一节课程，点击在主窗口中打开，下面是固定的菜单项
+pub fn build_menu(app: &AppHandle, status: &TrayStatus) -> tauri::Result<Menu<tauri::Wry>> {
+    let mut builder = MenuBuilder::new(app);
+    let entries: Vec<&CourseEntry> = status.current.iter().chain(status.next.iter()).collect();
+    if entries.is_empty() {
+        let empty = MenuItem::with_id(
+            app,
+            "no_course",
+            "今明两天没有更多课程",
+            false,
+            None::<&str>,
+        )?;
+        builder = builder.item(&empty);
+    }
+    for entry in entries {
+        let item = MenuItem::with_id(
+            app,
+            format!("{}{}", COURSE_ITEM_PREFIX, entry.course_id),
+            &entry.label,
+            true,
+            None::<&str>,
+        )?;
+        builder = builder.item(&item);
+    }
+
+    let show_main = MenuItem::with_id(app, "show_main", "显示主窗口", true, None::<&str>)?;
+    let toggle_widget_menu =
+        MenuItem::with_id(app, "toggle_widget", "显示/隐藏小组件", true, None::<&str>)?;
+    let snooze = MenuItem::with_id(app, "snooze_reminder", "稍后提醒", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
+
+    builder
+        .item(&PredefinedMenuItem::separator(app)?)
+        .items(&[&show_main, &toggle_widget_menu, &snooze])
+        .item(&PredefinedMenuItem::separator(app)?)
+        .item(&quit)
+        .build()
+}
+
+// 显示主窗口并通知前端打开这门课程
+pub fn open_course(app: &AppHandle, course_id: &str) {
+    info!("托盘菜单：打开课程 {}", course_id);
+    let _ = crate::show_main_app(app.clone());
+    if let Err(e) = app.emit_to("main", "open-course", course_id) {
+        error!("通知主窗口打开课程失败: {}", e);
+    }
+}
+
+// 定时刷新托盘提示和课程菜单，课程数据来自提醒循环加载的今天和明天的课程
+pub fn spawn(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(REFRESH_INTERVAL_SECS));
+        let mut last_status = None;
+        loop {
+            interval.tick().await;
+            let status = {
+                let state = app.state::<Mutex<ReminderState>>();
+                let state = state.lock().unwrap();
+                TrayStatus::from_days(state.days(), Local::now().naive_local())
+            };
+            // 内容没有变化时不重建菜单，避免菜单打开时闪烁
+            if last_status.as_ref() == Some(&status) {
+                continue;
+            }
+            let Some(tray) = app.tray_by_id(TRAY_ID) else {
+                continue;
+            };
+
+            debug!("刷新托盘课程信息: {:?}", status);
+            let _ = tray.set_tooltip(Some(status.tooltip()));
+            match build_menu(&app, &status) {
+                Ok(menu) => {
+                    let _ = tray.set_menu(Some(menu));
+                }
+                Err(e) => error!("创建托盘菜单失败: {}", e),
+            }
+            last_status = Some(status);
+        }
+    });
+}
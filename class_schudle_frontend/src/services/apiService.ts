@@ -2,12 +2,21 @@ interface Course {
   id: string;
   name: string;
   teacher?: string;
+  teacher_id?: string; // 关联教师时 teacher 为教师姓名
+  room_id?: string; // 关联教室时 location 为教室名称
+  owner_id?: string; // 所属用户，未登录时创建的课程为空
+  timetable_id?: string; // 所属课表，/schedule 等接口使用默认课表
   location?: string;
   weekday: number;
   start_time: string;
   end_time: string;
   weeks: number[];
+  week_rule: string; // 周次规则，如 "1-17单"
   color?: string;
+  semester_id?: string;
+  campus?: string;
+  start_period?: number; // 按节次定义时的起止节次
+  end_period?: number;
 }
 
 interface CreateCourseRequest {
@@ -36,6 +45,15 @@ interface Schedule {
   courses: Course[];
 }
 
+interface CurrentWeek {
+  semester_id: string;
+  semester_name: string;
+  date: string; // "YYYY-MM-DD" 格式
+  week: number;
+  total_weeks: number;
+  in_semester: boolean;
+}
+
 interface PushScheduleRequest {
   courses: CreateCourseRequest[];
   replace: boolean;
@@ -106,6 +124,11 @@ class ApiService {
     });
   }
 
+  // 获取当前教学周（以后端当前学期为准）
+  async getCurrentWeek(): Promise<CurrentWeek> {
+    return this.request<CurrentWeek>('/semesters/current/week');
+  }
+
   // 推送课程表（第三方接口）
   async pushSchedule(request: PushScheduleRequest): Promise<Schedule> {
     return this.request<Schedule>('/schedule/push', {
@@ -116,4 +139,4 @@ class ApiService {
 }
 
 export const apiService = new ApiService();
-export type { Course, CreateCourseRequest, UpdateCourseRequest, Schedule, PushScheduleRequest };
+export type { Course, CreateCourseRequest, UpdateCourseRequest, Schedule, PushScheduleRequest, CurrentWeek };
@@ -1,18 +1,28 @@
 import { reactive } from 'vue';
-import { apiService, type Course, type CreateCourseRequest, type UpdateCourseRequest } from '../services/apiService';
+import { apiService, type Course, type CreateCourseRequest, type UpdateCourseRequest, type CurrentWeek } from '../services/apiService';
+import { getCurrentWeek } from '../utils/courseUtils';
 
 interface ScheduleState {
   courses: Course[];
   loading: boolean;
   error: string | null;
+  currentWeek: CurrentWeek | null;
 }
 
 const state = reactive<ScheduleState>({
   courses: [],
   loading: false,
   error: null,
+  currentWeek: null,
 });
 
+// 本地日期 "YYYY-MM-DD"，与后端返回的 date 比较
+function toDateString(date: Date): string {
+  const month = String(date.getMonth() + 1).padStart(2, '0');
+  const day = String(date.getDate()).padStart(2, '0');
+  return `${date.getFullYear()}-${month}-${day}`;
+}
+
 class ScheduleStore {
   get courses() {
     return state.courses;
@@ -26,6 +36,25 @@ class ScheduleStore {
     return state.error;
   }
 
+  // 从后端获取当前教学周，失败时清空，周次改用本地推算
+  async loadCurrentWeek() {
+    try {
+      state.currentWeek = await apiService.getCurrentWeek();
+    } catch (error) {
+      console.warn('获取当前教学周失败，使用本地推算:', error);
+      state.currentWeek = null;
+    }
+  }
+
+  // 指定时刻的教学周：后端数据是当天的就用后端的，否则（离线、跨天未刷新）按本地学期配置推算
+  weekAt(time: Date): number {
+    const current = state.currentWeek;
+    if (current && current.date === toDateString(time)) {
+      return current.week;
+    }
+    return getCurrentWeek(time);
+  }
+
   // 从本地存储加载课程表
   loadFromLocalStorage() {
     try {
@@ -0,0 +1,24 @@
+/**
+ * 课程提醒相关类型定义，与 src-tauri/src/reminders.rs 保持一致
+ */
+
+export interface ReminderSettings {
+  enabled: boolean;
+  backend_url: string;
+  api_token: string | null; // 后端开启 REQUIRE_AUTH 时使用的登录令牌
+  minutes_before: number; // 提前多少分钟提醒
+  snooze_minutes: number; // 稍后提醒的间隔
+  dnd_enabled: boolean; // 免打扰时段内不弹出提醒
+  dnd_start: string; // "HH:MM" 格式，可以跨越午夜
+  dnd_end: string;
+}
+
+// 后台提醒循环发出的 course-reminder 事件
+export interface ReminderEvent {
+  course_id: string;
+  name: string;
+  starts_at: string; // "YYYY-MM-DDTHH:MM:SS" 格式，稍后提醒时原样传回
+  location: string | null;
+}
+
+export const REMINDER_EVENT = 'course-reminder';